@@ -1,14 +1,15 @@
 //! Entities doing things
 
-use rand::{seq::SliceRandom, Rng};
+use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use util::{s4, RngExt};
-use world::{Block, EquippedAt, Power};
+use world::{Block, Environs, EquippedAt, ItemKind, Power};
 
 use crate::{
-    ecs::{ActsNext, Momentum, Voice},
+    ecs::{ActsNext, Momentum, OnHit, Voice},
+    power::Impact,
     prelude::*,
-    ALERT_RADIUS, PHASES_IN_TURN, SHOUT_RADIUS,
+    ALERT_RADIUS, NORMAL_SPEED, PHASES_IN_TURN, SHOUT_RADIUS, THROW_RANGE,
 };
 
 impl Entity {
@@ -50,12 +51,25 @@ impl Entity {
             Pass => self.pass(r, is_direct),
             Bump(dir) => {
                 let dir = modified_dir(dir);
+                self.unlock(r, dir);
                 let succeeded = self.attack_step(r, dir, is_direct);
 
                 if !succeeded {
                     if let Some(loc) =
                         self.loc(r).map(|loc| loc + dir.extend(0))
                     {
+                        // Player bumps into merchant or someone to talk
+                        // to, request trade or dialogue menu.
+                        if let Some(mob) = loc.mob_at(r) {
+                            if self.is_player(r) {
+                                if mob.is_merchant(r) {
+                                    send_msg(Msg::Trade(mob));
+                                } else if mob.dialogue(r).is_some() {
+                                    send_msg(Msg::Talk(mob));
+                                }
+                            }
+                        }
+
                         // Player bumps into altar, request altar menu.
                         if loc.voxel(r) == Some(Block::Altar) {
                             if self.is_player(r) {
@@ -64,6 +78,12 @@ impl Entity {
                                 msg!("The altar does not respond to your minion.");
                             }
                         }
+
+                        if matches!(loc.voxel(r), Some(Block::LockedDoor(_)))
+                            && self.is_player(r)
+                        {
+                            msg!(Warning: "The door is locked.");
+                        }
                     }
                 }
             }
@@ -80,12 +100,44 @@ impl Entity {
             }
             Cast(power, dir) => self.cast(r, power, modified_dir(dir)),
             Throw(item, dir) => self.throw(r, &item, modified_dir(dir)),
+            Eat(item) => self.eat(r, &item),
             Equip(item) => self.equip(r, &item),
             Unequip(item) => self.unequip(r, &item),
             LevelUp => self.player_level_up(r),
+            Search => self.search(r),
+            Sneak => self.toggle_sneak(r),
+            Buy(merchant, item) => {
+                self.buy(r, &merchant, &item);
+            }
+            Sell(merchant, item) => {
+                self.sell(r, &merchant, &item);
+            }
+            Answer(speaker, node, choice) => {
+                self.answer(r, &speaker, &node, choice);
+            }
         }
     }
 
+    /// Open a locked door in the given direction if carrying a key for it.
+    ///
+    /// The key is kept and the door stays open afterwards.
+    fn unlock(&self, r: &mut impl AsMut<Runtime>, dir: IVec2) {
+        let r = r.as_mut();
+
+        let Some(loc) = self.loc(r).map(|loc| loc + dir.extend(0)) else {
+            return;
+        };
+        let Some(Block::LockedDoor(lock)) = loc.voxel(r) else {
+            return;
+        };
+        let Some(key) = self.key_for(r, lock) else {
+            return;
+        };
+
+        r.set_voxel(loc, Some(Block::Door));
+        msg!("[One] unlock[s] the door with [another]."; self.noun(r), key.noun(r));
+    }
+
     fn step(
         &self,
         r: &mut impl AsMut<Runtime>,
@@ -93,11 +145,11 @@ impl Entity {
         is_direct: bool,
     ) -> bool {
         let r = r.as_mut();
-        debug_assert!(dir.taxi_len() == 1);
+        debug_assert!(dir.chess_len() == 1);
 
         let Some(loc) = self.loc(r) else { return false };
 
-        let Some(new_loc) = loc.walk_step(r, dir) else {
+        let Some(new_loc) = self.walk_step(r, loc, dir) else {
             return false;
         };
 
@@ -106,7 +158,8 @@ impl Entity {
 
         let mut displace = None;
 
-        if let Some(mob) = new_loc.mob_at(r) {
+        // Large mobs will find themselves in the cells they're moving into.
+        if let Some(mob) = new_loc.mob_at(r).filter(|e| e != self) {
             if self.can_displace(r, dir, &mob, is_direct) {
                 displace = Some(mob);
                 r.placement.remove(&mob);
@@ -122,15 +175,29 @@ impl Entity {
                 r.placement.insert(loc, mob);
             }
 
-            // Pick up items when moving with a direct command.
-            if is_direct {
-                if let Some(item) = self.loc(r).and_then(|loc| loc.item_at(r)) {
+            self.spring_trap(r);
+
+            // Pick up items when moving with a direct command or when the
+            // player is exploring. Item kinds that have been filtered out
+            // are left where they are.
+            let is_exploring = self.is_player(r)
+                && matches!(self.goal(r), Goal::Autoexplore(_));
+            if is_direct || is_exploring {
+                if let Some(item) = self.loc(r).and_then(|loc| {
+                    loc.entities_at(r).find(|e| {
+                        e.is_item(r) && r.auto_pickup(e.get::<ItemKind>(r))
+                    })
+                }) {
                     self.take(r, &item);
                 }
             }
 
             // This is walking, so we only complete a phase, not a full turn.
             self.complete_phase(r);
+            // Sneaking takes an extra phase for every step.
+            if self.is_sneaking(r) {
+                self.complete_phase(r);
+            }
 
             true
         } else {
@@ -162,9 +229,39 @@ impl Entity {
     fn shoot(&self, r: &mut impl AsMut<Runtime>, dir: IVec2) {
         let r = r.as_mut();
 
+        let has_gun = self
+            .equipment_at(r, EquippedAt::GunHand)
+            .is_some_and(|e| e.is_ranged_weapon(r));
+
+        if has_gun && self.ammo(r).is_none() {
+            msg!(Warning: "[One] [is] out of ammo."; self.noun(r));
+            return;
+        }
+
         if let Some(mob) = self.target_for_attack(r, dir, EquippedAt::GunHand) {
             self.attack(r, mob);
+            return;
         }
+
+        // No enemy in line, loose the shot anyway if there's a gun to fire.
+        if !has_gun {
+            return;
+        }
+        let (Some(ammo), Some(loc)) = (self.ammo(r), self.loc(r)) else {
+            return;
+        };
+
+        let land = match r.line_of_fire(loc, dir, THROW_RANGE as usize) {
+            // Spent ammo drops in front of the wall.
+            Impact::Wall(loc) => loc - dir.extend(0),
+            Impact::Spent(loc) => loc,
+            // Something friendly is in the way, hold fire.
+            Impact::Mob(..) => return,
+        };
+
+        send_msg(Msg::Fire(*self, dir));
+        ammo.split_off_one(r).place(r, land);
+        self.complete_turn(r);
     }
 
     fn pass(&self, r: &mut impl AsMut<Runtime>, is_direct: bool) {
@@ -181,10 +278,15 @@ impl Entity {
     }
 
     /// Mark the entity as having taken a long action.
+    ///
+    /// A long action takes a full turn at normal speed, faster mobs get it
+    /// done sooner.
     pub(crate) fn complete_turn(&self, r: &mut impl AsMut<Runtime>) {
         let r = r.as_mut();
         let t = self.acts_next(r).max(r.now());
-        self.set(r, ActsNext(t + PHASES_IN_TURN));
+        let cost =
+            PHASES_IN_TURN * NORMAL_SPEED as i64 / self.speed(r).max(1) as i64;
+        self.set(r, ActsNext(t + cost));
     }
 
     /// Mark the entity as having taken a short action.
@@ -196,15 +298,44 @@ impl Entity {
     fn attack(&self, r: &mut impl AsMut<Runtime>, target: Entity) {
         let r = r.as_mut();
 
+        self.stop_sneaking(r);
+
         if let Some(d) = self.vec_towards(r, &target) {
-            if d.taxi_len() > 1 {
+            if d.chess_len() > 1 {
                 send_msg(Msg::Fire(*self, d.to_dir4()));
+
+                let has_gun = self
+                    .equipment_at(r, EquippedAt::GunHand)
+                    .is_some_and(|e| e.is_ranged_weapon(r));
+
+                // Spent ammo from a gun lands where the target is.
+                if has_gun {
+                    if let (Some(ammo), Some(loc)) =
+                        (self.ammo(r), target.loc(r))
+                    {
+                        ammo.split_off_one(r).place(r, loc);
+                    }
+                }
             }
         }
 
         if self.try_to_hit(r, &target) {
             let dmg = self.stats(r).dmg;
             target.damage(r, Some(*self), dmg);
+
+            // Some weapons do something extra on every melee hit.
+            let on_hit = self
+                .equipment_at(r, EquippedAt::RunHand)
+                .and_then(|e| e.get::<OnHit>(r).0);
+            if let Some(power) = on_hit {
+                if let (Some(loc), Some(d)) =
+                    (self.loc(r), self.vec_towards(r, &target))
+                {
+                    if d.chess_len() == 1 {
+                        r.invoke_power(power, Some(*self), loc, d);
+                    }
+                }
+            }
         } else {
             send_msg(Msg::Miss(target));
         }
@@ -220,7 +351,7 @@ impl Entity {
         let r = r.as_mut();
 
         let odds = Odds(self.to_hit(r) - other.evasion(r));
-        r.rng().sample(odds)
+        r.rng().chance(odds)
     }
 
     pub(crate) fn shout(
@@ -248,8 +379,12 @@ impl Entity {
             }
         }
 
-        // The shout alerts nearby other mobs.
-        let mobs = self.fov_mobs(r, SHOUT_RADIUS);
+        // The shout alerts other mobs within earshot.
+        let Some(loc) = self.loc(r) else { return };
+        let mobs: Vec<Entity> = r
+            .sound_from(loc, SHOUT_RADIUS)
+            .filter_map(|(loc, _)| loc.mob_at(r))
+            .collect();
         for m in mobs {
             if m != *self && m.is_ally(r, self) {
                 if let Some(enemy) = enemy {
@@ -272,10 +407,15 @@ impl Entity {
     ) -> bool {
         let r = r.as_mut();
 
-        match self.vec_towards(r, enemy) {
-            None => return false,
-            Some(v) if v.taxi_len() > ALERT_RADIUS => return false,
-            _ => {}
+        // Must be within earshot of the enemy.
+        let (Some(loc), Some(enemy_loc)) = (self.loc(r), enemy.loc(r)) else {
+            return false;
+        };
+        if !r
+            .sound_from(enemy_loc, ALERT_RADIUS)
+            .any(|(loc2, _)| loc2 == loc)
+        {
+            return false;
         }
         if self.is_looking_for_fight(r) {
             self.set_goal(r, Goal::Attack(*enemy));
@@ -297,7 +437,363 @@ pub enum Action {
     Cast(Power, IVec2),
     Use(Entity, IVec2),
     Throw(Entity, IVec2),
+    /// Eat a corpse for a bit of healing, or poisoning if it's bad.
+    Eat(Entity),
     Equip(Entity),
     Unequip(Entity),
     LevelUp,
+    /// Look for secret doors and hidden traps nearby.
+    Search,
+    /// Start or stop sneaking, doesn't take time.
+    Sneak,
+    /// Buy an item from a merchant.
+    Buy(Entity, Entity),
+    /// Sell an item to a merchant.
+    Sell(Entity, Entity),
+    /// Pick a response in a dialogue node when talking to someone.
+    Answer(Entity, String, usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::ivec3;
+    use util::s4;
+    use world::{Block, Environs, ItemKind, Lock, Power, Rect};
+
+    use crate::{
+        ecs::{Count, Voice},
+        prelude::*,
+        test_util::{fill_area, free_step, open_area, runtime, spawn_monster},
+        EntitySpec,
+    };
+
+    #[test]
+    fn locked_door() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+        let (dir, dest) = free_step(&r, loc);
+
+        r.set_voxel(dest, Some(Block::LockedDoor(Lock::Copper)));
+
+        // Can't get through without a key.
+        player.execute_direct(&mut r, Action::Bump(dir));
+        assert_eq!(player.loc(&r), Some(loc));
+
+        let key = r.spawn_at(&"copper key".parse().unwrap(), player)[0];
+
+        player.execute_direct(&mut r, Action::Bump(dir));
+        assert_eq!(player.loc(&r), Some(dest));
+        assert_eq!(r.voxel(dest), Some(Block::Door));
+        assert_eq!(player.key_for(&r, Lock::Copper), Some(key));
+    }
+
+    #[test]
+    fn pickup_filter() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let start = player.loc(&r).unwrap();
+        let (dir, dest) = free_step(&r, start);
+
+        r.set_auto_pickup(ItemKind::MeleeWeapon, false);
+        player.set_goal(&mut r, Goal::Autoexplore(start.sector()));
+
+        let sword = world::Item {
+            kind: ItemKind::MeleeWeapon,
+            ..Default::default()
+        }
+        .build(&mut r, "sword");
+        sword.place(&mut r, dest);
+
+        // Filtered item gets left on the ground.
+        player.execute_indirect(&mut r, Action::Bump(dir));
+        assert_eq!(player.loc(&r), Some(dest));
+        assert_eq!(sword.loc(&r), Some(dest));
+
+        let potion = world::Item {
+            kind: ItemKind::Potion,
+            ..Default::default()
+        }
+        .build(&mut r, "potion");
+        potion.place(&mut r, start);
+
+        // Allowed item gets picked up.
+        player.execute_indirect(&mut r, Action::Bump(-dir));
+        assert_eq!(player.loc(&r), Some(start));
+        assert!(player.contents(&r).any(|e| e == potion));
+        assert!(!player.contents(&r).any(|e| e == sword));
+    }
+
+    #[test]
+    fn confused_movement() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let start = player.loc(&r).unwrap();
+        let (dir, dest) = free_step(&r, start);
+
+        let mut strayed = 0;
+        for _ in 0..100 {
+            player.place(&mut r, start);
+            player.confuse(&mut r);
+            player.execute_direct(&mut r, Action::Bump(dir));
+            if player.loc(&r) != Some(dest) {
+                strayed += 1;
+            }
+        }
+
+        // Confused mobs sometimes stumble off in the wrong direction, but
+        // mostly go where they're told.
+        assert!(strayed > 0);
+        assert!(strayed < 50);
+    }
+
+    #[test]
+    fn diagonal_bump() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+        let north_east = ivec2(1, -1);
+
+        // Clear out an open room around the player.
+        open_area(&mut r, loc, Rect::new([-1, -1], [2, 2]));
+
+        // Diagonally adjacent enemy gets attacked.
+        let target = spawn_monster(&mut r, 10, loc + north_east.extend(0));
+
+        let t = player.acts_next(&r);
+        player.execute_direct(&mut r, Action::Bump(north_east));
+        assert_eq!(player.loc(&r), Some(loc));
+        assert_eq!(target.loc(&r), Some(loc + north_east.extend(0)));
+        assert!(player.acts_next(&r) > t);
+
+        // Without the enemy, the player steps diagonally.
+        target.destroy(&mut r);
+        player.execute_direct(&mut r, Action::Bump(north_east));
+        assert_eq!(player.loc(&r), Some(loc + north_east.extend(0)));
+
+        // Can't squeeze between two walls.
+        player.place(&mut r, loc);
+        for wall in [loc + ivec3(1, 0, 0), loc + ivec3(0, -1, 0)] {
+            wall.apply_char_terrain(&mut r, '#').unwrap();
+        }
+        player.execute_direct(&mut r, Action::Bump(north_east));
+        assert_eq!(player.loc(&r), Some(loc));
+    }
+
+    #[test]
+    fn knockback() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+
+        // Find a straight line of three open cells next to the player.
+        let (dir, cells) = s4::DIR
+            .iter()
+            .find_map(|&d| {
+                let cells = [1, 2, 3].map(|i| loc + d.extend(0) * i);
+                cells
+                    .iter()
+                    .all(|c| c.can_be_stood_in(&r) && c.mob_at(&r).is_none())
+                    .then_some((d, cells))
+            })
+            .expect("No room for knockback");
+
+        let target = spawn_monster(&mut r, 10, cells[0]);
+
+        r.invoke_power(Power::Knockback, Some(player), loc, dir);
+        assert_eq!(target.loc(&r), Some(cells[2]));
+
+        // Knocking the target against a wall does damage instead.
+        let wall = cells[2] + dir.extend(0);
+        r.set_voxel(wall.above(), Some(Block::Stone));
+        r.set_voxel(wall, Some(Block::Stone));
+        let wounds = target.wounds(&r);
+
+        r.invoke_power(Power::Knockback, Some(player), cells[1], dir);
+        assert_eq!(target.loc(&r), Some(cells[2]));
+        assert!(target.wounds(&r) > wounds);
+    }
+
+    #[test]
+    fn knockback_weapon() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+        let north_east = ivec2(1, -1);
+
+        // Clear out room for the target to fly diagonally.
+        open_area(&mut r, loc, Rect::new([-1, -4], [5, 2]));
+
+        let maul = r.spawn_at(&"goremaul".parse().unwrap(), player)[0];
+        // The on-hit power doesn't make the weapon usable by itself.
+        assert!(!maul.can_be_used(&r));
+        assert!(player.make_equipped(&mut r, &maul));
+
+        let start = loc + north_east.extend(0);
+        let target = spawn_monster(&mut r, 30, start);
+
+        // Bump until a hit lands, it knocks the target back diagonally.
+        for _ in 0..100 {
+            player.execute_direct(&mut r, Action::Bump(north_east));
+            if target.loc(&r) != Some(start) {
+                break;
+            }
+        }
+        assert!(target.is_alive(&r));
+        assert_eq!(target.loc(&r), Some(start + north_east.extend(0) * 2));
+    }
+
+    #[test]
+    fn melee_keeps_ammo() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+        let north_east = ivec2(1, -1);
+
+        open_area(&mut r, loc, Rect::new([-1, -2], [3, 2]));
+
+        let arrows = world::Item {
+            kind: ItemKind::Ammo,
+            ..Default::default()
+        }
+        .build(&mut r, "arrow");
+        arrows.set(&mut r, Count(2));
+        arrows.place(&mut r, player);
+
+        let start = loc + north_east.extend(0);
+        let target = spawn_monster(&mut r, 30, start);
+
+        // Diagonal melee attacks don't fire the carried arrows.
+        for _ in 0..10 {
+            player.execute_direct(&mut r, Action::Bump(north_east));
+        }
+        assert_eq!(target.loc(&r), Some(start));
+        assert_eq!(arrows.count(&r), 2);
+        assert!(start.item_at(&r).is_none());
+    }
+
+    #[test]
+    fn firing_uses_ammo() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+
+        // Find a spot two steps away to shoot at.
+        let (dir, dest) = s4::DIR
+            .iter()
+            .find_map(|&d| {
+                let dest = loc.walk_step(&r, d)?.walk_step(&r, d)?;
+                (dest.z() == loc.z()
+                    && (loc + d.extend(0)).mob_at(&r).is_none()
+                    && dest.mob_at(&r).is_none())
+                .then_some((d, dest))
+            })
+            .expect("No room to shoot");
+
+        spawn_monster(&mut r, 10, dest);
+
+        let bow = world::Item {
+            kind: ItemKind::RangedWeapon,
+            ..Default::default()
+        }
+        .build(&mut r, "bow");
+        bow.place(&mut r, player);
+        assert!(player.make_equipped(&mut r, &bow));
+
+        let arrows = world::Item {
+            kind: ItemKind::Ammo,
+            ..Default::default()
+        }
+        .build(&mut r, "arrow");
+        arrows.set(&mut r, Count(2));
+        arrows.place(&mut r, player);
+
+        player.execute_direct(&mut r, Action::Shoot(dir));
+        assert_eq!(arrows.count(&r), 1);
+
+        player.execute_direct(&mut r, Action::Shoot(dir));
+        assert!(player.ammo(&r).is_none());
+
+        // Can't fire anymore when out of ammo.
+        let t = player.acts_next(&r);
+        player.execute_direct(&mut r, Action::Shoot(dir));
+        assert_eq!(player.acts_next(&r), t);
+    }
+
+    #[test]
+    fn fire_in_direction() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let origin = player.loc(&r).unwrap();
+        let dir = ivec2(1, 0);
+
+        // Clear out a corridor to the east with a wall at the end.
+        open_area(&mut r, origin, Rect::new([1, 0], [7, 1]));
+        let wall = origin + ivec3(6, 0, 0);
+        wall.apply_char_terrain(&mut r, '#').unwrap();
+
+        let [near, far] =
+            [2, 4].map(|x| spawn_monster(&mut r, 10, origin + ivec3(x, 0, 0)));
+
+        let bow = world::Item {
+            kind: ItemKind::RangedWeapon,
+            ..Default::default()
+        }
+        .build(&mut r, "bow");
+        bow.place(&mut r, player);
+        assert!(player.make_equipped(&mut r, &bow));
+
+        let arrows = world::Item {
+            kind: ItemKind::Ammo,
+            ..Default::default()
+        }
+        .build(&mut r, "arrow");
+        arrows.set(&mut r, Count(3));
+        arrows.place(&mut r, player);
+
+        // The shot goes to the first enemy in the row.
+        player.execute_direct(&mut r, Action::Shoot(dir));
+        assert_eq!(arrows.count(&r), 2);
+        assert!(near.loc(&r).unwrap().item_at(&r).is_some());
+        assert!(far.loc(&r).unwrap().item_at(&r).is_none());
+
+        // With nobody in the way, the shot misses and lands by the wall.
+        near.destroy(&mut r);
+        far.destroy(&mut r);
+        let t = player.acts_next(&r);
+        player.execute_direct(&mut r, Action::Shoot(dir));
+        assert_eq!(arrows.count(&r), 1);
+        assert!(player.acts_next(&r) > t);
+        assert!((wall - dir.extend(0)).item_at(&r).is_some());
+    }
+
+    #[test]
+    fn shout_follows_corridors() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let center = player.loc(&r).unwrap();
+
+        // Fill the surroundings with solid rock.
+        fill_area(&mut r, center, Rect::new([-8, -8], [9, 9]));
+
+        // Dig out a corridor that turns a corner and a sealed pocket at the
+        // same distance from the shouter on the other side.
+        for [x, y] in [[0, 0], [1, 0], [2, 0], [3, 0], [4, 0], [4, 1], [4, 2]] {
+            r.set_voxel(center + ivec3(x, y, 0), None);
+        }
+        r.set_voxel(center + ivec3(4, -2, 0), None);
+
+        let spawn = |r: &mut Runtime, [x, y]: [i32; 2]| {
+            spawn_monster(r, 3, center + ivec3(x, y, 0))
+        };
+
+        let shouter = spawn(&mut r, [1, 0]);
+        shouter.set(&mut r, Voice::Shout);
+        let around_corner = spawn(&mut r, [4, 2]);
+        let sealed = spawn(&mut r, [4, -2]);
+
+        shouter.shout(&mut r, Some(&player));
+        assert_eq!(around_corner.goal(&r), Goal::Attack(player));
+        assert_ne!(sealed.goal(&r), Goal::Attack(player));
+    }
 }
@@ -1,13 +1,13 @@
 //! Mobs figuring out what to do on their own.
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
-use util::{s4, Sdf};
-use world::{Cube, EquippedAt};
+use util::{s4, v3, Sdf};
+use world::{Cube, EquippedAt, MonsterFlags};
 
 use crate::{
-    ecs::{IsEphemeral, IsFriendly},
+    ecs::{IsEphemeral, IsFriendly, Momentum},
     prelude::*,
-    FOV_RADIUS, THROW_RANGE,
+    SNEAK_RADIUS, THROW_RANGE,
 };
 
 impl Entity {
@@ -39,9 +39,13 @@ impl Entity {
                 {
                     // Enemies visible! Go fight them.
                     path_dest = Cube::unit(enemy_loc);
-                } else if let Some(loc) = player.loc(r) {
-                    // Otherwise follow player.
-                    path_dest = Cube::unit(loc);
+                } else if let Some(player_loc) = player.loc(r) {
+                    // Otherwise fall in formation around the player.
+                    match self.formation_slot(r, player_loc) {
+                        Some(slot) if slot == loc => return Some(Action::Pass),
+                        Some(slot) => path_dest = Cube::unit(slot),
+                        None => path_dest = Cube::unit(player_loc),
+                    }
                 } else {
                     // Follow target can't be found, abandon goal.
                     return None;
@@ -144,7 +148,7 @@ impl Entity {
             .find(|(_, loc)| path_dest.sd(*loc) <= 0)
         {
             // There's an enemy, fight it.
-            if dest.mob_at(r).map_or(false, |e| e.is_enemy(r, self)) {
+            if dest.mob_at(r).is_some_and(|e| e.is_enemy(r, self)) {
                 return Some(Action::Bump(dir));
             }
 
@@ -154,18 +158,29 @@ impl Entity {
             }
         }
 
+        // Incorporeal mobs don't need paths, they go straight through
+        // walls.
+        if self.has_monster_flag(r, MonsterFlags::INCORPOREAL) {
+            let dir = (v3(path_dest.center()) - loc).truncate().to_dir4();
+            if dir != IVec2::ZERO && self.can_step(r, dir) {
+                return Some(Action::Bump(dir));
+            }
+        }
+
         // Path towards target.
         // Bit of difference, player-aligned mobs path according to seen
         // things, enemy mobs path according to full information.
+        let cost = |loc| self.move_cost(r, loc);
         if let Some(mut path) = {
+            use FogPathing::*;
             if self.is_player_aligned(r) {
                 // Try to path through only known areas first, then by
                 // exploring
-                r.find_path(FogPathing::Avoid, loc, &path_dest).or_else(|| {
-                    r.find_path(FogPathing::Explore, loc, &path_dest)
-                })
+                r.find_path_with_cost(Avoid, loc, &path_dest, cost).or_else(
+                    || r.find_path_with_cost(Explore, loc, &path_dest, cost),
+                )
             } else {
-                r.find_path(FogPathing::Ignore, loc, &path_dest)
+                r.find_path_with_cost(Ignore, loc, &path_dest, cost)
             }
         } {
             // Path should always have a good step after a successful
@@ -259,7 +274,7 @@ impl Entity {
         dir: IVec2,
     ) -> bool {
         debug_assert_eq!(s4::norm(dir), dir);
-        self.fov_mobs(r, FOV_RADIUS)
+        self.fov_mobs(r, self.sight_radius(r))
             .into_iter()
             .filter(|&e| e.is_enemy(r, self))
             .any(|e| {
@@ -280,7 +295,17 @@ impl Entity {
         self.is_player_aligned(r) && !self.get::<IsEphemeral>(r).0
     }
 
+    /// Merchants are always peaceful, other mobs can be flagged as such.
+    pub fn is_peaceful(&self, r: &impl AsRef<Runtime>) -> bool {
+        self.is_merchant(r) || self.has_monster_flag(r, MonsterFlags::PEACEFUL)
+    }
+
     pub fn is_enemy(&self, r: &impl AsRef<Runtime>, other: &Entity) -> bool {
+        // Peaceful NPCs stay out of fights.
+        if self.is_peaceful(r) || other.is_peaceful(r) {
+            return false;
+        }
+
         self.is_player_aligned(r) != other.is_player_aligned(r)
     }
 
@@ -356,7 +381,8 @@ impl Entity {
         let r = r.as_ref();
         let mut range = 1;
         if let Some(item) = self.equipment_at(r, weapon_slot) {
-            if item.is_ranged_weapon(r) {
+            // Ranged weapons only work at range when there's ammo to fire.
+            if item.is_ranged_weapon(r) && self.ammo(r).is_some() {
                 // TODO Varying ranges for ranged weapons?
                 range = THROW_RANGE as usize;
             }
@@ -401,19 +427,116 @@ impl Entity {
             .collect()
     }
 
+    /// Whether `other` is within the mob's field of view.
+    pub fn can_see(&self, r: &impl AsRef<Runtime>, other: &Entity) -> bool {
+        let r = r.as_ref();
+        let (Some(loc), Some(other_loc)) = (self.loc(r), other.loc(r)) else {
+            return false;
+        };
+        r.fov_from(loc, self.sight_radius(r))
+            .any(|(_, loc)| loc == other_loc)
+    }
+
+    /// Whether the mob notices `other` being around.
+    ///
+    /// The other mob must be in view, and sneaking mobs only get noticed up
+    /// close.
+    pub fn notices(&self, r: &impl AsRef<Runtime>, other: &Entity) -> bool {
+        let r = r.as_ref();
+
+        if !self.can_see(r, other) {
+            return false;
+        }
+        if other.is_sneaking(r) {
+            return self
+                .vec_towards(r, other)
+                .is_some_and(|v| v.taxi_len() <= SNEAK_RADIUS);
+        }
+        true
+    }
+
     /// Returns an enemy from the mob's FOV or `None` if there are no visible
     /// enemies.
     ///
     /// The selection criteria for the enemy should be that it's the most
     /// preferred target of opportunity for the current mob given its current
-    /// FOV. The choice may depend on the capabilities of the queried mob.
+    /// FOV. Nearest enemies come first, ties are broken towards the enemy
+    /// the mob is already fighting, then towards the enemy with the least
+    /// health left and finally towards the direction the mob is moving in.
     pub fn first_visible_enemy(
         &self,
         r: &impl AsRef<Runtime>,
     ) -> Option<Entity> {
-        self.fov_mobs(r, FOV_RADIUS)
+        let r = r.as_ref();
+        let loc = self.loc(r)?;
+        let goal = self.goal(r);
+        let facing = self.get::<Momentum>(r).0;
+
+        r.fov_from(loc, self.sight_radius(r))
+            .filter_map(|(v, loc)| Some((v, loc.mob_at(r)?)))
+            .filter(|(_, e)| e.is_enemy(r, self))
+            .min_by_key(|&(v, e)| {
+                (
+                    v.taxi_len(),
+                    goal != Goal::Attack(e),
+                    e.max_wounds(r) - e.wounds(r),
+                    -v.dot(facing),
+                )
+            })
+            .map(|(_, e)| e)
+    }
+
+    /// Find a spot for a follower to stand in near the leader.
+    ///
+    /// Followers spread out into the open cells closest to the leader instead
+    /// of all crowding towards the leader's own cell. A follower that's
+    /// already standing in one of the slots stays put, others go to the
+    /// nearest free slot.
+    fn formation_slot(
+        &self,
+        r: &impl AsRef<Runtime>,
+        leader: Location,
+    ) -> Option<Location> {
+        // Enough slots to fill everything within two steps of the leader in
+        // open terrain.
+        const FORMATION_SIZE: usize = 12;
+
+        let r = r.as_ref();
+        let loc = self.loc(r)?;
+
+        let slots: Vec<Location> = r
+            .perturbed_fill_positions(leader)
+            .filter(|&p| p != leader)
+            .take(FORMATION_SIZE)
+            .collect();
+
+        if slots.contains(&loc) {
+            return Some(loc);
+        }
+
+        // Head for the nearest free slot so followers don't need to squeeze
+        // past the ones already in place.
+        slots
             .into_iter()
-            .find(|e| e.is_enemy(r, self))
+            .filter(|&p| p.mob_at(r).is_none() && self.can_enter(r, p))
+            .min_by_key(|&p| (p - loc).truncate().taxi_len())
+    }
+
+    /// Return whether the mob is a follower that has settled into its
+    /// formation slot around the player.
+    pub(crate) fn is_in_formation(&self, r: &impl AsRef<Runtime>) -> bool {
+        let r = r.as_ref();
+        if self.goal(r) != Goal::FollowPlayer {
+            return false;
+        }
+
+        let (Some(loc), Some(leader)) =
+            (self.loc(r), r.player().and_then(|p| p.loc(r)))
+        else {
+            return false;
+        };
+
+        self.formation_slot(r, leader) == Some(loc)
     }
 
     pub(crate) fn scan_fov(&self, r: &mut impl AsMut<Runtime>) {
@@ -425,8 +548,10 @@ impl Entity {
 
         let Some(loc) = self.loc(r) else { return };
 
-        let cells: Vec<Location> =
-            r.fov_from(loc, FOV_RADIUS).map(|(_, loc)| loc).collect();
+        let cells: Vec<Location> = r
+            .fov_from(loc, self.sight_radius(r))
+            .map(|(_, loc)| loc)
+            .collect();
 
         // Should we look for a fight while doing the scan?
         let mut looking_for_target = self.is_looking_for_fight(r);
@@ -434,14 +559,18 @@ impl Entity {
         for loc in cells {
             if let Some(mob) = loc.mob_at(r) {
                 if self.is_enemy(r, &mob) {
-                    if looking_for_target {
+                    if looking_for_target && self.notices(r, &mob) {
                         // Found a target, go attack.
                         looking_for_target = false;
                         self.set_goal(r, Goal::Attack(mob));
+                        mob.stop_sneaking(r);
                     }
 
-                    // Alert the other mob to self.
-                    if mob.alert_to(r, self) {
+                    // Alert the other mob to self if it notices self too,
+                    // FOV isn't always symmetric and mobs shouldn't notice
+                    // someone sneaking behind cover.
+                    if mob.notices(r, self) && mob.alert_to(r, self) {
+                        self.stop_sneaking(r);
                         // Shout here if alert was successful, alert_to might
                         // get called from shout too. The first spotter is the
                         // one that makes noise.
@@ -452,6 +581,7 @@ impl Entity {
 
             if self.is_player_aligned(r) {
                 r.fov.insert(loc);
+                r.remember_mobs_at(loc);
             }
         }
     }
@@ -521,4 +651,178 @@ impl Goal {
     pub fn is_some(&self) -> bool {
         !matches!(self, Goal::None)
     }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, Goal::None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::ivec3;
+    use world::{Environs, Monster, Rect};
+
+    use crate::{
+        ecs::IsFriendly,
+        prelude::*,
+        test_util::{fill_area, open_area, runtime, spawn_monster},
+        EntitySpec, FOV_RADIUS, PHASES_IN_TURN,
+    };
+
+    #[test]
+    fn follower_formation() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let center = player.loc(&r).unwrap();
+
+        // Clear out an open room around the player.
+        open_area(&mut r, center, Rect::new([-5, -5], [6, 6]));
+
+        let followers = [[-5, -5], [5, -5], [5, 5]].map(|[x, y]| {
+            let mob = spawn_monster(&mut r, 3, center + ivec3(x, y, 0));
+            mob.set(&mut r, IsFriendly(true));
+            mob.set_goal(&mut r, Goal::FollowPlayer);
+            mob
+        });
+
+        for _ in 0..PHASES_IN_TURN * 20 {
+            r.tick();
+        }
+
+        let locs = followers.map(|e| e.loc(&r).unwrap());
+        for (i, loc) in locs.iter().enumerate() {
+            assert!(!locs[..i].contains(loc), "Followers stacked up");
+            let d = *loc - center;
+            assert!(d.x.abs() + d.y.abs() <= 2, "Follower strayed: {d}");
+            assert_eq!(followers[i].goal(&r), Goal::FollowPlayer);
+        }
+    }
+
+    #[test]
+    fn target_priority() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let center = player.loc(&r).unwrap();
+
+        open_area(&mut r, center, Rect::new([-5, -5], [6, 6]));
+
+        let spawn = |r: &mut Runtime, [x, y]: [i32; 2]| {
+            spawn_monster(r, 3, center + ivec3(x, y, 0))
+        };
+
+        // Pick the nearest enemy.
+        let far = spawn(&mut r, [4, 0]);
+        let near = spawn(&mut r, [0, -2]);
+        assert_eq!(player.first_visible_enemy(&r), Some(near));
+        far.destroy(&mut r);
+
+        // Prefer the weaker one of two enemies at the same distance.
+        let wounded = spawn(&mut r, [-2, 0]);
+        wounded.damage(&mut r, None, 1);
+        assert_eq!(player.first_visible_enemy(&r), Some(wounded));
+    }
+
+    #[test]
+    fn sneak_behind_cover() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let center = player.loc(&r).unwrap();
+
+        // Fill the surroundings with solid rock.
+        fill_area(&mut r, center, Rect::new([-8, -8], [9, 9]));
+
+        // L-shaped corridor with the enemy around the corner.
+        for x in 0..=5 {
+            r.set_voxel(center + ivec3(x, 0, 0), None);
+        }
+        for y in 1..=4 {
+            r.set_voxel(center + ivec3(5, y, 0), None);
+        }
+
+        let mob = spawn_monster(&mut r, 3, center + ivec3(5, 4, 0));
+
+        // Within alert radius, but the corner blocks the view.
+        player.scan_fov(&mut r);
+        assert!(!mob.can_see(&r, &player));
+        assert_eq!(mob.goal(&r), Goal::None);
+
+        // Stepping into view gets noticed.
+        player.place(&mut r, center + ivec3(5, 0, 0));
+        player.scan_fov(&mut r);
+        assert!(mob.can_see(&r, &player));
+        assert_eq!(mob.goal(&r), Goal::Attack(player));
+    }
+
+    #[test]
+    fn sneaking() {
+        // Return how close the player gets to an inert enemy before it
+        // reacts.
+        let approach = |sneak: bool| {
+            let mut r = runtime();
+            let player = r.player().unwrap();
+            let center = player.loc(&r).unwrap();
+
+            // Straight corridor through solid rock.
+            fill_area(&mut r, center, Rect::new([-2, -3], [12, 4]));
+            for x in 0..=9 {
+                r.set_voxel(center + ivec3(x, 0, 0), None);
+            }
+
+            let mob = spawn_monster(&mut r, 3, center + ivec3(9, 0, 0));
+            // It saw the player when it got placed, make it inert again.
+            mob.set_goal(&mut r, Goal::None);
+
+            if sneak {
+                player.execute_direct(&mut r, Action::Sneak);
+                assert!(player.is_sneaking(&r));
+            }
+
+            for x in 1..9 {
+                player.place(&mut r, center + ivec3(x, 0, 0));
+                if mob.goal(&r) == Goal::Attack(player) {
+                    // Getting noticed breaks stealth.
+                    assert!(!player.is_sneaking(&r));
+                    return 9 - x;
+                }
+            }
+            panic!("Enemy never noticed the player");
+        };
+
+        assert!(approach(true) < approach(false));
+    }
+
+    #[test]
+    fn keen_sight() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let center = player.loc(&r).unwrap();
+
+        // Long straight corridor through solid rock.
+        fill_area(&mut r, center, Rect::new([-2, -3], [FOV_RADIUS + 4, 4]));
+        for x in 0..=FOV_RADIUS + 1 {
+            r.set_voxel(center + ivec3(x, 0, 0), None);
+        }
+
+        // Mob placed one cell beyond the default sight radius from the
+        // player looks around when it arrives.
+        let spawn = |r: &mut Runtime, sight| {
+            let mob = Monster {
+                icon: 'x',
+                level: 3,
+                sight,
+                ..Default::default()
+            }
+            .build(r, "monster");
+            mob.place(r, center + ivec3(FOV_RADIUS + 1, 0, 0));
+            mob
+        };
+
+        let keen = spawn(&mut r, FOV_RADIUS + 2);
+        assert_eq!(keen.goal(&r), Goal::Attack(player));
+        keen.destroy(&mut r);
+
+        let normal = spawn(&mut r, 0);
+        assert_eq!(normal.sight_radius(&r), FOV_RADIUS);
+        assert_eq!(normal.goal(&r), Goal::None);
+    }
 }
@@ -0,0 +1,79 @@
+//! Conversations with NPCs.
+
+use world::{Data, Dialogue};
+
+use crate::prelude::*;
+
+impl Entity {
+    /// Conversation tree for the entity if it has something to say.
+    pub fn dialogue(
+        &self,
+        r: &impl AsRef<Runtime>,
+    ) -> Option<&'static Dialogue> {
+        Data::get().dialogue.get(self.base_desc(r).as_str())
+    }
+
+    /// Pick a response from a node of a conversation with the speaker and
+    /// apply its effects.
+    pub(crate) fn answer(
+        &self,
+        r: &mut impl AsMut<Runtime>,
+        speaker: &Entity,
+        node: &str,
+        choice: usize,
+    ) {
+        let r = r.as_mut();
+
+        let Some(((_,), choice)) = speaker
+            .dialogue(r)
+            .and_then(|d| d.get(node))
+            .and_then(|n| n.choices.get(choice))
+        else {
+            log::warn!("Entity::answer: Bad dialogue choice {node}:{choice}");
+            return;
+        };
+
+        if let Some(flag) = &choice.set_flag {
+            r.set_flag(flag);
+        }
+
+        if let Some(give) = &choice.give {
+            for item in r.spawn_at(give, *self) {
+                msg!("[One] receive[s] [another]."; self.noun(r), item.noun(r));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        prelude::*,
+        test_util::{free_step, runtime},
+    };
+
+    #[test]
+    fn dialogue_sets_flag() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+        let (_, dest) = free_step(&r, loc);
+        let hermit = r.spawn_at(&"hermit".parse().unwrap(), dest)[0];
+        assert!(!hermit.is_enemy(&r, &player));
+
+        let dialogue = hermit.dialogue(&r).expect("Hermit has no dialogue");
+        let ((_,), ask) = &dialogue["start"].choices[0];
+        assert_eq!(ask.next.as_deref(), Some("king"));
+
+        // Asking about the king doesn't commit to anything yet.
+        player
+            .execute_direct(&mut r, Action::Answer(hermit, "start".into(), 0));
+        assert!(!r.has_flag("oath-sworn"));
+
+        // Agreeing to the quest raises the flag and gets a gift.
+        let items = player.inventory(&r).count();
+        player.execute_direct(&mut r, Action::Answer(hermit, "king".into(), 0));
+        assert!(r.has_flag("oath-sworn"));
+        assert_eq!(player.inventory(&r).count(), items + 1);
+    }
+}
@@ -13,7 +13,7 @@ use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
 };
 use util::InString;
-use world::{EquippedAt, ItemKind, MonsterFlags, Power};
+use world::{EquippedAt, ItemKind, Lock, MonsterFlags, Power};
 
 use crate::{power::PowerState, prelude::*, Buff};
 
@@ -117,14 +117,24 @@ components! {
     ItemKind,
     Powers,
     ItemPower,
+    OnHit,
+    Enchantment,
+    Opens,
+    IsPoisonous,
     EquippedAt,
+    InventoryLetter,
     Stats,
     MonsterFlags,
     Buffs,
     Speed,
+    Size,
+    Sight,
     Wounds,
+    LastHurt,
     Cash,
+    Xp,
     NumDeaths,
+    NumKills,
     IsMob,
     Voice,
     IsFriendly,
@@ -133,6 +143,8 @@ components! {
     Momentum,
     IsEphemeral,
     IsDying,
+    IsInvulnerable,
+    IsSneaking,
 }
 
 /// Time when the mob can act next.
@@ -155,12 +167,24 @@ pub struct ActsNext(pub Instant);
 )]
 pub struct Buffs(BTreeMap<Buff, Instant>);
 
+/// Time when the mob last took damage.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct LastHurt(pub Instant);
+
 /// Stacking value, value 0 means there's one item but it does not stack.
 #[derive(
     Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
 )]
 pub struct Count(pub i32);
 
+/// Bonus levels of an individual equipment item, shown as "+2 sword".
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct Enchantment(pub i32);
+
 #[derive(
     Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
 )]
@@ -184,14 +208,42 @@ pub struct IsEphemeral(pub bool);
 )]
 pub struct IsFriendly(pub bool);
 
+/// Persistent letter of an item in its carrier's inventory listing.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct InventoryLetter(pub Option<char>);
+
+/// Debug flag for entities that don't take damage.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct IsInvulnerable(pub bool);
+
+/// Mob is moving slowly and quietly to avoid being noticed.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct IsSneaking(pub bool);
+
 #[derive(
     Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
 )]
 pub struct IsMob(pub bool);
 
+/// Food that poisons whoever eats it.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct IsPoisonous(pub bool);
+
 #[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct ItemPower(pub Option<Power>);
 
+/// Power a weapon invokes on the target of every melee hit.
+#[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub struct OnHit(pub Option<Power>);
+
 /// Used by AI movement, moving sets momentum and you can't displace a mob
 /// that moved this turn against its momentum.
 #[derive(
@@ -209,9 +261,32 @@ pub struct Nickname(pub String);
 #[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct NumDeaths(pub i32);
 
+/// How many enemies has the player side killed.
+#[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub struct NumKills(pub i32);
+
+/// Lock the entity opens if it's a key.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct Opens(pub Option<Lock>);
+
 #[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct Powers(pub BTreeMap<Power, PowerState>);
 
+/// Width of a large mob's square footprint, mobs without this take up a
+/// single cell.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct Size(pub i32);
+
+/// Sight radius of a mob that sees further or closer than usual.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct Sight(pub i32);
+
 #[derive(
     Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
 )]
@@ -286,6 +361,12 @@ pub struct Wounds(pub i32);
 )]
 pub struct Cash(pub i32);
 
+/// Experience points earned from killing enemies.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize,
+)]
+pub struct Xp(pub i32);
+
 ////////////////////////////////
 
 /// Entity component system. Stores all the data of game entities.
@@ -5,7 +5,7 @@ use derive_more::Deref;
 use hecs::Component;
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use util::{Noun, Silo, StrExt};
-use world::{Block, Data};
+use world::{Block, Data, MonsterFlags};
 
 use crate::{ecs::*, placement::Place, prelude::*};
 
@@ -131,6 +131,8 @@ impl Entity {
         let place = place.into();
         if Some(place) != r.placement.get(self) {
             self.detach(r);
+            self.set(r, InventoryLetter(None));
+
             // Try to merge stacks in the new place, if successful will
             // consume self to grow a stack. Otherwise move self.
             if self.try_merge_in(r, place) {
@@ -138,6 +140,9 @@ impl Entity {
             }
 
             r.placement.insert(place, *self);
+            if let Place::In(owner) = place {
+                self.assign_inventory_letter(r, &owner);
+            }
             self.post_move_hook(r);
         }
     }
@@ -188,7 +193,13 @@ impl Entity {
     }
 
     pub(crate) fn post_move_hook(&self, r: &mut impl AsMut<Runtime>) {
+        let r = r.as_mut();
+
         self.scan_fov(r);
+
+        if self.is_player(r) {
+            self.search_for_traps(r);
+        }
     }
 
     /// Return the type of terrain the entity is expected to spawn in.
@@ -228,26 +239,38 @@ impl Entity {
         self.get::<IsEphemeral>(r).0
     }
 
+    pub fn is_invulnerable(&self, r: &impl AsRef<Runtime>) -> bool {
+        self.get::<IsInvulnerable>(r).0
+    }
+
     /// Description without modifiers like count or nicknames.
     pub fn base_desc(&self, r: &impl AsRef<Runtime>) -> String {
         self.get::<Name>(r).0.to_string()
     }
 
     /// Description string of the entity.
+    ///
+    /// Stacks of several items show the count and the plural name, "3 potions
+    /// of healing".
     pub fn desc(&self, r: &impl AsRef<Runtime>) -> String {
         let nickname = self.get::<Nickname>(r).0;
 
+        let plus = match self.get::<Enchantment>(r).0 {
+            0 => String::new(),
+            n => format!("{n:+} "),
+        };
+
         let count = self.count(r);
         let name = if count > 1 {
             format!(
-                "{count} {}",
+                "{count} {plus}{}",
                 self.base_desc(r).pluralize(&Data::get().plurals)
             )
         } else {
-            self.base_desc(r)
+            format!("{plus}{}", self.base_desc(r))
         };
 
-        let is_proper = name.chars().next().map_or(false, |c| c.is_uppercase());
+        let is_proper = name.chars().next().is_some_and(|c| c.is_uppercase());
 
         if !nickname.is_empty() {
             if is_proper {
@@ -277,14 +300,18 @@ impl Entity {
     pub fn can_enter(&self, r: &impl AsRef<Runtime>, loc: Location) -> bool {
         let r = r.as_ref();
 
-        if !loc.can_be_stood_in(r) {
-            return false;
-        }
-        if self.is_mob(r) && loc.mob_at(r).is_some() {
-            return false;
-        }
+        self.footprint_at(r, loc).all(|loc| {
+            if !loc.can_be_stood_in(r)
+                && !self.has_monster_flag(r, MonsterFlags::INCORPOREAL)
+            {
+                return false;
+            }
+            if self.is_mob(r) && loc.mob_at(r).is_some_and(|e| e != *self) {
+                return false;
+            }
 
-        true
+            true
+        })
     }
 
     /// Method called at the start of every frame.
@@ -295,6 +322,9 @@ impl Entity {
             // Clear momentum from previous turn at the start of the next one.
             self.set(r, Momentum::default());
         }
+
+        self.regenerate(r);
+        self.suffer_poison(r);
     }
 
     pub fn destroy(&self, r: &mut impl AsMut<Runtime>) {
@@ -1,18 +1,107 @@
-use world::{Item, Monster};
+use world::{Item, ItemKind, Monster, MonsterFlags, PodKind, SpawnDist};
 
-use crate::{ecs::*, prelude::*};
+use crate::{ecs::*, prelude::*, NORMAL_SPEED};
 
 /// Static entity descriptors that specify runtime entities.
 pub trait EntitySpec {
     fn build(&self, r: &mut Runtime, name: &str) -> Entity;
 }
 
+/// Owned entity spec that can be read back from a live entity.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Spec {
+    Monster(Monster, Instance),
+    Item(Item, Instance),
+}
+
+impl EntitySpec for Spec {
+    fn build(&self, r: &mut Runtime, name: &str) -> Entity {
+        let (ret, instance) = match self {
+            Spec::Monster(a, instance) => (a.build(r, name), instance),
+            Spec::Item(a, instance) => (a.build(r, name), instance),
+        };
+        ret.set(r, Enchantment(instance.enchantment));
+        ret.set(r, Wounds(instance.wounds));
+        ret.set(r, IsFriendly(instance.is_friendly));
+        ret
+    }
+}
+
+/// State of a single entity that changes during play and isn't part of the
+/// static data.
+///
+/// Fields that don't apply to the entity are left at their defaults.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Instance {
+    pub enchantment: i32,
+    pub wounds: i32,
+    pub is_friendly: bool,
+}
+
+impl Entity {
+    /// Snapshot the entity's current state into a spec that will build a
+    /// similar entity.
+    ///
+    /// Spawn distribution values aren't stored in the ECS, they're copied
+    /// from the data entry the entity is named after if there is one.
+    /// Inventory items are separate entities and need to be snapshotted
+    /// separately.
+    pub fn to_spec(&self, r: &impl AsRef<Runtime>) -> Spec {
+        let base = self.base_desc(r).parse::<PodKind>().ok();
+        let (rarity, min_depth) = base
+            .as_ref()
+            .map(|a| (a.rarity(), a.min_depth()))
+            .unwrap_or_default();
+
+        let instance = Instance {
+            enchantment: self.get::<Enchantment>(r).0,
+            wounds: self.get::<Wounds>(r).0,
+            is_friendly: self.get::<IsFriendly>(r).0,
+        };
+
+        let stats = self.get::<Stats>(r);
+        if self.is_mob(r) {
+            Spec::Monster(
+                Monster {
+                    icon: self.get::<Icon>(r).0,
+                    level: stats.level,
+                    evasion: stats.ev,
+                    attack_damage: stats.dmg,
+                    rarity,
+                    min_depth,
+                    flags: self.get::<MonsterFlags>(r),
+                    speed: self.get::<Speed>(r).0,
+                    size: self.size(r),
+                    sight: self.get::<Sight>(r).0,
+                },
+                instance,
+            )
+        } else {
+            Spec::Item(
+                Item {
+                    level: stats.level,
+                    kind: self.get::<ItemKind>(r),
+                    rarity,
+                    lock: self.get::<Opens>(r).0,
+                    on_hit: self.get::<OnHit>(r).0,
+                    power: self.get::<ItemPower>(r).0,
+                },
+                instance,
+            )
+        }
+    }
+}
+
 impl EntitySpec for Monster {
     fn build(&self, r: &mut Runtime, name: &str) -> Entity {
         let ret = Entity(r.ecs.spawn((
             Name(name.into()),
             Icon(self.icon),
-            Speed(3),
+            Speed(if self.speed > 0 {
+                self.speed
+            } else {
+                NORMAL_SPEED
+            }),
             IsMob(true),
             Stats {
                 level: self.level,
@@ -27,6 +116,12 @@ impl EntitySpec for Monster {
         if !self.flags.is_empty() {
             ret.set(r, self.flags);
         }
+        if self.size > 1 {
+            ret.set(r, Size(self.size));
+        }
+        if self.sight > 0 {
+            ret.set(r, Sight(self.sight));
+        }
 
         ret
     }
@@ -47,6 +142,53 @@ impl EntitySpec for Item {
         if self.kind.is_stacking() {
             ret.set(r, Count(1));
         }
+        ret.set(r, Opens(self.lock));
+        if self.on_hit.is_some() {
+            ret.set(r, OnHit(self.on_hit.clone()));
+        }
         ret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use world::PodKind;
+
+    use crate::{
+        ecs::{Enchantment, Wounds},
+        test_util::runtime,
+        EntitySpec, Spec,
+    };
+
+    fn base_spec(name: &str) -> Spec {
+        match name.parse::<PodKind>().unwrap() {
+            PodKind::Monster(a) => Spec::Monster(a.clone(), Default::default()),
+            PodKind::Item(a) => Spec::Item(a.clone(), Default::default()),
+        }
+    }
+
+    #[test]
+    fn spec_round_trip() {
+        let mut r = runtime();
+
+        for name in ["goblin", "red ooze", "potion of healing", "sword"] {
+            let spec = base_spec(name);
+            let e = spec.build(&mut r, name);
+            assert_eq!(e.to_spec(&r), spec, "{name}");
+        }
+
+        // Changes made during play are carried over to the copy.
+        let sword = base_spec("sword").build(&mut r, "sword");
+        sword.set(&mut r, Enchantment(2));
+        let spec = sword.to_spec(&r);
+        assert_ne!(spec, base_spec("sword"));
+        let copy = spec.build(&mut r, "sword");
+        assert_eq!(copy.desc(&r), "+2 sword");
+        assert_eq!(copy.to_spec(&r), spec);
+
+        let goblin = base_spec("goblin").build(&mut r, "goblin");
+        goblin.set(&mut r, Wounds(1));
+        let copy = goblin.to_spec(&r).build(&mut r, "goblin");
+        assert_eq!(copy.wounds(&r), 1);
+    }
+}
@@ -32,6 +32,43 @@ impl From<Fov> for BitAtlas {
 }
 
 impl Runtime {
+    /// Return the cells currently seen by the player's side.
+    ///
+    /// Unlike the explored cells in `fov`, this is recomputed on every call.
+    pub fn visible_cells(&self) -> HashSet<Location> {
+        self.live_entities()
+            .filter(|e| e.is_mob(self) && e.is_player_aligned(self))
+            .filter_map(|e| Some((e.loc(self)?, e.sight_radius(self))))
+            .flat_map(|(loc, radius)| {
+                self.fov_from(loc, radius).map(|(_, loc)| loc)
+            })
+            .collect()
+    }
+
+    /// Return the mob last seen at an explored location and its icon.
+    ///
+    /// The mob may have since moved elsewhere, use this to show a memory of
+    /// it for cells that aren't currently in view.
+    pub fn last_seen_at(&self, loc: Location) -> Option<(Entity, char)> {
+        self.last_seen.get(&loc).copied()
+    }
+
+    /// Update the memory of mobs seen at a location that's currently in
+    /// view of the player's side.
+    pub(crate) fn remember_mobs_at(&mut self, loc: Location) {
+        match loc.mob_at(self) {
+            Some(mob) if !mob.is_player_aligned(self) => {
+                // A mob is only remembered where it was seen last.
+                self.last_seen.retain(|_, (e, _)| *e != mob);
+                let icon = mob.icon(self);
+                self.last_seen.insert(loc, (mob, icon));
+            }
+            _ => {
+                self.last_seen.swap_remove(&loc);
+            }
+        }
+    }
+
     pub fn fov_from(
         &self,
         loc: Location,
@@ -94,3 +131,70 @@ impl Runtime {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::ivec3;
+    use world::{Environs, Rect, LEVEL_DEPTH};
+
+    use crate::{
+        prelude::*,
+        test_util::{fill_area, runtime, spawn_monster},
+    };
+
+    #[test]
+    fn visible_vs_explored() {
+        let mut r = runtime();
+        let loc = r.player().unwrap().loc(&r).unwrap();
+
+        let visible = r.visible_cells();
+        assert!(loc.is_visible(&r, &visible));
+        assert!(loc.is_explored(&r));
+
+        // Remembered from earlier but not in view now.
+        let far = loc + ivec3(0, 0, -20);
+        let floor = far.snap_above_floor(&r);
+        r.fov.insert(floor);
+        assert!(far.is_explored(&r));
+        assert!(!far.is_visible(&r, &visible));
+    }
+
+    #[test]
+    fn last_seen_mobs() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let start = player.loc(&r).unwrap();
+        let at = |x, y| start + ivec3(x, y, 0);
+
+        // Corridor with a side passage around the corner at the end.
+        fill_area(&mut r, start, Rect::new([-2, -3], [9, 6]));
+        for x in 0..=6 {
+            r.set_voxel(at(x, 0), None);
+        }
+        for y in 1..=3 {
+            r.set_voxel(at(6, y), None);
+        }
+
+        let mob = spawn_monster(&mut r, 3, at(6, 3));
+
+        // Spot the mob from the corner.
+        player.place(&mut r, at(6, 0));
+        assert_eq!(r.last_seen_at(at(6, 3)), Some((mob, 'x')));
+
+        // Go downstairs and have the mob move while the player is away.
+        player.place(&mut r, start + ivec3(0, 0, -LEVEL_DEPTH));
+        mob.place(&mut r, at(6, 2));
+
+        // Come back to where the mob is out of view.
+        player.place(&mut r, start);
+        assert!(at(6, 3).is_explored(&r));
+        assert!(!at(6, 3).is_visible(&r, &r.visible_cells()));
+        assert_eq!(r.last_seen_at(at(6, 3)), Some((mob, 'x')));
+        assert_eq!(r.last_seen_at(at(6, 2)), None);
+
+        // Seeing the mob again moves the memory.
+        player.place(&mut r, at(6, 0));
+        assert_eq!(r.last_seen_at(at(6, 3)), None);
+        assert_eq!(r.last_seen_at(at(6, 2)), Some((mob, 'x')));
+    }
+}
@@ -3,12 +3,15 @@
 use rand::seq::SliceRandom;
 use strum::IntoEnumIterator;
 use util::{s4, RngExt};
-use world::{EquippedAt, ItemKind};
+use world::{EquippedAt, ItemKind, Lock};
 
 use crate::{
-    ecs::{Cash, Count, IsEphemeral, ItemPower},
+    ecs::{
+        Cash, Count, InventoryLetter, IsEphemeral, IsPoisonous, ItemPower,
+        Opens, Stats, Wounds,
+    },
     prelude::*,
-    THROW_RANGE,
+    Buff, PHASES_IN_TURN, THROW_RANGE,
 };
 
 impl Entity {
@@ -50,13 +53,43 @@ impl Entity {
     }
 
     pub fn use_needs_aim(&self, r: &impl AsRef<Runtime>) -> bool {
-        self.get::<ItemPower>(r).0.map_or(false, |p| p.needs_aim())
+        self.get::<ItemPower>(r).0.is_some_and(|p| p.needs_aim())
     }
 
     pub fn can_be_used(&self, r: &impl AsRef<Runtime>) -> bool {
         self.get::<ItemPower>(r).0.is_some()
     }
 
+    pub fn can_be_eaten(&self, r: &impl AsRef<Runtime>) -> bool {
+        self.get::<ItemKind>(r) == ItemKind::Corpse
+    }
+
+    /// Letter the item is listed under in its carrier's inventory.
+    ///
+    /// Letters stay the same as long as the item stays in the inventory.
+    pub fn inventory_letter(&self, r: &impl AsRef<Runtime>) -> Option<char> {
+        self.get::<InventoryLetter>(r).0
+    }
+
+    /// Give the item the first inventory letter not used by other items.
+    pub(crate) fn assign_inventory_letter(
+        &self,
+        r: &mut impl AsMut<Runtime>,
+        owner: &Entity,
+    ) {
+        const LETTERS: &str = "abcdefghijklmnopqrstuvwxyz";
+
+        let r = r.as_mut();
+
+        let used: Vec<char> = owner
+            .contents(r)
+            .filter(|e| e != self)
+            .filter_map(|e| e.inventory_letter(r))
+            .collect();
+        let letter = LETTERS.chars().find(|c| !used.contains(c));
+        self.set(r, InventoryLetter(letter));
+    }
+
     pub fn is_equipped(&self, r: &impl AsRef<Runtime>) -> bool {
         self.equipped_at(r).is_some()
     }
@@ -111,6 +144,31 @@ impl Entity {
         ret
     }
 
+    /// Whether equipping the item would strictly improve the mob's stats
+    /// over what's in the slot it would go in.
+    pub fn is_upgrade_for(
+        &self,
+        r: &impl AsRef<Runtime>,
+        mob: &Entity,
+    ) -> bool {
+        if !self.can_be_equipped(r) || self.is_equipped(r) {
+            return false;
+        }
+
+        let current = match self.find_slot_in(r, mob) {
+            Ok(slot) if slot.is_none() => return false,
+            Ok(_) => Stats::default(),
+            Err((_, e)) => e.stats(r),
+        };
+        let new = self.stats(r);
+
+        new != current
+            && new.level >= current.level
+            && new.hit >= current.hit
+            && new.ev >= current.ev
+            && new.dmg >= current.dmg
+    }
+
     pub fn fits(&self, r: &impl AsRef<Runtime>, slot: EquippedAt) -> bool {
         self.get::<ItemKind>(r).fits(slot)
     }
@@ -225,6 +283,22 @@ impl Entity {
         self.contents(r)
     }
 
+    /// Return carried ammunition for ranged weapons.
+    pub fn ammo(&self, r: &impl AsRef<Runtime>) -> Option<Entity> {
+        self.inventory(r)
+            .find(|e| e.get::<ItemKind>(r) == ItemKind::Ammo)
+    }
+
+    /// Return a carried key that opens the given lock.
+    pub fn key_for(
+        &self,
+        r: &impl AsRef<Runtime>,
+        lock: Lock,
+    ) -> Option<Entity> {
+        self.inventory(r)
+            .find(|e| e.get::<Opens>(r).0 == Some(lock))
+    }
+
     pub fn equipment_at(
         &self,
         r: &impl AsRef<Runtime>,
@@ -241,7 +315,8 @@ impl Entity {
     pub(crate) fn take(&self, r: &mut impl AsMut<Runtime>, item: &Entity) {
         let r = r.as_mut();
 
-        if self.is_player(r) && item.is_cash(r) {
+        let is_cash = self.is_player(r) && item.is_cash(r);
+        if is_cash {
             // Cash items get deleted and added to cash component.
             let n = item.count(r);
             self.with_mut::<Cash, _>(r, |Cash(c)| *c += n);
@@ -252,6 +327,17 @@ impl Entity {
         }
 
         msg!("[One] pick[s] up [another]."; self.noun(r), item.noun(r));
+
+        // Put on upgrades that don't need anything else taken off.
+        if !is_cash
+            && self.is_player(r)
+            && r.auto_equip()
+            && item.find_slot_in(r, self).is_ok()
+            && item.is_upgrade_for(r, self)
+            && self.make_equipped(r, item)
+        {
+            msg!("[One] equip[s] [another]."; self.noun(r), item.noun(r));
+        }
     }
 
     pub(crate) fn drop(&self, r: &mut impl AsMut<Runtime>, item: &Entity) {
@@ -285,12 +371,37 @@ impl Entity {
         self.complete_turn(r);
     }
 
+    pub(crate) fn eat(&self, r: &mut impl AsMut<Runtime>, item: &Entity) {
+        const FOOD_HEAL: i32 = 3;
+        const POISON_DURATION: i64 = PHASES_IN_TURN * 10;
+
+        let r = r.as_mut();
+
+        if !item.can_be_eaten(r) {
+            msg!("[One] can't eat [another]."; self.noun(r), item.noun(r));
+            return;
+        }
+
+        msg!("[One] eat[s] [another]."; self.noun(r), item.noun(r));
+        if item.get::<IsPoisonous>(r).0 {
+            msg!(Warning: "[One] [is] poisoned."; self.noun(r));
+            self.buff(r, Buff::Poison, POISON_DURATION);
+        } else {
+            let wounds = self.get::<Wounds>(r).0;
+            self.set(r, Wounds((wounds - FOOD_HEAL).max(0)));
+        }
+        item.consume(r);
+        self.complete_turn(r);
+    }
+
     pub(crate) fn throw(
         &self,
         r: &mut impl AsMut<Runtime>,
         item: &Entity,
         mut v: IVec2,
     ) {
+        const POTION_SPLASH_RADIUS: i32 = 1;
+
         let r = r.as_mut();
         let Some(loc) = self.loc(r) else { return };
 
@@ -322,16 +433,32 @@ impl Entity {
                     // TODO Figure out throw damage based on item (and thrower strength?)
                     // TODO Throw to-hit determination should be different than melee, wielded weapon doesn't matter for one thing
                     // TODO Mulch items when they are used as weapons
-                    msg!("[One] hit[s] [another]."; item.noun(r), mob.noun(r));
+                    msg!(Combat: "[One] hit[s] [another].";
+                        item.noun(r), mob.noun(r));
                     mob.damage(r, Some(*self), 4);
                 } else {
                     // TODO The projectile should keep flying past the mobs it misses
-                    msg!("[One] miss[es] [another]."; item.noun(r), mob.noun(r));
+                    msg!(Combat: "[One] miss[es] [another].";
+                        item.noun(r), mob.noun(r));
                 }
             } else {
                 msg!("[One] throw[s] [another]."; self.noun(r), item.noun(r));
             }
-            item.place(r, target);
+
+            // Potions break and splash their effect around where they land.
+            if let (ItemKind::Potion, Some(power)) =
+                (item.get::<ItemKind>(r), item.get::<ItemPower>(r).0)
+            {
+                msg!("[One] shatter[s]."; item.noun(r));
+                for loc in r.blast_area(target, POTION_SPLASH_RADIUS) {
+                    if let Some(mob) = loc.mob_at(r) {
+                        r.invoke_power(power.clone(), Some(mob), loc, v);
+                    }
+                }
+                item.destroy(r);
+            } else {
+                item.place(r, target);
+            }
         }
     }
 
@@ -381,3 +508,112 @@ impl Entity {
         Some(pile)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use world::{EquippedAt, ItemKind};
+
+    use crate::{ecs::Enchantment, prelude::*, test_util::runtime, EntitySpec};
+
+    #[test]
+    fn stack_names() {
+        let mut r = runtime();
+        let loc = r.player().unwrap().loc(&r).unwrap();
+
+        let potions =
+            r.spawn_at(&"3x potion of healing".parse().unwrap(), loc)[0];
+        assert_eq!(potions.count(&r), 3);
+        assert_eq!(potions.desc(&r), "3 potions of healing");
+
+        // Non-stacking items are spawned as separate single entities.
+        let swords = r.spawn_at(&"2x sword".parse().unwrap(), loc);
+        assert_eq!(swords.len(), 2);
+        assert_eq!(swords[0].desc(&r), "sword");
+    }
+
+    #[test]
+    fn enchanted_weapon() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+
+        let sword = r.spawn_at(&"sword".parse().unwrap(), player)[0];
+        assert!(player.make_equipped(&mut r, &sword));
+        let base_damage = player.stats(&r).dmg;
+
+        sword.set(&mut r, Enchantment(2));
+        assert_eq!(sword.desc(&r), "+2 sword");
+        assert_eq!(player.stats(&r).dmg, base_damage + 2);
+
+        // Enchantment is stored in the entity and survives a save.
+        let save = idm::to_string(&r).unwrap();
+        let r: Runtime = idm::from_str(&save).unwrap();
+        assert_eq!(sword.desc(&r), "+2 sword");
+        assert_eq!(player.stats(&r).dmg, base_damage + 2);
+    }
+
+    #[test]
+    fn stable_inventory_letters() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+
+        let items: Vec<Entity> = ["sword", "dagger", "axe"]
+            .iter()
+            .map(|name| {
+                let e = world::Item {
+                    kind: ItemKind::MeleeWeapon,
+                    ..Default::default()
+                }
+                .build(&mut r, name);
+                e.place(&mut r, player);
+                e
+            })
+            .collect();
+        let letters: Vec<Option<char>> =
+            items.iter().map(|e| e.inventory_letter(&r)).collect();
+        assert!(letters.iter().all(|k| k.is_some()));
+
+        // Remove an item from the middle.
+        items[1].place(&mut r, loc);
+        assert_eq!(items[1].inventory_letter(&r), None);
+
+        // New item takes the freed letter, others keep theirs.
+        let mace = world::Item {
+            kind: ItemKind::MeleeWeapon,
+            ..Default::default()
+        }
+        .build(&mut r, "mace");
+        mace.place(&mut r, player);
+        assert_eq!(mace.inventory_letter(&r), letters[1]);
+        assert_eq!(items[0].inventory_letter(&r), letters[0]);
+        assert_eq!(items[2].inventory_letter(&r), letters[2]);
+    }
+
+    #[test]
+    fn auto_equip_upgrade() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+
+        // Empty the weapon hand.
+        if let Some(weapon) = player.equipment_at(&r, EquippedAt::RunHand) {
+            weapon.destroy(&mut r);
+        }
+
+        // Nothing gets equipped with the option off.
+        r.set_auto_equip(false);
+        let dagger = r.spawn_at(&"dagger".parse().unwrap(), loc)[0];
+        assert!(dagger.is_upgrade_for(&r, &player));
+        player.take(&mut r, &dagger);
+        assert!(!dagger.is_equipped(&r));
+
+        r.set_auto_equip(true);
+        let sword = r.spawn_at(&"sword".parse().unwrap(), loc)[0];
+        assert!(sword.is_upgrade_for(&r, &player));
+        player.take(&mut r, &sword);
+        assert_eq!(sword.equipped_at(&r), EquippedAt::RunHand);
+
+        // The dagger is worse than what's being wielded now.
+        assert!(!dagger.is_upgrade_for(&r, &player));
+    }
+}
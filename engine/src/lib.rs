@@ -1,33 +1,47 @@
 //! Game logic layer machinery.
 
-/// How far can the player see.
+/// How far can mobs see by default.
 pub const FOV_RADIUS: i32 = 10;
 
 /// From how far away do inert enemies first react to foes.
 pub const ALERT_RADIUS: i32 = 9;
 
+/// From how far away do enemies notice a sneaking mob.
+pub const SNEAK_RADIUS: i32 = 3;
+
 /// From how far away does the enemy shout wake up mobs.
 pub const SHOUT_RADIUS: i32 = 6;
 
 /// How far can you throw items.
 pub const THROW_RANGE: i32 = 10;
 
+/// How far away can mobs spot hidden traps.
+pub const TRAP_DETECT_RADIUS: i32 = 2;
+
 /// How many move phases does a complete turn contain.
 pub const PHASES_IN_TURN: i64 = 12;
 
+/// How many move phases a mob of normal speed gets in a turn.
+pub const NORMAL_SPEED: i8 = 3;
+
+/// Widest footprint a large mob can have.
+pub const MAX_MOB_SIZE: i32 = 2;
+
 mod action;
 pub use action::Action;
 
 mod ai;
 pub use ai::Goal;
 
+mod dialogue;
+
 pub mod ecs;
 
 mod entity;
 pub use entity::Entity;
 
 mod entity_spec;
-pub use entity_spec::EntitySpec;
+pub use entity_spec::{EntitySpec, Instance, Spec};
 
 mod fov;
 pub use crate::fov::Fov;
@@ -41,7 +55,10 @@ mod mob;
 pub use mob::Buff;
 
 mod msg;
-pub use msg::{send_msg, Grammatize, Msg, Receiver};
+pub use msg::{send_msg, Grammatize, Msg, MsgKind, Receiver};
+
+mod objective;
+pub use objective::Quest;
 
 mod pathing;
 pub use pathing::FogPathing;
@@ -56,9 +73,23 @@ pub mod prelude;
 mod runtime;
 pub use runtime::Runtime;
 
+mod sim;
+pub use sim::SimReport;
+
+#[cfg(test)]
+mod test_util;
+
 mod time;
-pub use time::Instant;
+pub use time::{Event, Instant, Scheduler};
+
+mod trade;
+
+mod trap;
+
+mod wizard;
+pub use wizard::WizardCommand;
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum ScenarioStatus {
     Ongoing,
     Won,
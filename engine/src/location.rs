@@ -1,8 +1,8 @@
 use glam::ivec3;
-use util::{s4, s8, Neighbors2D};
-use world::{Block, Environs, Tile};
+use util::{s4, s8, v2, Neighbors2D};
+use world::{Block, Environs, Rect, Tile};
 
-use crate::{prelude::*, Grammatize};
+use crate::{prelude::*, Grammatize, MAX_MOB_SIZE};
 
 pub trait RuntimeCoordinates: Coordinates {
     /// Tile setter that doesn't cover functional terrain.
@@ -14,6 +14,15 @@ pub trait RuntimeCoordinates: Coordinates {
     /// Location has been seen by an allied unit at some point.
     fn is_explored(&self, r: &impl AsRef<Runtime>) -> bool;
 
+    /// Location is currently seen by an allied unit.
+    ///
+    /// Uses a visible cell set from `Runtime::visible_cells`.
+    fn is_visible(
+        &self,
+        r: &impl AsRef<Runtime>,
+        visible: &HashSet<Location>,
+    ) -> bool;
+
     /// Destination for UI path selection, may dip outside the +/-1 slice if
     /// the point is a wall above/below a position reached from an adjacent
     /// slope.
@@ -24,7 +33,7 @@ pub trait RuntimeCoordinates: Coordinates {
         match self.voxel(r) {
             // Door is held open by someone passing through.
             Some(Block::Door) if self.mob_at(r).is_some() => false,
-            Some(_) => true,
+            Some(b) => b.blocks_shot(),
             None => false,
         }
     }
@@ -39,6 +48,24 @@ pub trait RuntimeCoordinates: Coordinates {
         }
     }
 
+    /// How far sound has to travel to pass through the location, `None` if
+    /// sound can't pass through it.
+    fn sound_cost(&self, r: &impl AsRef<Runtime>) -> Option<usize> {
+        // Closed doors and windows muffle sound heavily.
+        const DAMPED_COST: usize = 3;
+
+        let r = r.as_ref();
+        match self.voxel(r) {
+            // Door is held open by someone passing through.
+            Some(Block::Door) if self.mob_at(r).is_some() => Some(1),
+            Some(b) if b.blocks_sound() => None,
+            Some(Block::Door | Block::LockedDoor(_) | Block::Glass) => {
+                Some(DAMPED_COST)
+            }
+            _ => Some(1),
+        }
+    }
+
     fn entities_at<'a>(
         &self,
         r: &'a impl AsRef<Runtime>,
@@ -97,14 +124,9 @@ pub trait RuntimeCoordinates: Coordinates {
     }
 
     /// Description for the general area of the location.
-    fn region_name(&self, _r: &impl AsRef<Runtime>) -> String {
-        let depth = -self.z().div_euclid(2);
-        if depth > 0 {
-            format!("Mazes of Menace: {depth}")
-        } else {
-            "Surface world".into()
-        }
-    }
+    ///
+    /// Uses the name the scenario gives to the level if there is one.
+    fn region_name(&self, r: &impl AsRef<Runtime>) -> String;
 
     fn damage(
         &self,
@@ -136,37 +158,28 @@ impl RuntimeCoordinates for Location {
 
     fn is_explored(&self, r: &impl AsRef<Runtime>) -> bool {
         let r = r.as_ref();
-        if self.snap_above_floor(r).is_in_fov_set(r) {
-            return true;
-        }
+        is_seen(r, *self, |loc| loc.is_in_fov_set(r))
+    }
 
-        if self.tile(r).is_wall() {
-            // Any 4-adjacent visible cell makes a wall visible.
-            if self
-                .ns_4()
-                .any(|loc| loc.snap_above_floor(r).is_in_fov_set(r))
-            {
-                return true;
-            }
+    fn is_visible(
+        &self,
+        r: &impl AsRef<Runtime>,
+        visible: &HashSet<Location>,
+    ) -> bool {
+        is_seen(r.as_ref(), *self, |loc| visible.contains(&loc))
+    }
 
-            for diag in s8::DIAGONALS.iter().map(|&p| p.extend(0)) {
-                // This is a corner wall and next to a visible floor.
-                //
-                // Since last step didn't return, there's no directly adjacent
-                // visible floor.
-                //
-                // Must have two adjacent walls to qualify as visible here.
-                if (*self + diag).snap_above_floor(r).is_in_fov_set(r) {
-                    if (*self + diag * ivec3(1, 0, 0)).tile(r).is_wall()
-                        && (*self + diag * ivec3(0, 1, 0)).tile(r).is_wall()
-                    {
-                        return true;
-                    }
-                }
-            }
+    fn region_name(&self, r: &impl AsRef<Runtime>) -> String {
+        if let Some(name) = r.as_ref().world.region_name(*self) {
+            return name.into();
         }
 
-        false
+        let depth = -self.z().div_euclid(2);
+        if depth > 0 {
+            format!("Mazes of Menace: {depth}")
+        } else {
+            "Surface world".into()
+        }
     }
 
     fn ui_path_destination(&self, r: &impl AsRef<Runtime>) -> Self {
@@ -209,7 +222,21 @@ impl RuntimeCoordinates for Location {
         r: &'a impl AsRef<Runtime>,
     ) -> impl Iterator<Item = Entity> + 'a {
         let r = r.as_ref();
-        r.placement.entities_at(*self)
+        let loc = *self;
+
+        // Large mobs are placed at their northwest corner, also look for
+        // ones whose footprint extends over this cell.
+        let covering = Rect::new([1 - MAX_MOB_SIZE, 1 - MAX_MOB_SIZE], [1, 1])
+            .into_iter()
+            .map(v2)
+            .filter(|d| *d != IVec2::ZERO)
+            .flat_map(move |d| {
+                r.placement
+                    .entities_at(loc + d.extend(0))
+                    .filter(move |e| (-d).max_element() < e.size(r))
+            });
+
+        r.placement.entities_at(loc).chain(covering)
     }
 
     fn damage(
@@ -224,3 +251,40 @@ impl RuntimeCoordinates for Location {
         }
     }
 }
+
+/// Shared logic for explored and visible checks, `in_set` tells whether a
+/// floor-snapped location has been seen.
+fn is_seen(
+    r: &Runtime,
+    loc: Location,
+    in_set: impl Fn(Location) -> bool,
+) -> bool {
+    if in_set(loc.snap_above_floor(r)) {
+        return true;
+    }
+
+    if loc.tile(r).is_wall() {
+        // Any 4-adjacent visible cell makes a wall visible.
+        if loc.ns_4().any(|loc| in_set(loc.snap_above_floor(r))) {
+            return true;
+        }
+
+        for diag in s8::DIAGONALS.iter().map(|&p| p.extend(0)) {
+            // This is a corner wall and next to a visible floor.
+            //
+            // Since last step didn't return, there's no directly adjacent
+            // visible floor.
+            //
+            // Must have two adjacent walls to qualify as visible here.
+            if in_set((loc + diag).snap_above_floor(r)) {
+                if (loc + diag * ivec3(1, 0, 0)).tile(r).is_wall()
+                    && (loc + diag * ivec3(0, 1, 0)).tile(r).is_wall()
+                {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
@@ -1,16 +1,23 @@
 //! Entity logic for active creatures.
+use std::fmt;
+
+use glam::ivec3;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use util::v2;
-use world::{Block, Environs, MonsterFlags, Rect};
+use util::{v2, RngExt};
+use world::{
+    Block, Data, Environs, ItemKind, MonsterFlags, Pod, PodObject, Rect,
+    SpawnDist,
+};
 
 use crate::{
     ecs::{
-        ActsNext, Buffs, IsDying, IsEphemeral, IsMob, Momentum, Speed, Stats,
-        Wounds,
+        ActsNext, Buffs, Enchantment, IsDying, IsEphemeral, IsMob, IsPoisonous,
+        IsSneaking, LastHurt, Momentum, Name, NumDeaths, NumKills, Sight, Size,
+        Speed, Stats, Wounds, Xp,
     },
     prelude::*,
-    PHASES_IN_TURN,
+    EntitySpec, FOV_RADIUS, MAX_MOB_SIZE, PHASES_IN_TURN,
 };
 
 impl Entity {
@@ -19,11 +26,19 @@ impl Entity {
         r.player == Some(*self)
     }
 
-    pub fn become_player(&self, r: &mut impl AsMut<Runtime>) {
+    /// Make the entity the new player mob.
+    ///
+    /// Return false and show a message if the entity can't be controlled.
+    pub fn become_player(&self, r: &mut impl AsMut<Runtime>) -> bool {
         let r = r.as_mut();
         let prev_player = r.player();
         if Some(*self) == prev_player {
-            return;
+            return true;
+        }
+
+        if !self.can_become_player(r) {
+            msg!(Warning: "[One] can't be controlled."; self.noun(r));
+            return false;
         }
 
         msg!("You are now [one]."; self.noun(r));
@@ -40,6 +55,8 @@ impl Entity {
                 prev_player.set_goal(r, Goal::FollowPlayer);
             }
         }
+
+        true
     }
 
     pub fn is_mob(&self, r: &impl AsRef<Runtime>) -> bool {
@@ -73,10 +90,64 @@ impl Entity {
         }
     }
 
+    /// Look for the location the mob ends up in when stepping from `loc`.
+    ///
+    /// Incorporeal mobs can move straight through walls.
+    pub fn walk_step(
+        &self,
+        r: &impl AsRef<Runtime>,
+        loc: Location,
+        dir: IVec2,
+    ) -> Option<Location> {
+        let r = r.as_ref();
+        loc.walk_step(r, dir).or_else(|| {
+            self.has_monster_flag(r, MonsterFlags::INCORPOREAL)
+                .then(|| loc + dir.extend(0))
+        })
+    }
+
+    /// Cost of the mob moving into a location.
+    pub fn move_cost(&self, r: &impl AsRef<Runtime>, loc: Location) -> usize {
+        let r = r.as_ref();
+        if self.has_monster_flag(r, MonsterFlags::FLIES) {
+            // Fliers don't care what the ground is like.
+            1
+        } else {
+            loc.move_cost(r)
+        }
+    }
+
+    /// Estimate how many turns it takes the mob to walk along a path.
+    ///
+    /// Terrain is weighted the same way pathfinding weighs it. Return `None`
+    /// if the mob can't move at all.
+    pub fn travel_turns(
+        &self,
+        r: &impl AsRef<Runtime>,
+        path: &[Location],
+    ) -> Option<i64> {
+        let r = r.as_ref();
+        let speed = self.speed(r) as i64;
+        if speed == 0 {
+            return None;
+        }
+
+        let mut phases: i64 =
+            path.iter().map(|&loc| self.move_cost(r, loc) as i64).sum();
+        // Sneaking takes an extra phase for every step.
+        if self.is_sneaking(r) {
+            phases += path.len() as i64;
+        }
+
+        // Mob gets as many phases in a turn as its speed.
+        Some((phases + speed - 1) / speed)
+    }
+
     pub fn can_step(&self, r: &impl AsRef<Runtime>, dir: IVec2) -> bool {
         let r = r.as_ref();
 
-        let Some(n) = self.loc(r).and_then(|loc| loc.walk_step(r, dir)) else {
+        let Some(n) = self.loc(r).and_then(|loc| self.walk_step(r, loc, dir))
+        else {
             return false;
         };
 
@@ -124,6 +195,12 @@ impl Entity {
             return false;
         }
 
+        // Don't push followers out of a formation they've settled into,
+        // otherwise two followers can keep swapping places forever.
+        if other.is_in_formation(r) {
+            return false;
+        }
+
         true
     }
 
@@ -133,7 +210,7 @@ impl Entity {
 
     pub fn acts_this_frame(&self, r: &impl AsRef<Runtime>) -> bool {
         let r = r.as_ref();
-        self.get::<Speed>(r).0 > 0 && self.acts_next(r) <= r.now()
+        self.speed(r) > 0 && self.acts_next(r) <= r.now()
     }
 
     pub fn acts_before_next_player_frame(
@@ -195,7 +272,7 @@ impl Entity {
     pub(crate) fn next_phase_frame(&self, r: &impl AsRef<Runtime>) -> Instant {
         let r = r.as_ref();
         let mut t = self.acts_next(r).max(r.now()) + 1;
-        let speed = self.get::<Speed>(r).0;
+        let speed = self.speed(r);
         assert!(speed > 0);
 
         while !t.is_action_frame(speed) {
@@ -204,6 +281,59 @@ impl Entity {
         t
     }
 
+    /// Current number of move phases the mob gets in a turn, factoring in
+    /// buffs.
+    ///
+    /// Zero speed means the mob never acts.
+    pub fn speed(&self, r: &impl AsRef<Runtime>) -> i8 {
+        let mut speed = self.get::<Speed>(r).0;
+        if speed <= 0 {
+            return 0;
+        }
+
+        if self.has_buff(r, Buff::Haste) {
+            speed = speed.saturating_mul(2);
+        }
+        if self.has_buff(r, Buff::Slow) {
+            speed /= 2;
+        }
+
+        // Can't act more than once per frame.
+        speed.clamp(1, PHASES_IN_TURN as i8)
+    }
+
+    /// How far the mob can see.
+    ///
+    /// Mobs without their own sight stat use the scenario setting or the
+    /// engine default.
+    pub fn sight_radius(&self, r: &impl AsRef<Runtime>) -> i32 {
+        match self.get::<Sight>(r).0 {
+            n if n > 0 => n,
+            _ => match world::settings().sight_radius {
+                n if n > 0 => n,
+                _ => FOV_RADIUS,
+            },
+        }
+    }
+
+    /// Width of the mob's square footprint in cells.
+    pub fn size(&self, r: &impl AsRef<Runtime>) -> i32 {
+        self.get::<Size>(r).0.clamp(1, MAX_MOB_SIZE)
+    }
+
+    /// Cells the entity covers when its origin is at `loc`.
+    ///
+    /// Large mobs extend south and east from their origin cell.
+    pub fn footprint_at(
+        &self,
+        r: &impl AsRef<Runtime>,
+        loc: Location,
+    ) -> impl Iterator<Item = Location> {
+        let size = self.size(r);
+        (0..size)
+            .flat_map(move |y| (0..size).map(move |x| loc + ivec3(x, y, 0)))
+    }
+
     /// Return current stats for an entity, factoring in its equipment.
     ///
     /// This method should always be used when querying the stats of a mob
@@ -211,6 +341,15 @@ impl Entity {
     /// don't include bonuses from equipment.
     pub fn stats(&self, r: &impl AsRef<Runtime>) -> Stats {
         let mut stats = self.get::<Stats>(r);
+
+        // Enchanted weapons hit harder and enchanted armor protects better.
+        let plus = self.get::<Enchantment>(r).0;
+        match self.get::<ItemKind>(r) {
+            ItemKind::MeleeWeapon | ItemKind::RangedWeapon => stats.dmg += plus,
+            ItemKind::Armor => stats.ev += plus,
+            _ => {}
+        }
+
         for (_, e) in self.equipment(r) {
             stats += e.stats(r);
         }
@@ -228,10 +367,22 @@ impl Entity {
 
     pub fn confuse(&self, r: &mut impl AsMut<Runtime>) {
         let r = r.as_mut();
-        msg!("[One] [is] confused."; self.noun(r));
+        msg!(Warning: "[One] [is] confused."; self.noun(r));
         self.buff(r, Buff::Confusion, 40);
     }
 
+    pub fn haste(&self, r: &mut impl AsMut<Runtime>) {
+        let r = r.as_mut();
+        msg!("[One] speed[s] up."; self.noun(r));
+        self.buff(r, Buff::Haste, SPEED_BUFF_DURATION);
+    }
+
+    pub fn slow(&self, r: &mut impl AsMut<Runtime>) {
+        let r = r.as_mut();
+        msg!(Warning: "[One] [is] slowed down."; self.noun(r));
+        self.buff(r, Buff::Slow, SPEED_BUFF_DURATION);
+    }
+
     pub fn buff(&self, r: &mut impl AsMut<Runtime>, buff: Buff, duration: i64) {
         let r = r.as_mut();
         let now = r.now();
@@ -241,7 +392,19 @@ impl Entity {
     pub fn has_buff(&self, r: &impl AsRef<Runtime>, buff: Buff) -> bool {
         let r = r.as_ref();
         self.with::<Buffs, _>(r, |b| {
-            b.get(&buff).map_or(false, |&e| e >= r.now())
+            b.get(&buff).is_some_and(|&e| e >= r.now())
+        })
+    }
+
+    /// Active buffs and the number of turns left on each, rounded up.
+    pub fn active_buffs(&self, r: &impl AsRef<Runtime>) -> Vec<(Buff, i64)> {
+        let r = r.as_ref();
+        let now = r.now();
+        self.with::<Buffs, _>(r, |b| {
+            b.iter()
+                .filter(|(_, &t)| t >= now)
+                .map(|(&b, &t)| (b, (t - now) / PHASES_IN_TURN + 1))
+                .collect()
         })
     }
 
@@ -265,6 +428,32 @@ impl Entity {
         self.has_buff(r, Buff::Confusion)
     }
 
+    pub fn is_sneaking(&self, r: &impl AsRef<Runtime>) -> bool {
+        self.get::<IsSneaking>(r).0
+    }
+
+    /// Start or stop sneaking.
+    pub(crate) fn toggle_sneak(&self, r: &mut impl AsMut<Runtime>) {
+        let r = r.as_mut();
+
+        if self.is_sneaking(r) {
+            self.stop_sneaking(r);
+        } else {
+            self.set(r, IsSneaking(true));
+            msg!("[One] start[s] sneaking."; self.noun(r));
+        }
+    }
+
+    /// Break stealth after getting noticed or starting a fight.
+    pub(crate) fn stop_sneaking(&self, r: &mut impl AsMut<Runtime>) {
+        let r = r.as_mut();
+
+        if self.is_sneaking(r) {
+            self.set(r, IsSneaking(false));
+            msg!("[One] stop[s] sneaking."; self.noun(r));
+        }
+    }
+
     pub fn max_wounds(&self, r: &impl AsRef<Runtime>) -> i32 {
         5 + self.get::<Stats>(r).level.max(0) * 5
     }
@@ -273,6 +462,68 @@ impl Entity {
         self.get::<Wounds>(r).0
     }
 
+    /// How many turns it takes the mob to passively heal a wound.
+    ///
+    /// Only the player regenerates passively, and does it faster at higher
+    /// levels.
+    pub fn regen_interval(&self, r: &impl AsRef<Runtime>) -> Option<i64> {
+        let r = r.as_ref();
+
+        let base = world::settings().regen_interval;
+        if base <= 0 || !self.is_player(r) {
+            return None;
+        }
+
+        let level = self.get::<Stats>(r).level.max(0) as i64;
+        Some((base / (1 + level)).max(1))
+    }
+
+    /// Heal a wound at the start of every turn if the mob regenerates.
+    ///
+    /// The player heals a wound once they haven't been hurt for
+    /// `regen_delay` turns, and then every `regen_interval` turns.
+    pub(crate) fn regenerate(&self, r: &mut impl AsMut<Runtime>) {
+        let r = r.as_mut();
+
+        if r.now().0.rem_euclid(PHASES_IN_TURN) != 0 {
+            return;
+        }
+
+        let wounds = self.wounds(r);
+        if wounds <= 0 {
+            return;
+        }
+
+        let heals = if self.has_monster_flag(r, MonsterFlags::REGENERATES) {
+            true
+        } else if let Some(interval) = self.regen_interval(r) {
+            let delay = world::settings().regen_delay.max(0);
+            let unhurt = (r.now() - self.get::<LastHurt>(r).0) / PHASES_IN_TURN;
+            unhurt >= delay && (unhurt - delay) % interval == 0
+        } else {
+            false
+        };
+
+        if heals {
+            self.set(r, Wounds(wounds - 1));
+        }
+    }
+
+    /// Take poison damage every turn while poisoned.
+    pub(crate) fn suffer_poison(&self, r: &mut impl AsMut<Runtime>) {
+        const POISON_DAMAGE: i32 = 1;
+
+        let r = r.as_mut();
+
+        if r.now().0.rem_euclid(PHASES_IN_TURN) != 0
+            || !self.has_buff(r, Buff::Poison)
+        {
+            return;
+        }
+
+        self.damage(r, None, POISON_DAMAGE);
+    }
+
     pub fn damage(
         &self,
         r: &mut impl AsMut<Runtime>,
@@ -281,17 +532,55 @@ impl Entity {
     ) {
         let r = r.as_mut();
 
+        if self.is_invulnerable(r) {
+            return;
+        }
+
         let mut wounds = self.wounds(r);
         wounds += amount;
         self.set(r, Wounds(wounds));
         if amount > 0 {
+            self.set(r, LastHurt(r.now()));
             send_msg(Msg::Hurt(*self));
+            send_msg(Msg::Damage(*self, amount));
         }
         if wounds >= self.max_wounds(r) {
             self.die(r, perp);
         }
     }
 
+    /// Pick an item from the monster's loot table to drop on death.
+    fn roll_loot(&self, r: &mut impl AsMut<Runtime>) -> Option<Pod> {
+        let r = r.as_mut();
+        let loot: Vec<PodObject> = Data::get()
+            .loot
+            .get(self.base_desc(r).as_str())
+            .into_iter()
+            .flatten()
+            .filter_map(|a| a.0.parse().ok())
+            .collect();
+        let choice =
+            r.rng().weighted_choice(&loot, |a| a.kind.spawn_weight())?;
+        choice.to_string().parse().ok()
+    }
+
+    fn leave_corpse(&self, r: &mut impl AsMut<Runtime>, loc: Location) {
+        let r = r.as_mut();
+
+        let is_poisonous = self.has_monster_flag(r, MonsterFlags::POISONOUS);
+        if !is_poisonous && !self.has_monster_flag(r, MonsterFlags::CORPSE) {
+            return;
+        }
+
+        let corpse = world::Item {
+            kind: ItemKind::Corpse,
+            ..Default::default()
+        }
+        .build(r, &format!("{} corpse", self.base_desc(r)));
+        corpse.set(r, IsPoisonous(is_poisonous));
+        corpse.place(r, loc);
+    }
+
     pub fn die(&self, r: &mut impl AsMut<Runtime>, perp: Option<Entity>) {
         let r = r.as_mut();
 
@@ -304,7 +593,8 @@ impl Entity {
         if let Some(loc) = self.loc(r) {
             // Effects.
             if let Some(perp) = perp {
-                msg!("[One] kill[s] [another]."; perp.noun(r), self.noun(r));
+                msg!(Combat: "[One] kill[s] [another].";
+                    perp.noun(r), self.noun(r));
             } else {
                 // Exploding mobs go "the ooze explodes" instead later in the
                 // function, so avoid the redundant message here.
@@ -314,7 +604,7 @@ impl Entity {
                 // extra information, so this condition only exists in this
                 // branch.
                 if !self.has_monster_flag(r, MonsterFlags::EXPLODES) {
-                    msg!("[One] die[s]."; self.noun(r));
+                    msg!(Combat: "[One] die[s]."; self.noun(r));
                 }
             }
 
@@ -359,6 +649,19 @@ impl Entity {
             return;
         }
 
+        // Player side gets experience from killing enemies.
+        if perp.is_some_and(|p| p.is_player_aligned(r))
+            && !self.is_player_aligned(r)
+        {
+            if let Some(player) = r.player() {
+                let xp = self.xp_value(r);
+                player.gain_xp(r, xp);
+
+                let num_kills = player.num_kills(r);
+                player.set(r, NumKills(num_kills + 1));
+            }
+        }
+
         if let Some(loc) = self.loc(r) {
             // Drop stuff on floor.
             for e in self.contents(r).collect::<Vec<_>>() {
@@ -366,6 +669,12 @@ impl Entity {
             }
 
             if !self.is_player_aligned(r) && !self.is_ephemeral(r) {
+                if let Some(loot) = self.roll_loot(r) {
+                    r.spawn_at(&loot, loc);
+                }
+
+                self.leave_corpse(r, loc);
+
                 let level = self.get::<Stats>(r).level;
                 if level > 0 {
                     // XXX: Formula for cash dropped per enemy prolly needs
@@ -383,6 +692,8 @@ impl Entity {
 
         // Boss death
         if self.has_monster_flag(r, MonsterFlags::BOSS) {
+            r.bosses_killed += 1;
+
             // Find the spawn position of self in samsara
             if let Some(origin) = self.spawn_origin(r) {
                 // Bosses are immediately removed from samsara when killed.
@@ -402,7 +713,7 @@ impl Entity {
         // Explodey enemy. Do a similar effect to a fireball spell.
         if self.has_monster_flag(r, MonsterFlags::EXPLODES) {
             let explode_damage = self.stats(r).level;
-            msg!("[One] explode[s]."; self.noun(r));
+            msg!(Combat: "[One] explode[s]."; self.noun(r));
             if let Some(loc) = self.loc(r) {
                 send_msg(Msg::Explosion(loc));
                 for p in Rect::new([-1, -1], [2, 2]) {
@@ -434,15 +745,67 @@ impl Entity {
         (100.0 * 1.05f32.powi(level)) as i32
     }
 
+    pub fn xp(&self, r: &impl AsRef<Runtime>) -> i32 {
+        self.get::<Xp>(r).0
+    }
+
+    pub fn num_deaths(&self, r: &impl AsRef<Runtime>) -> i32 {
+        self.get::<NumDeaths>(r).0
+    }
+
+    pub fn num_kills(&self, r: &impl AsRef<Runtime>) -> i32 {
+        self.get::<NumKills>(r).0
+    }
+
+    /// Experience needed to gain the next level.
+    pub fn xp_to_level_up(&self, r: &impl AsRef<Runtime>) -> i32 {
+        let level = self.get::<Stats>(r).level;
+        (50.0 * 1.25f32.powi(level)) as i32
+    }
+
+    /// Experience gained from killing this mob.
+    pub fn xp_value(&self, r: &impl AsRef<Runtime>) -> i32 {
+        let level = self.get::<Stats>(r).level.max(0);
+        // Rarer monsters are worth more.
+        let rarity = Data::get()
+            .bestiary
+            .get(self.get::<Name>(r).0.as_str())
+            .map_or(0, |m| m.rarity as i32);
+        (level + 1) * 10 + rarity / 5
+    }
+
+    pub fn gain_xp(&self, r: &mut impl AsMut<Runtime>, amount: i32) {
+        let r = r.as_mut();
+
+        let was_ready = self.has_xp_for_level_up(r);
+        let xp = self.xp(r) + amount;
+        self.set(r, Xp(xp));
+        if !was_ready && self.has_xp_for_level_up(r) {
+            msg!("[One] [is] ready to grow stronger."; self.noun(r));
+        }
+    }
+
+    pub fn has_xp_for_level_up(&self, r: &impl AsRef<Runtime>) -> bool {
+        self.xp(r) >= self.xp_to_level_up(r)
+    }
+
     pub fn can_afford_level_up(&self, r: &impl AsRef<Runtime>) -> bool {
-        self.carried_cash(r) >= self.level_up_cost(r)
+        self.has_xp_for_level_up(r)
+            || self.carried_cash(r) >= self.level_up_cost(r)
     }
 
-    /// The level up action, must have enough cash to do it.
+    /// The level up action, must have enough experience or cash to do it.
+    ///
+    /// Experience is used first if there's enough of it.
     pub fn player_level_up(&self, r: &mut impl AsMut<Runtime>) {
         let r = r.as_mut();
 
-        if self.subtract_cash(r, self.level_up_cost(r)) {
+        if self.has_xp_for_level_up(r) {
+            let xp = self.xp(r) - self.xp_to_level_up(r);
+            self.set(r, Xp(xp));
+            msg!("[One] feel[s] stronger from experience."; self.noun(r));
+            self.level_up(r);
+        } else if self.subtract_cash(r, self.level_up_cost(r)) {
             msg!("[One] offer[s] a sacrifice and feel[s] stronger."; self.noun(r));
             self.level_up(r);
         } else {
@@ -451,6 +814,9 @@ impl Entity {
     }
 }
 
+/// How long haste and slow last.
+const SPEED_BUFF_DURATION: i64 = 20 * PHASES_IN_TURN;
+
 /// Status effects.
 #[derive(
     Copy,
@@ -466,6 +832,23 @@ impl Entity {
 )]
 pub enum Buff {
     Confusion,
+    /// Doubles speed.
+    Haste,
+    /// Halves speed.
+    Slow,
+    /// Takes damage every turn.
+    Poison,
+}
+
+impl fmt::Display for Buff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Buff::Confusion => write!(f, "Confused"),
+            Buff::Haste => write!(f, "Hasted"),
+            Buff::Slow => write!(f, "Slowed"),
+            Buff::Poison => write!(f, "Poisoned"),
+        }
+    }
 }
 
 impl Buff {
@@ -475,6 +858,322 @@ impl Buff {
             Buff::Confusion => {
                 msg!("[One] [is] no longer confused."; noun);
             }
+            Buff::Haste => {
+                msg!("[One] slow[s] down."; noun);
+            }
+            Buff::Slow => {
+                msg!("[One] speed[s] back up."; noun);
+            }
+            Buff::Poison => {
+                msg!("[One] [is] no longer poisoned."; noun);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::ivec3;
+    use world::{Block, Environs, Monster, MonsterFlags, Power, Rect};
+
+    use super::Buff;
+    use crate::{
+        ecs::{IsEphemeral, IsFriendly, Speed},
+        prelude::*,
+        test_util::{fill_area, free_step, open_area, runtime, spawn_monster},
+        EntitySpec, PHASES_IN_TURN,
+    };
+
+    fn spawn_next_to_player(r: &mut Runtime, flags: MonsterFlags) -> Entity {
+        let loc = r.player().unwrap().loc(r).unwrap();
+        let (_, dest) = free_step(r, loc);
+        let mob = spawn_monster(r, 3, dest);
+        mob.set(r, flags);
+        mob
+    }
+
+    /// Have the mobs pass for the given number of turns and count how many
+    /// times each one got to act.
+    ///
+    /// A turn is spent first to let the mobs settle into their action
+    /// frames after a speed change.
+    fn count_moves<const N: usize>(
+        r: &mut Runtime,
+        mobs: [Entity; N],
+        turns: i64,
+    ) -> [usize; N] {
+        let mut ret = [0; N];
+        for t in 0..PHASES_IN_TURN * (turns + 1) {
+            for (mob, n) in mobs.iter().zip(ret.iter_mut()) {
+                if mob.acts_this_frame(r) {
+                    mob.execute_indirect(r, Action::Pass);
+                    if t >= PHASES_IN_TURN {
+                        *n += 1;
+                    }
+                }
+            }
+            r.tick();
+        }
+        ret
+    }
+
+    #[test]
+    fn exploding_mob() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let ooze = spawn_next_to_player(&mut r, MonsterFlags::EXPLODES);
+        let wounds = player.wounds(&r);
+
+        ooze.die(&mut r, None);
+
+        assert!(!ooze.is_alive(&r));
+        // Explosion damage is the level of the exploding mob.
+        assert_eq!(player.wounds(&r), wounds + 3);
+    }
+
+    #[test]
+    fn loot_drop() {
+        let mut r = runtime();
+        let loc = r.player().unwrap().loc(&r).unwrap();
+        // Trolls have a single entry in their loot table.
+        let troll = r.spawn_at(&"troll".parse().unwrap(), loc)[0];
+        let loc = troll.loc(&r).unwrap();
+
+        troll.die(&mut r, None);
+
+        assert!(loc.entities_at(&r).any(|e| e.base_desc(&r) == "greatsword"));
+    }
+
+    #[test]
+    fn corpse() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+
+        // Goblins leave wholesome corpses.
+        let goblin = r.spawn_at(&"goblin".parse().unwrap(), loc)[0];
+        let loc = goblin.loc(&r).unwrap();
+        goblin.die(&mut r, None);
+        let corpse = loc
+            .entities_at(&r)
+            .find(|e| e.base_desc(&r) == "goblin corpse")
+            .expect("Goblin didn't leave a corpse");
+        assert!(corpse.can_be_eaten(&r));
+
+        player.damage(&mut r, None, 5);
+        player.execute_direct(&mut r, Action::Eat(corpse));
+        assert_eq!(player.wounds(&r), 2);
+        assert!(corpse.loc(&r).is_none());
+
+        // Snakes leave poisonous ones.
+        let snake = r.spawn_at(&"snake".parse().unwrap(), loc)[0];
+        let loc = snake.loc(&r).unwrap();
+        snake.die(&mut r, None);
+        let corpse = loc
+            .entities_at(&r)
+            .find(|e| e.base_desc(&r) == "snake corpse")
+            .expect("Snake didn't leave a corpse");
+
+        player.execute_direct(&mut r, Action::Eat(corpse));
+        assert!(player.has_buff(&r, Buff::Poison));
+        assert_eq!(player.wounds(&r), 2);
+    }
+
+    #[test]
+    fn large_mob() {
+        let mut r = runtime();
+        let center = r.player().unwrap().loc(&r).unwrap();
+
+        // Clear out an open room next to the player.
+        open_area(&mut r, center, Rect::new([-3, -3], [4, 4]));
+
+        let mob = Monster {
+            icon: 'x',
+            size: 2,
+            ..Default::default()
+        }
+        .build(&mut r, "monster");
+        let origin = center + ivec3(1, 0, 0);
+        mob.place(&mut r, origin);
+
+        let footprint: Vec<Location> = mob.footprint_at(&r, origin).collect();
+        assert_eq!(footprint.len(), 4);
+        for loc in &footprint {
+            assert_eq!(loc.mob_at(&r), Some(mob));
+        }
+        assert_eq!((origin + ivec3(2, 0, 0)).mob_at(&r), None);
+        assert_eq!((origin + ivec3(0, 2, 0)).mob_at(&r), None);
+
+        mob.destroy(&mut r);
+        for loc in &footprint {
+            assert_eq!(loc.mob_at(&r), None);
+        }
+    }
+
+    #[test]
+    fn regenerating_mob() {
+        let mut r = runtime();
+        let troll = spawn_next_to_player(&mut r, MonsterFlags::REGENERATES);
+        troll.damage(&mut r, None, 10);
+
+        for _ in 0..PHASES_IN_TURN * 5 {
+            r.tick();
+        }
+
+        // One wound healed per turn.
+        assert_eq!(troll.wounds(&r), 5);
+    }
+
+    #[test]
+    fn experience_level_up() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let max_wounds = player.max_wounds(&r);
+
+        let mob = spawn_next_to_player(&mut r, MonsterFlags::empty());
+        mob.die(&mut r, Some(player));
+        assert!(player.xp(&r) > 0);
+
+        while !player.has_xp_for_level_up(&r) {
+            let mob = spawn_next_to_player(&mut r, MonsterFlags::empty());
+            mob.die(&mut r, Some(player));
+        }
+
+        let xp = player.xp(&r);
+        player.execute_direct(&mut r, Action::LevelUp);
+        assert!(player.xp(&r) < xp);
+        assert!(player.max_wounds(&r) > max_wounds);
+    }
+
+    #[test]
+    fn mob_speed() {
+        let mut r = runtime();
+        let fast = spawn_next_to_player(&mut r, MonsterFlags::empty());
+        fast.set(&mut r, Speed(6));
+        let slow = spawn_next_to_player(&mut r, MonsterFlags::empty());
+
+        let [fast_moves, slow_moves] = count_moves(&mut r, [fast, slow], 4);
+        assert!(slow_moves > 0);
+        assert_eq!(fast_moves, 2 * slow_moves);
+
+        // Hasted slow mob keeps up with the fast one.
+        slow.haste(&mut r);
+        let [fast_moves, slow_moves] = count_moves(&mut r, [fast, slow], 4);
+        assert_eq!(fast_moves, slow_moves);
+    }
+
+    #[test]
+    fn haste_wears_off() {
+        let mut r = runtime();
+        let mob = spawn_next_to_player(&mut r, MonsterFlags::empty());
+
+        let [normal] = count_moves(&mut r, [mob], 4);
+        assert!(normal > 0);
+
+        let loc = mob.loc(&r).unwrap();
+        r.invoke_power(Power::Haste, Some(mob), loc, ivec2(0, 0));
+        assert!(mob.has_buff(&r, Buff::Haste));
+        assert_eq!(count_moves(&mut r, [mob], 4), [2 * normal]);
+
+        // Run out the buff.
+        while mob.has_buff(&r, Buff::Haste) {
+            r.tick();
+        }
+        assert!(mob.active_buffs(&r).is_empty());
+        assert_eq!(count_moves(&mut r, [mob], 4), [normal]);
+    }
+
+    #[test]
+    fn player_regenerates() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let interval = player.regen_interval(&r).unwrap();
+        let delay = world::settings().regen_delay;
+
+        // Return the turns during which the player healed.
+        let heal_turns = |r: &mut Runtime, turns| {
+            let mut ret = Vec::new();
+            for turn in 1..=turns {
+                let wounds = player.wounds(r);
+                for _ in 0..PHASES_IN_TURN {
+                    r.tick();
+                }
+                if player.wounds(r) < wounds {
+                    ret.push(turn);
+                }
+            }
+            ret
+        };
+
+        player.damage(&mut r, None, 3);
+        assert_eq!(player.wounds(&r), 3);
+
+        // Nothing happens during the grace period, then wounds heal at the
+        // configured interval.
+        let healed = heal_turns(&mut r, delay + 2 * interval);
+        assert_eq!(healed.len(), 2);
+        assert!(healed[0] > delay);
+        assert_eq!(healed[1] - healed[0], interval);
+        assert_eq!(player.wounds(&r), 1);
+
+        // Taking damage starts the grace period over.
+        player.damage(&mut r, None, 1);
+        assert!(heal_turns(&mut r, delay).is_empty());
+        assert_eq!(player.wounds(&r), 2);
+        assert_eq!(heal_turns(&mut r, interval).len(), 1);
+    }
+
+    #[test]
+    fn cannot_control_summons() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let summon = spawn_next_to_player(&mut r, MonsterFlags::empty());
+        summon.set(&mut r, IsFriendly(true));
+        summon.set(&mut r, IsEphemeral(true));
+
+        let recv = Receiver::default();
+        assert!(!summon.become_player(&mut r));
+        assert_eq!(r.player(), Some(player));
+        assert!(recv.try_iter().any(|m| matches!(
+            m,
+            Msg::Message(MsgKind::Warning, text)
+                if text == "The monster can't be controlled."
+        )));
+
+        // Permanent allies can still be taken over.
+        summon.set(&mut r, IsEphemeral(false));
+        assert!(summon.become_player(&mut r));
+        assert_eq!(r.player(), Some(summon));
+    }
+
+    #[test]
+    fn travel_estimate() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+        assert_eq!(player.speed(&r), 3);
+
+        // Dig a straight corridor east from the player.
+        fill_area(&mut r, loc, Rect::new([-1, -1], [8, 2]));
+        for x in 0..=6 {
+            r.set_voxel(loc + ivec3(x, 0, 0), None);
+        }
+
+        let path = r
+            .find_path(
+                FogPathing::Ignore,
+                loc,
+                &world::Cube::unit(loc + ivec3(6, 0, 0)),
+            )
+            .unwrap();
+        assert_eq!(path.len(), 6);
+        // Six steps at three steps per turn.
+        assert_eq!(player.travel_turns(&r, &path), Some(2));
+
+        // Rough ground takes longer to cross.
+        for x in 1..=6 {
+            r.set_voxel(loc + ivec3(x, 0, -1), Some(Block::SplatteredRock));
         }
+        assert_eq!(player.travel_turns(&r, &path), Some(4));
     }
 }
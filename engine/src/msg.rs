@@ -1,8 +1,8 @@
 //! Emitting messages about instant events to the UI layer
 
-use std::sync::{
-    mpsc::{self, Sender},
-    LazyLock, Mutex,
+use std::{
+    cell::RefCell,
+    sync::mpsc::{self, Sender},
 };
 
 use anyhow::bail;
@@ -14,7 +14,7 @@ use crate::prelude::*;
 /// Interface for receiving game event messages for displaying.
 pub enum Msg {
     /// Text message.
-    Message(String),
+    Message(MsgKind, String),
 
     /// Entity e shot a projectile towards direction.
     Fire(Entity, IVec2),
@@ -22,6 +22,9 @@ pub enum Msg {
     /// Entity is hurt.
     Hurt(Entity),
 
+    /// Entity took an amount of damage.
+    Damage(Entity, i32),
+
     /// An attack missed an entity.
     Miss(Entity),
 
@@ -42,26 +45,59 @@ pub enum Msg {
 
     /// Altar was activated, run client-side altar menu.
     ActivatedAltar(Location),
+
+    /// Player bumped into a merchant, run client-side trade menu.
+    Trade(Entity),
+
+    /// Player bumped into someone to talk to, run client-side dialogue.
+    Talk(Entity),
 }
 
-static RCV: LazyLock<Mutex<Option<Sender<Msg>>>> =
-    LazyLock::new(Default::default);
+/// Category of a text message, used by the UI to color and filter messages.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum MsgKind {
+    /// Neutral message.
+    #[default]
+    Info,
+    /// Fighting and getting hurt.
+    Combat,
+    /// Finding things and learning about the world.
+    Discovery,
+    /// Something that needs the player's attention.
+    Warning,
+    /// Notes about the game program itself, saving, version etc.
+    System,
+}
 
+// The channel is per thread so that runtimes on separate threads, like
+// parallel unit tests, don't send messages to each other's receivers.
+thread_local! {
+    static RCV: RefCell<Option<Sender<Msg>>> = const { RefCell::new(None) };
+}
+
+/// Receiver for the messages sent on the current thread.
+///
+/// Creating a new receiver replaces the previous one.
 #[derive(Deref)]
 pub struct Receiver(mpsc::Receiver<Msg>);
 
 impl Default for Receiver {
     fn default() -> Self {
         let (send, recv) = mpsc::channel();
-        *RCV.lock().unwrap() = Some(send);
+        RCV.with_borrow_mut(|rcv| *rcv = Some(send));
         Receiver(recv)
     }
 }
 
 pub fn send_msg(msg: Msg) {
-    if let Some(ref mut sender) = *RCV.lock().unwrap() {
-        sender.send(msg).expect("Msg channel failure");
-    }
+    RCV.with_borrow_mut(|rcv| {
+        if let Some(sender) = rcv {
+            if sender.send(msg).is_err() {
+                // Receiver was dropped, nobody is listening anymore.
+                *rcv = None;
+            }
+        }
+    });
 }
 
 pub trait Grammatize {
@@ -87,28 +123,49 @@ impl Grammatize for (Noun, Noun) {
     }
 }
 
+/// Send a text message to the UI.
+///
+/// The message can be prefixed with a `MsgKind` category,
+/// `msg!(Warning: "[One] [is] out of ammo."; noun)`. Messages without one are
+/// `MsgKind::Info`.
 #[macro_export]
 macro_rules! msg {
+    ($kind:ident: $($arg:tt)*) => {
+        $crate::send_msg($crate::Msg::Message(
+            $crate::MsgKind::$kind,
+            $crate::msg_text!($($arg)*),
+        ))
+    };
+
+    ($($arg:tt)*) => {
+        $crate::send_msg($crate::Msg::Message(
+            $crate::MsgKind::default(),
+            $crate::msg_text!($($arg)*),
+        ))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! msg_text {
     // NB. Even the simple cases needs to be wrapped in `format!` in case the
     // fmt string is doing named variable capture.
     ($fmt:expr) => {
-        $crate::send_msg($crate::Msg::Message(format!($fmt)))
+        format!($fmt)
     };
 
     ($fmt:expr, $($arg:expr),*) => {
-        let __txt = format!($fmt, $($arg),*);
-        $crate::send_msg($crate::Msg::Message(__txt))
+        format!($fmt, $($arg),*)
     };
 
     ($fmt:expr; $($grammar_arg:expr),*) => {
-        let __txt = format!($fmt);
-        let __txt = $crate::Grammatize::format(&($($grammar_arg,)*), &__txt);
-        $crate::send_msg($crate::Msg::Message(__txt))
+        $crate::Grammatize::format(&($($grammar_arg,)*), &format!($fmt))
     };
 
     ($fmt:expr, $($arg:expr),*; $($grammar_arg:expr),*) => {
-        let __txt = format!($fmt, $($arg),*);
-        let __txt = $crate::Grammatize::format(&($($grammar_arg,)*), &__txt);
-        $crate::send_msg($crate::Msg::Message(__txt))
+        $crate::Grammatize::format(
+            &($($grammar_arg,)*),
+            &format!($fmt, $($arg),*),
+        )
     };
 }
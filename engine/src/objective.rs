@@ -0,0 +1,148 @@
+//! Scenario objectives that decide when the game is won.
+
+use serde::{Deserialize, Serialize};
+use world::{MonsterFlags, Objective};
+
+use crate::prelude::*;
+
+fn is_met(objective: &Objective, r: &Runtime) -> bool {
+    match objective {
+        Objective::DefeatBosses => {
+            // Bosses in parts of the world that haven't been generated yet
+            // must be killed too, but also don't count a win before any
+            // bosses have even shown up.
+            r.bosses_killed > 0
+                && r.bosses_killed >= r.world.num_bosses()
+                && !r
+                    .live_entities()
+                    .any(|e| e.has_monster_flag(r, MonsterFlags::BOSS))
+        }
+        Objective::ReachDepth(depth) => r
+            .player()
+            .and_then(|p| p.loc(r))
+            .is_some_and(|loc| -loc.z().div_euclid(2) >= *depth),
+        Objective::Retrieve(name) => r
+            .player()
+            .is_some_and(|p| p.inventory(r).any(|e| e.base_desc(r) == *name)),
+    }
+}
+
+/// Objective in the quest log and whether it has been completed.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Quest {
+    pub objective: Objective,
+    pub is_completed: bool,
+}
+
+impl From<Objective> for Quest {
+    fn from(objective: Objective) -> Self {
+        Quest {
+            objective,
+            is_completed: false,
+        }
+    }
+}
+
+impl Runtime {
+    /// Quest log of the scenario.
+    pub fn quests(&self) -> &[Quest] {
+        &self.quests
+    }
+
+    pub fn add_quest(&mut self, objective: Objective) {
+        self.quests.push(objective.into());
+    }
+
+    /// Mark newly completed quests.
+    ///
+    /// Completed quests stay completed even if the objective stops being met.
+    pub(crate) fn update_quests(&mut self) {
+        let met: Vec<bool> = self
+            .quests
+            .iter()
+            .map(|q| q.is_completed || is_met(&q.objective, self))
+            .collect();
+
+        for (q, is_met) in self.quests.iter_mut().zip(met) {
+            if is_met && !q.is_completed {
+                q.is_completed = true;
+                msg!(Discovery: "Objective complete: {}.", q.objective);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use world::{MonsterFlags, Objective};
+
+    use crate::{
+        prelude::*,
+        test_util::{free_step, runtime},
+        PHASES_IN_TURN,
+    };
+
+    fn spawn_boss(r: &mut Runtime) -> Entity {
+        let loc = r.player().unwrap().loc(r).unwrap();
+        let (_, dest) = free_step(r, loc);
+        r.spawn_at(&"goblin king".parse().unwrap(), dest)[0]
+    }
+
+    fn live_bosses(r: &Runtime) -> Vec<Entity> {
+        r.live_entities()
+            .filter(|e| e.has_monster_flag(r, MonsterFlags::BOSS))
+            .collect()
+    }
+
+    fn pass_turn(r: &mut Runtime) {
+        for _ in 0..PHASES_IN_TURN {
+            r.tick();
+        }
+    }
+
+    #[test]
+    fn defeat_boss() {
+        let mut r = runtime();
+        assert!(r
+            .quests()
+            .iter()
+            .any(|q| q.objective == Objective::DefeatBosses));
+
+        let boss = spawn_boss(&mut r);
+        pass_turn(&mut r);
+        assert_eq!(r.scenario_status(), ScenarioStatus::Ongoing);
+
+        // Take out every boss that's around, the last one ends the game.
+        let bosses = live_bosses(&r);
+        assert!(bosses.contains(&boss));
+        for e in bosses {
+            e.die(&mut r, None);
+        }
+
+        pass_turn(&mut r);
+        assert!(r.quests().iter().all(|q| q.is_completed));
+        assert_eq!(r.scenario_status(), ScenarioStatus::Won);
+    }
+
+    #[test]
+    fn win_after_all_bosses() {
+        let mut r = runtime();
+        spawn_boss(&mut r);
+        spawn_boss(&mut r);
+
+        let mut bosses = live_bosses(&r);
+        assert!(bosses.len() >= 2);
+        let last = bosses.pop().unwrap();
+
+        for e in bosses {
+            e.die(&mut r, None);
+            r.update_quests();
+            assert_eq!(r.scenario_status(), ScenarioStatus::Ongoing);
+        }
+
+        last.die(&mut r, None);
+        r.update_quests();
+        assert_eq!(r.scenario_status(), ScenarioStatus::Won);
+    }
+}
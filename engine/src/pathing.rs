@@ -60,12 +60,26 @@ impl Runtime {
     /// Find a path from a starting point to a target volume.
     ///
     /// Intended for short-range pathfinding, not spanning multiple sectors.
+    /// Every step costs the same, use `find_path_with_cost` to make the
+    /// path avoid difficult terrain.
     pub fn find_path(
         &self,
         fog_behavior: FogPathing,
         start: Location,
         // Destination volume.
         dest: &Cube,
+    ) -> Option<Vec<Location>> {
+        self.find_path_with_cost(fog_behavior, start, dest, |_| 1)
+    }
+
+    /// Like `find_path`, but with a custom cost for walking into each
+    /// location.
+    pub fn find_path_with_cost(
+        &self,
+        fog_behavior: FogPathing,
+        start: Location,
+        dest: &Cube,
+        cost: impl Fn(Location) -> usize,
     ) -> Option<Vec<Location>> {
         // NB. This cannot navigate between sectors that aren't directly
         // connected by moving off to the side. This is by design, if you need
@@ -93,7 +107,7 @@ impl Runtime {
                     // Walk normally when you know where you're going.
                     if let Some(loc_2) = loc.walk_step(self, dir) {
                         if in_domain(loc_2) {
-                            ret.push((loc_2, 1));
+                            ret.push((loc_2, cost(loc_2)));
                         }
                     }
                 } else if fog_behavior == Explore {
@@ -114,8 +128,12 @@ impl Runtime {
             ret
         };
 
-        let (mut path, _) =
-            astar(&start, neighbors, |&a| dest.sd(a), |&a| dest.sd(a) <= 0)?;
+        let (mut path, _) = astar(
+            &start,
+            neighbors,
+            |&a| dest.sd(a) as usize,
+            |&a| dest.sd(a) <= 0,
+        )?;
 
         path.reverse();
         path.pop();
@@ -138,6 +156,51 @@ impl Runtime {
         .map(|n| n.0)
     }
 
+    /// Locations where a sound made at `origin` can be heard, along with
+    /// the distance the sound travels to get there.
+    ///
+    /// Sound travels around walls instead of through them and gets muffled
+    /// by doors, so it follows corridors instead of passing through solid
+    /// rock.
+    pub fn sound_from(
+        &self,
+        origin: Location,
+        radius: i32,
+    ) -> impl Iterator<Item = (Location, usize)> + '_ {
+        let radius = radius.max(0) as usize;
+        util::dijkstra_map_weighted(
+            move |&loc| {
+                let mut ret = Vec::new();
+                for d in s4::DIR {
+                    let side = loc + d.extend(0);
+                    for loc2 in [side, side.above(), side.below()] {
+                        if !origin.sector().fat().contains(loc2) {
+                            continue;
+                        }
+                        // Sound only goes up or down a step through open
+                        // space, don't let it leak over the tops of walls.
+                        if loc2 == side.above()
+                            && loc.above().sound_cost(self).is_none()
+                        {
+                            continue;
+                        }
+                        if loc2 == side.below()
+                            && side.sound_cost(self).is_none()
+                        {
+                            continue;
+                        }
+                        if let Some(cost) = loc2.sound_cost(self) {
+                            ret.push((loc2, cost));
+                        }
+                    }
+                }
+                ret
+            },
+            [origin],
+        )
+        .take_while(move |&(_, dist)| dist <= radius)
+    }
+
     /// Start filling positions around given location while staying within
     /// the same sector and on walkable tiles.
     pub fn perturbed_fill_positions(
@@ -1,8 +1,9 @@
 //! Special powers entities can use
 
+use rand::distributions::Distribution;
 use serde::{Deserialize, Serialize};
-use util::{v2, Neighbors2D};
-use world::{Pod, PodObject, Power, Rect, Zone};
+use util::{s8, v2, Neighbors2D};
+use world::{EquippedAt, Pod, PodObject, Power, Rect, Tile, Zone};
 
 use crate::{
     ecs::{self, Powers, Wounds},
@@ -25,10 +26,100 @@ impl Runtime {
             Fireball => self.fireball(perp, loc, v),
             MagicMapping => self.magic_map(perp, loc),
             HealSelf => self.heal(perp, loc),
+            EnchantWeapon => self.enchant_weapon(perp),
+            Knockback => self.knockback(perp, loc, v),
             Summon(monster) => self.summon_monster(perp, loc, monster),
+            Haste => self.haste(perp),
+            Slow => self.slow(perp, loc, v),
+            Teleport => self.teleport_self(perp),
+            TeleportOther => self.teleport_other(perp, loc, v),
         }
     }
 
+    /// Teleport a mob within its current sector.
+    ///
+    /// A random teleport lands near a random spot in the sector, a
+    /// controlled one near `target`. The mob is moved to the closest spot to
+    /// that where it can stand and that has no other mob in it. Returns the
+    /// landing spot or `None` if there was nowhere to go.
+    pub fn teleport(
+        &mut self,
+        e: Entity,
+        target: Option<Location>,
+    ) -> Option<Location> {
+        let sector = e.loc(self)?.sector();
+        let near = match target {
+            Some(loc) => loc,
+            None => sector.sample(self.rng()),
+        };
+
+        let dest = self.safe_landing(e, near)?;
+        msg!("[One] blink[s] away."; e.noun(self));
+        e.place(self, dest);
+        Some(dest)
+    }
+
+    /// Find the closest spot to `near` in its sector that `e` can safely
+    /// teleport into.
+    fn safe_landing(&self, e: Entity, near: Location) -> Option<Location> {
+        let zone = near.sector().fat();
+        let center = near.truncate();
+        let radius = SECTOR_WIDTH.max(SECTOR_HEIGHT);
+
+        s8::spiral(center)
+            .take_while(|p| s8::d(p, &center) <= radius)
+            .map(|p| p.extend(near.z).snap_above_floor(self))
+            .find(|&loc| {
+                // Check standability separately so incorporeal mobs don't
+                // end up inside walls.
+                zone.contains(loc)
+                    && loc.can_be_stood_in(self)
+                    && e.can_enter(self, loc)
+            })
+    }
+
+    /// Trace a shot from `origin` towards `dir` for at most `range` cells
+    /// and return what it runs into first.
+    ///
+    /// Terrain that blocks shots stops the shot, but an open door with a mob
+    /// standing in it doesn't block.
+    pub fn line_of_fire(
+        &self,
+        origin: Location,
+        dir: IVec2,
+        range: usize,
+    ) -> Impact {
+        self.trace_shot(origin, dir, range, |_| false)
+    }
+
+    /// Line of fire trace that flies past mobs for which `passes_through`
+    /// is true.
+    fn trace_shot(
+        &self,
+        origin: Location,
+        dir: IVec2,
+        range: usize,
+        passes_through: impl Fn(Entity) -> bool,
+    ) -> Impact {
+        let mut ret = Impact::Spent(origin);
+        for loc in origin.trace(dir).take(range.max(1)) {
+            let loc = loc.snap_above_floor(self);
+
+            if loc.blocks_shot(self) {
+                return Impact::Wall(loc);
+            }
+
+            if let Some(mob) = loc.mob_at(self) {
+                if !passes_through(mob) {
+                    return Impact::Mob(mob, loc);
+                }
+            }
+
+            ret = Impact::Spent(loc);
+        }
+        ret
+    }
+
     /// Raycast for a hittable target given a starting position, a direction
     /// and a maximum range.
     ///
@@ -44,33 +135,11 @@ impl Runtime {
         dir: IVec2,
         range: usize,
     ) -> Location {
-        let friend = |loc: Location| {
-            if let (Some(perp), Some(target)) = (perp, loc.mob_at(self)) {
-                target.is_ally(self, &perp)
-            } else {
-                false
-            }
-        };
-
-        for (i, loc) in from.trace(dir).enumerate() {
-            let loc = loc.snap_above_floor(self);
-
+        match self.trace_shot(from, dir, range, |e| self.is_friend(perp, e)) {
             // Hit a wall, pull back one tile.
-            if loc.blocks_shot(self) {
-                return loc - dir.extend(0);
-            }
-
-            // Stop at range limit.
-            if i + 1 >= range {
-                return loc;
-            }
-
-            // Non-friend found, stop here.
-            if loc.mob_at(self).is_some() && !friend(loc) {
-                return loc;
-            }
+            Impact::Wall(loc) => loc - dir.extend(0),
+            Impact::Mob(_, loc) | Impact::Spent(loc) => loc,
         }
-        unreachable!()
     }
 
     pub fn trace_enemy(
@@ -80,18 +149,43 @@ impl Runtime {
         dir: IVec2,
         range: usize,
     ) -> Option<Entity> {
-        if let Some(mob) =
-            self.trace_target(perp, from, dir, range).mob_at(self)
-        {
-            if let Some(perp) = perp {
-                if mob.is_enemy(self, &perp) {
-                    return Some(mob);
-                }
-            } else {
-                return Some(mob);
+        let Impact::Mob(mob, _) =
+            self.trace_shot(from, dir, range, |e| self.is_friend(perp, e))
+        else {
+            return None;
+        };
+
+        match perp {
+            Some(perp) if !mob.is_enemy(self, &perp) => None,
+            _ => Some(mob),
+        }
+    }
+
+    fn is_friend(&self, perp: Option<Entity>, e: Entity) -> bool {
+        perp.is_some_and(|perp| e.is_ally(self, &perp))
+    }
+
+    /// Cells within `radius` of `center` that have a clear line of fire
+    /// from the center.
+    pub fn blast_area(&self, center: Location, radius: i32) -> Vec<Location> {
+        let mut ret = Vec::new();
+        for p in Rect::new([-radius, -radius], [radius + 1, radius + 1]) {
+            let p = v2(p);
+            if p.length_squared() > radius * radius {
+                continue;
+            }
+
+            // Walls shelter the cells behind them.
+            if util::bresenham_line(IVec2::ZERO, p)
+                .skip(1)
+                .any(|q| (center + q.extend(0)).blocks_shot(self))
+            {
+                continue;
             }
+
+            ret.push(center + p.extend(0));
         }
-        None
+        ret
     }
 
     fn confusion(&mut self, perp: Option<Entity>, from: Location, dir: IVec2) {
@@ -104,6 +198,7 @@ impl Runtime {
 
     fn fireball(&mut self, perp: Option<Entity>, from: Location, dir: IVec2) {
         const FIREBALL_RANGE: usize = 12;
+        const FIREBALL_RADIUS: i32 = 2;
         const FIREBALL_DAMAGE: i32 = 10;
         let target = self.trace_target(perp, from, dir, FIREBALL_RANGE);
 
@@ -112,13 +207,55 @@ impl Runtime {
         }
         send_msg(Msg::Explosion(target));
 
-        // No need to worry about it going through walls since it only extends
-        // one cell in any direction from the valid starting cell.
-        for p in Rect::new([-1, -1], [2, 2]) {
-            (target + v2(p).extend(0)).damage(self, perp, FIREBALL_DAMAGE);
+        for loc in self.blast_area(target, FIREBALL_RADIUS) {
+            loc.damage(self, perp, FIREBALL_DAMAGE);
         }
     }
 
+    /// Push an adjacent enemy away from `from`.
+    ///
+    /// If the enemy is pushed against something it can't move into, it takes
+    /// impact damage instead.
+    fn knockback(&mut self, perp: Option<Entity>, from: Location, dir: IVec2) {
+        const KNOCKBACK_DISTANCE: usize = 2;
+        const IMPACT_DAMAGE: i32 = 4;
+        let Some(target) = self.trace_enemy(perp, from, dir, 1) else {
+            return;
+        };
+
+        for _ in 0..KNOCKBACK_DISTANCE {
+            let Some(loc) = target.loc(self) else { return };
+            if !target.is_alive(self) {
+                return;
+            }
+
+            match loc.walk_step(self, dir) {
+                Some(dest) if target.can_enter(self, dest) => {
+                    target.place(self, dest);
+                }
+                _ => {
+                    msg!(Combat: "[One] [is] slammed into an obstacle.";
+                        target.noun(self));
+                    target.damage(self, perp, IMPACT_DAMAGE);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn enchant_weapon(&mut self, perp: Option<Entity>) {
+        let Some(weapon) =
+            perp.and_then(|e| e.equipment_at(self, EquippedAt::RunHand))
+        else {
+            msg!("Nothing happens.");
+            return;
+        };
+
+        msg!(Discovery: "[One] glow[s] blue."; weapon.noun(self));
+        let plus = weapon.get::<ecs::Enchantment>(self).0;
+        weapon.set(self, ecs::Enchantment(plus + 1));
+    }
+
     fn heal(&mut self, perp: Option<Entity>, _from: Location) {
         const HEAL_AMOUNT: i32 = 8;
         if let Some(e) = perp {
@@ -156,11 +293,11 @@ impl Runtime {
     }
 
     fn magic_map(&mut self, _perp: Option<Entity>, from: Location) {
-        const MAGIC_MAP_RANGE: usize = 100;
-
         let zone = from.sector().fat();
 
-        let mut revealed: Vec<(Location, usize)> = util::bfs(
+        // Walk the connected area first so the reveal cascades out from the
+        // caster.
+        let mut dist: HashMap<Location, usize> = util::bfs(
             |&loc| {
                 loc.hover_neighbors(self)
                     .map(|(_, loc)| loc)
@@ -168,9 +305,27 @@ impl Runtime {
             },
             [from],
         )
-        .filter(|(loc, d)| !loc.is_explored(self) && *d < MAGIC_MAP_RANGE)
         .collect();
 
+        // Then pick up every floor surface in the sector, including ones
+        // that can't be walked to and stairwells that drop out of the zone.
+        // These get revealed in order of their distance from the caster.
+        for p in from.sector() {
+            let p = Location::from(p);
+            if matches!(p.tile(self), Tile::Surface(..)) {
+                let d = (p - from).truncate().abs().element_sum() as usize;
+                dist.entry(p.snap_above_floor(self)).or_insert(d);
+            }
+        }
+
+        let mut revealed: Vec<(Location, usize)> = dist
+            .into_iter()
+            .filter(|(loc, _)| !loc.is_explored(self))
+            .collect();
+        revealed.sort_by_key(|&(loc, d)| (d, loc.to_array()));
+        let revealed_set: HashSet<Location> =
+            revealed.iter().map(|(loc, _)| *loc).collect();
+
         // Hack to add walls to the cover.
         let rim: Vec<(Location, usize)> = revealed
             .iter()
@@ -178,8 +333,7 @@ impl Runtime {
                 loc.ns_8().map(|loc| (loc.snap_above_floor(self), *n + 1))
             })
             .filter(|(loc, _)| {
-                !loc.is_explored(self)
-                    && !revealed.iter().any(|(loc_2, _)| loc == loc_2)
+                !loc.is_explored(self) && !revealed_set.contains(loc)
             })
             .collect();
 
@@ -204,6 +358,39 @@ impl Runtime {
         send_msg(Msg::MagicMap(revealed));
     }
 
+    fn haste(&mut self, perp: Option<Entity>) {
+        if let Some(e) = perp {
+            e.haste(self);
+        }
+    }
+
+    fn slow(&mut self, perp: Option<Entity>, from: Location, dir: IVec2) {
+        const SLOW_RANGE: usize = 12;
+        if let Some(target) = self.trace_enemy(perp, from, dir, SLOW_RANGE) {
+            target.slow(self);
+        }
+    }
+
+    fn teleport_self(&mut self, perp: Option<Entity>) {
+        if let Some(e) = perp {
+            self.teleport(e, None);
+        }
+    }
+
+    fn teleport_other(
+        &mut self,
+        perp: Option<Entity>,
+        from: Location,
+        dir: IVec2,
+    ) {
+        const TELEPORT_OTHER_RANGE: usize = 12;
+        if let Some(target) =
+            self.trace_enemy(perp, from, dir, TELEPORT_OTHER_RANGE)
+        {
+            self.teleport(target, None);
+        }
+    }
+
     fn summon_monster(
         &mut self,
         perp: Option<Entity>,
@@ -257,3 +444,187 @@ impl Entity {
         self.complete_turn(r);
     }
 }
+
+/// What a shot traced along a line of fire runs into.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Impact {
+    /// Terrain that blocks shots.
+    Wall(Location),
+    /// A mob at the given cell, large mobs can be hit away from their
+    /// origin cell.
+    Mob(Entity, Location),
+    /// Nothing was hit, the shot ended at the given cell at the end of its
+    /// range.
+    Spent(Location),
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::ivec3;
+    use world::{Block, Environs, Monster, Power, Rect, Tile, Zone};
+
+    use super::Impact;
+    use crate::{
+        prelude::*,
+        test_util::{open_area, runtime, spawn_monster},
+        EntitySpec,
+    };
+
+    #[test]
+    fn fireball_splash() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let center = player.loc(&r).unwrap();
+
+        // Clear out an open room around the blast center.
+        open_area(&mut r, center, Rect::new([-4, -4], [5, 5]));
+        player.place(&mut r, center + ivec3(4, 4, 0));
+
+        // Wall that shelters a mob behind it.
+        let wall = center + ivec3(0, -1, 0);
+        r.set_voxel(wall.above(), Some(Block::Stone));
+        r.set_voxel(wall, Some(Block::Stone));
+
+        let [hit_1, hit_2, hit_3, sheltered, far] =
+            [[0, 0], [1, 0], [-1, -1], [0, -2], [0, 3]].map(|[x, y]| {
+                spawn_monster(&mut r, 30, center + ivec3(x, y, 0))
+            });
+
+        r.invoke_power(
+            Power::Fireball,
+            None,
+            center + ivec3(-3, 0, 0),
+            ivec2(1, 0),
+        );
+
+        for mob in [hit_1, hit_2, hit_3] {
+            assert!(mob.wounds(&r) > 0);
+        }
+        assert_eq!(sheltered.wounds(&r), 0);
+        assert_eq!(far.wounds(&r), 0);
+    }
+
+    #[test]
+    fn line_of_fire() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let origin = player.loc(&r).unwrap();
+        let dir = ivec2(1, 0);
+
+        // Clear out a corridor with a door and a wall at the end.
+        open_area(&mut r, origin, Rect::new([1, 0], [5, 1]));
+        let door = origin + ivec3(2, 0, 0);
+        door.apply_char_terrain(&mut r, '+').unwrap();
+        let wall = origin + ivec3(4, 0, 0);
+        wall.apply_char_terrain(&mut r, '#').unwrap();
+
+        // Closed door stops the shot.
+        assert_eq!(r.line_of_fire(origin, dir, 10), Impact::Wall(door));
+
+        // Door held open by a mob lets the shot hit the mob.
+        let mob = spawn_monster(&mut r, 0, door);
+        assert_eq!(r.line_of_fire(origin, dir, 10), Impact::Mob(mob, door));
+
+        // With the door gone, the shot flies up to the wall.
+        mob.destroy(&mut r);
+        r.set_voxel(door, None);
+        r.set_voxel(door.above(), None);
+        assert_eq!(r.line_of_fire(origin, dir, 10), Impact::Wall(wall));
+
+        // Range runs out before the wall.
+        assert_eq!(
+            r.line_of_fire(origin, dir, 2),
+            Impact::Spent(origin + ivec3(2, 0, 0))
+        );
+    }
+
+    #[test]
+    fn window() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let origin = player.loc(&r).unwrap();
+        let dir = ivec2(1, 0);
+
+        // Corridor with a window between the player and a mob.
+        open_area(&mut r, origin, Rect::new([1, 0], [4, 1]));
+        let window = origin + ivec3(1, 0, 0);
+        window.apply_char_terrain(&mut r, '"').unwrap();
+        let target = origin + ivec3(3, 0, 0);
+        let mob = spawn_monster(&mut r, 0, target);
+
+        // Can't walk through.
+        player.execute_direct(&mut r, Action::Bump(dir));
+        assert_eq!(player.loc(&r), Some(origin));
+
+        // Can see and shoot through.
+        assert!(player.can_see(&r, &mob));
+        assert_eq!(r.line_of_fire(origin, dir, 10), Impact::Mob(mob, target));
+    }
+
+    #[test]
+    fn magic_mapping() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let origin = player.loc(&r).unwrap();
+        let sector: Vec<Location> =
+            origin.sector().into_iter().map(Location::from).collect();
+
+        // Put a mob somewhere the player can't currently see.
+        let visible = r.visible_cells();
+        let hidden = sector
+            .iter()
+            .map(|p| p.snap_above_floor(&r))
+            .find(|loc| loc.can_be_stood_in(&r) && !visible.contains(loc))
+            .expect("No hidden floor in sector");
+        spawn_monster(&mut r, 0, hidden);
+
+        r.invoke_power(Power::MagicMapping, Some(player), origin, ivec2(0, 0));
+
+        for p in &sector {
+            if matches!(p.tile(&r), Tile::Surface(..)) {
+                assert!(p.is_explored(&r), "{p} not explored");
+            }
+        }
+
+        // Terrain got revealed, but nothing new is in view.
+        assert!(hidden.is_explored(&r));
+        assert_eq!(r.visible_cells(), visible);
+        assert!(!hidden.is_visible(&r, &r.visible_cells()));
+    }
+
+    #[test]
+    fn teleport() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let origin = player.loc(&r).unwrap();
+        let zone = origin.sector().fat();
+
+        // Scatter some mobs around to land on.
+        let mobs: Vec<Entity> = (0..8)
+            .map(|i| {
+                let mob = Monster {
+                    icon: 'x',
+                    ..Default::default()
+                }
+                .build(&mut r, "monster");
+                let loc = r.safe_landing(mob, origin + ivec3(i - 4, 2, 0));
+                mob.place(&mut r, loc.unwrap());
+                mob
+            })
+            .collect();
+
+        for _ in 0..100 {
+            let dest = r.teleport(player, None).expect("Nowhere to land");
+            assert_eq!(player.loc(&r), Some(dest));
+            assert!(zone.contains(dest));
+            assert!(dest.can_be_stood_in(&r));
+            assert_eq!(dest.mob_at(&r), Some(player));
+        }
+
+        // Controlled teleport onto a mob lands somewhere else.
+        let occupied = mobs[0].loc(&r).unwrap();
+        let dest = r.teleport(player, Some(occupied)).unwrap();
+        assert_ne!(dest, occupied);
+        assert_eq!(mobs[0].loc(&r), Some(occupied));
+    }
+}
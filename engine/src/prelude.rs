@@ -3,6 +3,6 @@ pub use util::{Error, HashMap, HashSet, IndexMap, IndexSet, Odds, VecExt};
 pub use world::{Coordinates, Location, SECTOR_HEIGHT, SECTOR_WIDTH};
 
 pub use crate::{
-    msg, send_msg, Action, Entity, FogPathing, Goal, Instant, Msg, Receiver,
-    Runtime, RuntimeCoordinates, ScenarioStatus,
+    msg, send_msg, Action, Entity, FogPathing, Goal, Instant, Msg, MsgKind,
+    Receiver, Runtime, RuntimeCoordinates, ScenarioStatus,
 };
@@ -1,12 +1,15 @@
-use std::ops::Deref;
+use std::{collections::BTreeSet, ops::Deref};
 
 use anyhow::Result;
 use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use util::{GameRng, Silo};
-use world::{Data, Environs, Level, Pod, Voxel, World, Zone};
+use world::{Data, Environs, ItemKind, Level, Pod, Voxel, World, Zone};
 
-use crate::{ecs::*, placement::Place, prelude::*, EntitySpec, Fov, Placement};
+use crate::{
+    ecs::*, placement::Place, prelude::*, EntitySpec, Fov, Placement, Quest,
+    Scheduler, PHASES_IN_TURN,
+};
 
 /// Main data container for game engine runtime.
 #[derive(Serialize, Deserialize)]
@@ -18,6 +21,9 @@ pub struct Runtime {
     /// The waypoint where the player last rested at.
     pub(crate) previous_waypoint: Location,
     pub(crate) fov: Fov,
+    /// Enemies the player's side has seen and their icons at the locations
+    /// they were last seen at.
+    pub(crate) last_seen: IndexMap<Location, (Entity, char)>,
     pub(crate) ecs: Ecs,
     pub(crate) placement: Placement,
 
@@ -27,8 +33,25 @@ pub struct Runtime {
     /// Record of mobs that should be respawned indexed by their spawn
     /// position.
     pub(crate) samsara: IndexMap<Location, (Entity, Pod)>,
+    /// The one persistent random number stream of the game, saved along
+    /// with the runtime so that a loaded game continues the same way as
+    /// an unbroken one. Any other randomness must be re-derived from the
+    /// world seed and context with `util::srng`.
     pub(crate) rng: GameRng,
     pub(crate) world: World,
+    /// Scenario flags raised by story events.
+    pub(crate) flags: IndexSet<String>,
+    /// Item kinds the player leaves on the ground when walking over them.
+    pub(crate) no_pickup: BTreeSet<ItemKind>,
+    /// Player equips upgraded gear for empty slots when picking it up.
+    pub(crate) auto_equip: bool,
+    /// Enemies the player has already spotted, only new ones stop travel.
+    pub(crate) seen_enemies: IndexSet<Entity>,
+    /// Objectives of the scenario.
+    pub(crate) quests: Vec<Quest>,
+    pub(crate) bosses_killed: usize,
+    /// Events that happen in the future.
+    pub(crate) scheduler: Scheduler,
 }
 
 impl AsRef<Runtime> for Runtime {
@@ -46,17 +69,23 @@ impl AsMut<Runtime> for Runtime {
 impl Default for Runtime {
     fn default() -> Self {
         Runtime {
-            // Start time from an above-zero value so that zero time values
-            // can work as "unspecified time".
-            now: Instant(3600),
+            now: Instant::START,
             player: Default::default(),
             previous_waypoint: Default::default(),
             fov: Default::default(),
+            last_seen: Default::default(),
             ecs: Default::default(),
             placement: Default::default(),
             samsara: Default::default(),
             rng: GameRng::seed_from_u64(0xdeadbeef),
             world: Default::default(),
+            flags: Default::default(),
+            no_pickup: Default::default(),
+            auto_equip: false,
+            seen_enemies: Default::default(),
+            quests: Default::default(),
+            bosses_killed: 0,
+            scheduler: Default::default(),
         }
     }
 }
@@ -75,9 +104,14 @@ impl Runtime {
             world,
             previous_waypoint,
             rng,
+            auto_equip: world::settings().auto_equip,
             ..Default::default()
         };
 
+        for objective in ret.world.objectives().to_vec() {
+            ret.add_quest(objective);
+        }
+
         let entrance = ret.world.player_entrance();
         // Construct the initial world space and create the spawns.
         ret.bump_cache_at(entrance);
@@ -90,6 +124,48 @@ impl Runtime {
         self.now
     }
 
+    /// Number of full turns completed since the start of the game.
+    ///
+    /// A turn that's still in progress isn't counted.
+    pub fn turn_count(&self) -> i64 {
+        (self.now - Instant::START) / PHASES_IN_TURN
+    }
+
+    /// The seed the game world was generated from.
+    pub fn seed(&self) -> &Silo {
+        self.world.seed()
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+
+    pub fn set_flag(&mut self, flag: impl Into<String>) {
+        self.flags.insert(flag.into());
+    }
+
+    /// Whether items of the kind get picked up when walking over them.
+    pub fn auto_pickup(&self, kind: ItemKind) -> bool {
+        !self.no_pickup.contains(&kind)
+    }
+
+    pub fn set_auto_pickup(&mut self, kind: ItemKind, pick_up: bool) {
+        if pick_up {
+            self.no_pickup.remove(&kind);
+        } else {
+            self.no_pickup.insert(kind);
+        }
+    }
+
+    /// Whether the player equips upgrades for empty slots on pickup.
+    pub fn auto_equip(&self) -> bool {
+        self.auto_equip
+    }
+
+    pub fn set_auto_equip(&mut self, auto_equip: bool) {
+        self.auto_equip = auto_equip;
+    }
+
     /// Access the persistent engine random number generator.
     pub(crate) fn rng(&mut self) -> &mut impl rand::Rng {
         &mut self.rng
@@ -371,18 +447,6 @@ impl Runtime {
 
             let goal = e.goal(self);
             if goal != Goal::None {
-                if e.is_player(self) && e.first_visible_enemy(self).is_some() {
-                    // Abort commands when player is threatened. (This is
-                    // placed here instead of inside `decide` so that the
-                    // player can still be made to single-step the goal by
-                    // calling decide when under threat.)
-                    match goal {
-                        Goal::GoTo { .. } => e.next_goal(self),
-                        Goal::Autoexplore(_) => e.next_goal(self),
-                        _ => {}
-                    }
-                }
-
                 if let Some(act) = e.decide(self, goal) {
                     e.execute_indirect(self, act);
                 } else {
@@ -391,9 +455,52 @@ impl Runtime {
             }
         }
 
+        // Check for new enemies after everyone has moved, so travel stops on
+        // the same tick an enemy shows up.
+        self.spot_new_enemies();
+
         self.now += 1;
+        self.fire_events();
         self.gc();
+
+        if self.now.0.rem_euclid(PHASES_IN_TURN) == 0 {
+            self.update_quests();
+        }
     }
+
+    /// Abort the player's travel when an enemy the player hasn't seen before
+    /// comes into view.
+    ///
+    /// This is done here instead of inside `decide` so that the player can
+    /// still be made to single-step a goal by calling decide when under
+    /// threat.
+    fn spot_new_enemies(&mut self) {
+        let Some(player) = self.player() else { return };
+
+        let mut seen = std::mem::take(&mut self.seen_enemies);
+        // Forget about enemies that are gone for good.
+        seen.retain(|e| e.is_alive(self));
+        let new_enemies: Vec<Entity> = player
+            .fov_mobs(self, player.sight_radius(self))
+            .into_iter()
+            .filter(|e| e.is_enemy(self, &player) && seen.insert(*e))
+            .collect();
+        self.seen_enemies = seen;
+
+        let Some(enemy) = new_enemies.first() else {
+            return;
+        };
+        if matches!(
+            player.goal(self),
+            Goal::GoTo { .. }
+                | Goal::Autoexplore(_)
+                | Goal::StartAutoexplore(_)
+        ) {
+            msg!(Warning: "[One] come[s] into view."; enemy.noun(self));
+            player.clear_goal(self);
+        }
+    }
+
     /// Return whether the overall game scenario is still going or if it has
     /// ended in victory or defeat.
     pub fn scenario_status(&self) -> ScenarioStatus {
@@ -401,7 +508,11 @@ impl Runtime {
             return ScenarioStatus::Lost;
         }
 
-        // TODO win condition
+        if !self.quests.is_empty() && self.quests.iter().all(|q| q.is_completed)
+        {
+            return ScenarioStatus::Won;
+        }
+
         ScenarioStatus::Ongoing
     }
 
@@ -428,6 +539,7 @@ impl Environs for Runtime {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{open_area, runtime, spawn_monster};
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -448,4 +560,87 @@ mod tests {
         // Check that roundtrip keeps it same.
         assert_eq!(save, idm::to_string(&runtime2).unwrap());
     }
+
+    #[test]
+    fn deterministic_reload() {
+        use glam::ivec3;
+        use util::v2;
+        use world::Rect;
+
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let center = player.loc(&r).unwrap();
+
+        open_area(&mut r, center, Rect::new([-2, -2], [3, 3]));
+        let enemy = spawn_monster(&mut r, 2, center + ivec3(1, 0, 0));
+
+        // Player keeps swinging at the enemy, to-hit and damage rolls use
+        // the runtime RNG.
+        let fight = |r: &mut Runtime, turns: usize| {
+            for _ in 0..turns * PHASES_IN_TURN as usize {
+                if player.acts_this_frame(r) {
+                    player.execute_direct(r, Action::Bump(v2([1, 0])));
+                }
+                r.tick();
+            }
+        };
+
+        fight(&mut r, 5);
+
+        // Save and load in the middle of combat.
+        let save = idm::to_string(&r).unwrap();
+        let mut r2: Runtime = idm::from_str(&save).unwrap();
+
+        fight(&mut r, 10);
+        fight(&mut r2, 10);
+
+        assert_eq!(enemy.wounds(&r), enemy.wounds(&r2));
+        assert_eq!(player.wounds(&r), player.wounds(&r2));
+        assert_eq!(idm::to_string(&r).unwrap(), idm::to_string(&r2).unwrap());
+    }
+
+    #[test]
+    fn turn_count() {
+        let mut r = runtime();
+        assert_eq!(r.turn_count(), 0);
+
+        for _ in 0..3 * PHASES_IN_TURN - 1 {
+            r.tick();
+        }
+        // The third turn isn't complete yet.
+        assert_eq!(r.turn_count(), 2);
+
+        r.tick();
+        assert_eq!(r.turn_count(), 3);
+    }
+
+    #[test]
+    fn new_enemy_stops_travel() {
+        use glam::ivec3;
+        use world::Rect;
+
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let center = player.loc(&r).unwrap();
+
+        // Clear out an open room around the player.
+        open_area(&mut r, center, Rect::new([-4, -4], [5, 5]));
+        r.tick();
+
+        let travel = |r: &mut Runtime| {
+            player.order_go_to(r, center + ivec3(-4, 4, 0));
+        };
+
+        travel(&mut r);
+        spawn_monster(&mut r, 3, center + ivec3(4, -4, 0));
+
+        // Travel stops on the tick the enemy shows up.
+        r.tick();
+        assert_eq!(player.goal(&r), Goal::None);
+
+        // Enemy that was already seen doesn't stop travel again.
+        travel(&mut r);
+        r.tick();
+        assert_ne!(player.goal(&r), Goal::None);
+    }
 }
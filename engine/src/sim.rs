@@ -0,0 +1,101 @@
+//! Headless game simulation for balance testing.
+
+use anyhow::Result;
+use util::Silo;
+
+use crate::{prelude::*, PHASES_IN_TURN};
+
+/// Outcome of a simulated game.
+#[derive(Copy, Clone, Debug)]
+pub struct SimReport {
+    pub status: ScenarioStatus,
+    /// Number of full turns simulated.
+    pub turns: i64,
+    /// Number of enemies the player side killed.
+    pub kills: i32,
+    /// Number of map cells that got explored during the game.
+    pub explored: usize,
+}
+
+impl Runtime {
+    /// Play a game from the seed with the player running on autopilot.
+    ///
+    /// The player explores and fights whatever it runs into. Simulation ends
+    /// when the scenario is won or lost or when `max_turns` have passed.
+    /// Since the player respawns on death in the actual game, the first
+    /// player death counts as a loss here.
+    pub fn simulate(seed: Silo, max_turns: i64) -> Result<SimReport> {
+        let mut r = Runtime::new(seed)?;
+        let start = r.now();
+        let explored = r.fov.len();
+
+        let mut status = ScenarioStatus::Ongoing;
+        while status == ScenarioStatus::Ongoing
+            && (r.now() - start) / PHASES_IN_TURN < max_turns
+        {
+            if let Some(p) = r.player() {
+                if p.acts_this_frame(&r) && p.goal(&r).is_none() {
+                    r.autopilot(p);
+                }
+            }
+
+            r.tick();
+
+            status = match r.player() {
+                Some(p) if p.num_deaths(&r) > 0 => ScenarioStatus::Lost,
+                _ => r.scenario_status(),
+            };
+        }
+
+        Ok(SimReport {
+            status,
+            turns: (r.now() - start) / PHASES_IN_TURN,
+            kills: r.player().map_or(0, |p| p.num_kills(&r)),
+            explored: r.fov.len() - explored,
+        })
+    }
+
+    /// Stand-in for player input, fight visible enemies or explore.
+    fn autopilot(&mut self, p: Entity) {
+        if let Some(enemy) = p.first_visible_enemy(self) {
+            if let Some(act) = p.decide(self, Goal::Attack(enemy)) {
+                p.execute_direct(self, act);
+                return;
+            }
+        }
+
+        if let Some(loc) = p.loc(self) {
+            p.set_goal(self, Goal::StartAutoexplore(loc.sector()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use util::Silo;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn simulate_games() {
+        world::register_data_from("../data").unwrap();
+
+        // Autoexplore is slow in debug builds, keep the games short.
+        const GAMES: usize = 2;
+        const TURNS: i64 = 50;
+
+        for i in 0..GAMES {
+            let report =
+                Runtime::simulate(Silo::new(format!("sim{i}")), TURNS).unwrap();
+            assert!(report.turns > 0);
+            assert!(report.turns <= TURNS);
+            // Unfinished games run until the turn limit.
+            if report.status == ScenarioStatus::Ongoing {
+                assert_eq!(report.turns, TURNS);
+            }
+
+            // The autopilot gets around the map on its own.
+            assert!(report.explored > 0, "Nothing explored in game {i}");
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Shared setup for engine tests
+
+use glam::ivec3;
+use util::{s4, v2, Silo};
+use world::{Block, Environs, Monster, Rect};
+
+use crate::{prelude::*, EntitySpec};
+
+/// Start a new game from the standard data and a fixed seed.
+pub fn runtime() -> Runtime {
+    world::register_data_from("../data").unwrap();
+    Runtime::new(Silo::new("rand0m")).unwrap()
+}
+
+/// Find a direction to step from `loc` into an empty cell on the same
+/// level.
+pub fn free_step(r: &Runtime, loc: Location) -> (IVec2, Location) {
+    s4::DIR
+        .iter()
+        .find_map(|&d| {
+            let dest = loc.walk_step(r, d)?;
+            (dest.z() == loc.z() && dest.mob_at(r).is_none())
+                .then_some((d, dest))
+        })
+        .expect("No room to step from location")
+}
+
+/// Turn the cells of `area` around `center` into open floor.
+///
+/// Mobs other than the player are removed from the area.
+pub fn open_area(r: &mut Runtime, center: Location, area: Rect) {
+    for p in area {
+        let loc = center + v2(p).extend(0);
+        remove_mob(r, loc);
+        r.set_voxel(loc.above(), None);
+        r.set_voxel(loc, None);
+        r.set_voxel(loc.below(), Some(Block::Stone));
+    }
+}
+
+/// Fill the cells of `area` around `center` with solid rock to dig
+/// corridors into.
+///
+/// Mobs other than the player are removed from the area.
+pub fn fill_area(r: &mut Runtime, center: Location, area: Rect) {
+    for p in area {
+        let loc = center + v2(p).extend(0);
+        remove_mob(r, loc);
+        for z in -1..=2 {
+            r.set_voxel(loc + ivec3(0, 0, z), Some(Block::Stone));
+        }
+    }
+}
+
+/// Spawn a plain monster of the given level at `loc`.
+pub fn spawn_monster(r: &mut Runtime, level: i32, loc: Location) -> Entity {
+    let mob = Monster {
+        icon: 'x',
+        level,
+        ..Default::default()
+    }
+    .build(r, "monster");
+    mob.place(r, loc);
+    mob
+}
+
+fn remove_mob(r: &mut Runtime, loc: Location) {
+    if let Some(mob) = loc.mob_at(r) {
+        if Some(mob) != r.player() {
+            mob.destroy(r);
+        }
+    }
+}
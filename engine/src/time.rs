@@ -1,8 +1,8 @@
-use std::fmt;
+use std::{cmp::Ordering, collections::BinaryHeap, fmt};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Runtime, PHASES_IN_TURN};
+use crate::{prelude::*, PHASES_IN_TURN};
 
 /// An opaque representation of a time instant.
 ///
@@ -23,6 +23,12 @@ use crate::{Runtime, PHASES_IN_TURN};
 pub struct Instant(pub(crate) i64);
 
 impl Instant {
+    /// Time when a new game starts.
+    ///
+    /// Starts from an above-zero value so that zero time values can work as
+    /// "unspecified time".
+    pub const START: Instant = Instant(3600);
+
     /// Return number of ticks elapsed since this instant.
     pub fn elapsed(&self, c: &Runtime) -> i64 {
         c.now() - *self
@@ -90,3 +96,147 @@ impl std::ops::SubAssign<i64> for Instant {
         self.0 -= rhs;
     }
 }
+
+/// Something that happens at a later time.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    /// Deal damage to a mob.
+    Damage(Entity, i32),
+    /// Blast everything around a location.
+    Explode(Location, i32),
+    /// Remove a temporary entity, like a summoned mob.
+    Expire(Entity),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+struct Scheduled {
+    at: Instant,
+    /// Tiebreaker so that events scheduled for the same time fire in the
+    /// order they were scheduled in.
+    seq: u64,
+    event: Event,
+}
+
+// Reverse ordering so that the max-heap pops the earliest event first.
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.at, other.seq).cmp(&(self.at, self.seq))
+    }
+}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Queue of future events ordered by when they happen.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scheduler {
+    queue: BinaryHeap<Scheduled>,
+    seq: u64,
+}
+
+impl Scheduler {
+    pub fn push(&mut self, at: Instant, event: Event) {
+        self.queue.push(Scheduled {
+            at,
+            seq: self.seq,
+            event,
+        });
+        self.seq += 1;
+    }
+
+    /// Remove and return the earliest event if it's due by `now`.
+    pub fn pop_due(&mut self, now: Instant) -> Option<Event> {
+        if self.queue.peek()?.at <= now {
+            self.queue.pop().map(|s| s.event)
+        } else {
+            None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl Runtime {
+    /// Schedule an event to happen after `delay` ticks.
+    pub fn schedule(&mut self, delay: i64, event: Event) {
+        let at = self.now() + delay;
+        self.scheduler.push(at, event);
+    }
+
+    /// Fire all scheduled events that are due.
+    pub(crate) fn fire_events(&mut self) {
+        while let Some(event) = self.scheduler.pop_due(self.now()) {
+            self.fire(event);
+        }
+    }
+
+    fn fire(&mut self, event: Event) {
+        match event {
+            Event::Damage(e, amount) => {
+                if e.is_alive(self) {
+                    e.damage(self, None, amount);
+                }
+            }
+            Event::Explode(loc, amount) => {
+                const EXPLOSION_RADIUS: i32 = 1;
+
+                send_msg(Msg::Explosion(loc));
+                for loc in self.blast_area(loc, EXPLOSION_RADIUS) {
+                    loc.damage(self, None, amount);
+                }
+            }
+            Event::Expire(e) => {
+                if e.is_alive(self) {
+                    msg!("[One] vanish[es]."; e.noun(self));
+                    e.destroy(self);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Event;
+    use crate::test_util::{free_step, runtime, spawn_monster};
+
+    #[test]
+    fn delayed_damage() {
+        let mut r = runtime();
+        let loc = r.player().unwrap().loc(&r).unwrap();
+        let (_, dest) = free_step(&r, loc);
+        let mob = spawn_monster(&mut r, 5, dest);
+
+        r.schedule(5, Event::Damage(mob, 1));
+        for _ in 0..4 {
+            r.tick();
+            assert_eq!(mob.wounds(&r), 0);
+        }
+
+        r.tick();
+        assert_eq!(mob.wounds(&r), 1);
+    }
+
+    #[test]
+    fn delayed_explosion() {
+        let mut r = runtime();
+        let loc = r.player().unwrap().loc(&r).unwrap();
+        let (_, dest) = free_step(&r, loc);
+        let mob = spawn_monster(&mut r, 5, dest);
+
+        r.schedule(1, Event::Explode(loc, 1));
+        r.tick();
+        assert!(mob.wounds(&r) > 0);
+    }
+}
@@ -0,0 +1,135 @@
+//! Buying and selling items with merchants.
+
+use world::{MonsterFlags, PodKind};
+
+use crate::{
+    ecs::{Cash, Stats},
+    prelude::*,
+};
+
+impl Entity {
+    pub fn is_merchant(&self, r: &impl AsRef<Runtime>) -> bool {
+        self.has_monster_flag(r, MonsterFlags::MERCHANT)
+    }
+
+    /// Price of a single unit of the item when buying it from a merchant.
+    ///
+    /// Higher level and rarer items cost more.
+    pub fn price(&self, r: &impl AsRef<Runtime>) -> i32 {
+        let rarity = match self.base_desc(r).parse::<PodKind>() {
+            Ok(PodKind::Item(item)) => item.rarity as i32,
+            _ => 0,
+        };
+        let level = self.get::<Stats>(r).level.max(0);
+
+        10 * (level + 1) + rarity
+    }
+
+    /// Price a merchant pays for a single unit of the item.
+    pub fn sell_price(&self, r: &impl AsRef<Runtime>) -> i32 {
+        self.price(r) / 2
+    }
+
+    /// Buy one unit of an item from a merchant's inventory.
+    ///
+    /// Return whether the purchase went through.
+    pub(crate) fn buy(
+        &self,
+        r: &mut impl AsMut<Runtime>,
+        merchant: &Entity,
+        item: &Entity,
+    ) -> bool {
+        let r = r.as_mut();
+
+        if !merchant.is_merchant(r) || !merchant.contents(r).any(|e| e == *item)
+        {
+            log::warn!("Entity::buy: {item} isn't sold by {merchant}");
+            return false;
+        }
+
+        let price = item.price(r);
+        if !self.subtract_cash(r, price) {
+            msg!(Warning: "[One] can't afford [another].";
+                self.noun(r), item.noun(r));
+            return false;
+        }
+
+        let item = item.split_off_one(r);
+        item.detach(r);
+        item.place(r, *self);
+        msg!("[One] buy[s] [another]."; self.noun(r), item.noun(r));
+        true
+    }
+
+    /// Sell one unit of a carried item to a merchant.
+    ///
+    /// Merchants have bottomless purses, so selling always succeeds for items
+    /// the seller is carrying.
+    pub(crate) fn sell(
+        &self,
+        r: &mut impl AsMut<Runtime>,
+        merchant: &Entity,
+        item: &Entity,
+    ) -> bool {
+        let r = r.as_mut();
+
+        if !merchant.is_merchant(r) || !self.contents(r).any(|e| e == *item) {
+            log::warn!("Entity::sell: {self} can't sell {item} to {merchant}");
+            return false;
+        }
+
+        let price = item.sell_price(r);
+        let item = item.split_off_one(r);
+        item.detach(r);
+        item.place(r, *merchant);
+        self.with_mut::<Cash, _>(r, |Cash(c)| *c += price);
+        msg!("[One] sell[s] [another]."; self.noun(r), item.noun(r));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ecs::Cash,
+        prelude::*,
+        test_util::{free_step, runtime},
+    };
+
+    fn spawn_merchant(r: &mut Runtime) -> Entity {
+        let loc = r.player().unwrap().loc(r).unwrap();
+        let (_, dest) = free_step(r, loc);
+        r.spawn_at(&"merchant".parse().unwrap(), dest)[0]
+    }
+
+    #[test]
+    fn buy_and_sell() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let merchant = spawn_merchant(&mut r);
+        assert!(merchant.is_merchant(&r));
+        assert!(!merchant.is_enemy(&r, &player));
+
+        let sword = r.spawn_at(&"sword".parse().unwrap(), merchant)[0];
+        let price = sword.price(&r);
+        assert!(price > 0);
+
+        // Can't afford it.
+        player.set(&mut r, Cash(price - 1));
+        assert!(!player.buy(&mut r, &merchant, &sword));
+        assert_eq!(player.carried_cash(&r), price - 1);
+        assert!(merchant.contents(&r).any(|e| e == sword));
+
+        // Can afford it.
+        player.set(&mut r, Cash(price + 5));
+        assert!(player.buy(&mut r, &merchant, &sword));
+        assert_eq!(player.carried_cash(&r), 5);
+        assert!(player.contents(&r).any(|e| e == sword));
+        assert!(!merchant.contents(&r).any(|e| e == sword));
+
+        // Sell it back.
+        assert!(player.sell(&mut r, &merchant, &sword));
+        assert_eq!(player.carried_cash(&r), 5 + sword.sell_price(&r));
+        assert!(merchant.contents(&r).any(|e| e == sword));
+    }
+}
@@ -0,0 +1,155 @@
+//! Hidden floor traps and secret doors
+
+use util::{s8, Odds, RngExt};
+use world::{Block, Environs, MonsterFlags, TrapKind};
+
+use crate::{prelude::*, TRAP_DETECT_RADIUS};
+
+impl Entity {
+    /// Set off a trap if the mob is standing on one.
+    pub(crate) fn spring_trap(&self, r: &mut impl AsMut<Runtime>) {
+        const TRAP_DAMAGE: i32 = 3;
+
+        let r = r.as_mut();
+
+        // Fliers pass over traps without touching them.
+        if !self.is_mob(r) || self.has_monster_flag(r, MonsterFlags::FLIES) {
+            return;
+        }
+
+        let Some(floor) = self.loc(r).map(|loc| loc.below()) else {
+            return;
+        };
+        let Some(kind) = floor.voxel(r).and_then(Block::trap) else {
+            return;
+        };
+
+        // Triggered traps are no longer hidden.
+        r.set_voxel(floor, Some(Block::Trap(kind)));
+
+        match kind {
+            TrapKind::Damage => {
+                msg!(Warning: "[One] step[s] on a spike trap."; self.noun(r));
+                self.damage(r, None, TRAP_DAMAGE);
+            }
+            TrapKind::Confusion => {
+                msg!(Warning: "[One] step[s] on a gas trap."; self.noun(r));
+                self.confuse(r);
+            }
+            TrapKind::Teleport => {
+                msg!(Warning: "[One] step[s] on a teleport trap.";
+                    self.noun(r));
+                r.teleport(*self, None);
+            }
+        }
+    }
+
+    /// Try to spot hidden traps near the mob.
+    pub(crate) fn search_for_traps(&self, r: &mut impl AsMut<Runtime>) {
+        // Chance of spotting a trap on any one search is one in this.
+        const SPOT_ODDS: usize = 3;
+
+        let r = r.as_mut();
+
+        let Some(loc) = self.loc(r) else { return };
+
+        let hidden: Vec<(Location, TrapKind)> = r
+            .fov_from(loc, TRAP_DETECT_RADIUS)
+            .filter_map(|(_, loc)| match loc.below().voxel(r) {
+                Some(Block::HiddenTrap(kind)) => Some((loc.below(), kind)),
+                _ => None,
+            })
+            .collect();
+
+        for (floor, kind) in hidden {
+            if r.rng.one_chance_in(SPOT_ODDS) {
+                r.set_voxel(floor, Some(Block::Trap(kind)));
+                msg!(Discovery: "[One] notice[s] a trap."; self.noun(r));
+            }
+        }
+    }
+
+    /// Spend a turn searching the adjacent cells for secret doors and hidden
+    /// traps.
+    ///
+    /// Higher level mobs are more perceptive, and orthogonally adjacent cells
+    /// are easier to search than diagonal ones.
+    pub(crate) fn search(&self, r: &mut impl AsMut<Runtime>) {
+        // Deciban odds of spotting a hidden thing at level zero.
+        const ORTHOGONAL_ODDS: i32 = -5;
+        const DIAGONAL_ODDS: i32 = -10;
+
+        let r = r.as_mut();
+
+        let Some(loc) = self.loc(r) else { return };
+        let perception = self.stats(r).level;
+
+        for d in s8::DIR {
+            let odds = if d.x == 0 || d.y == 0 {
+                ORTHOGONAL_ODDS
+            } else {
+                DIAGONAL_ODDS
+            };
+            if !r.rng.chance(Odds(perception + odds)) {
+                continue;
+            }
+
+            let loc = loc + d.extend(0);
+            if loc.voxel(r) == Some(Block::SecretDoor) {
+                r.set_voxel(loc, Some(Block::Door));
+                msg!(Discovery: "[One] find[s] a secret door."; self.noun(r));
+            } else if let Some(Block::HiddenTrap(kind)) = loc.below().voxel(r) {
+                r.set_voxel(loc.below(), Some(Block::Trap(kind)));
+                msg!(Discovery: "[One] find[s] a trap."; self.noun(r));
+            }
+        }
+
+        self.complete_turn(r);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use world::{Block, Environs, TrapKind};
+
+    use crate::{
+        prelude::*,
+        test_util::{free_step, runtime},
+    };
+
+    #[test]
+    fn damage_trap() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+        let (dir, dest) = free_step(&r, loc);
+
+        r.set_voxel(dest.below(), Some(Block::HiddenTrap(TrapKind::Damage)));
+        let wounds = player.wounds(&r);
+
+        player.execute_direct(&mut r, Action::Bump(dir));
+
+        assert_eq!(player.loc(&r), Some(dest));
+        assert!(player.wounds(&r) > wounds);
+        assert_eq!(r.voxel(dest.below()), Some(Block::Trap(TrapKind::Damage)));
+    }
+
+    #[test]
+    fn find_secret_door() {
+        let mut r = runtime();
+        let player = r.player().unwrap();
+        let loc = player.loc(&r).unwrap();
+
+        let (_, dest) = free_step(&r, loc);
+        r.set_voxel(dest, Some(Block::SecretDoor));
+
+        for _ in 0..100 {
+            if r.voxel(dest) == Some(Block::Door) {
+                break;
+            }
+            player.execute_direct(&mut r, Action::Search);
+        }
+
+        assert_eq!(r.voxel(dest), Some(Block::Door));
+    }
+}
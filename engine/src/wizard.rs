@@ -0,0 +1,125 @@
+//! Debug commands for wizard mode.
+
+use anyhow::{bail, Result};
+use util::v3;
+use world::Data;
+
+use crate::{ecs::IsInvulnerable, prelude::*};
+
+/// Cheat commands that are only available in wizard mode.
+#[derive(Clone, Debug)]
+pub enum WizardCommand {
+    /// Reveal the whole sector the location is in.
+    RevealSector(Location),
+    /// Move the player to the location.
+    Teleport(Location),
+    /// Spawn a monster from the bestiary at the location.
+    Spawn(String, Location),
+    /// Toggle whether the player can be damaged.
+    ToggleInvulnerable,
+}
+
+impl Runtime {
+    /// Run a wizard command.
+    ///
+    /// Commands fail unless `wizard_mode` says the game is running in wizard
+    /// mode.
+    pub fn wizard(
+        &mut self,
+        wizard_mode: bool,
+        cmd: WizardCommand,
+    ) -> Result<()> {
+        use WizardCommand::*;
+
+        if !wizard_mode {
+            bail!("Wizard commands need wizard mode");
+        }
+
+        match cmd {
+            RevealSector(loc) => {
+                for p in loc.sector() {
+                    self.fov.insert(v3(p));
+                }
+            }
+            Teleport(loc) => {
+                let Some(player) = self.player() else {
+                    bail!("No player to teleport");
+                };
+                let loc = loc.snap_above_floor(self);
+                if !player.can_enter(self, loc) {
+                    bail!("Can't teleport into {loc}");
+                }
+                player.place(self, loc);
+            }
+            Spawn(name, loc) => {
+                if !Data::get().bestiary.contains_key(name.as_str()) {
+                    bail!("Unknown monster {name:?}");
+                }
+                if self.wish(loc, &name).is_none() {
+                    bail!("Failed to spawn {name:?}");
+                }
+            }
+            ToggleInvulnerable => {
+                let Some(player) = self.player() else {
+                    bail!("No player to make invulnerable");
+                };
+                let is_invulnerable = !player.is_invulnerable(self);
+                player.set(self, IsInvulnerable(is_invulnerable));
+                if is_invulnerable {
+                    msg!("You feel invulnerable.");
+                } else {
+                    msg!("You feel vulnerable again.");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WizardCommand;
+    use crate::{prelude::*, test_util::runtime};
+
+    #[test]
+    fn reveal_sector() {
+        let mut r = runtime();
+        let loc = r.player().unwrap().loc(&r).unwrap();
+
+        let is_revealed = |r: &Runtime| {
+            loc.sector()
+                .into_iter()
+                .all(|p| Location::from(p).is_in_fov_set(r))
+        };
+        assert!(!is_revealed(&r));
+
+        // Nothing happens outside wizard mode.
+        assert!(r.wizard(false, WizardCommand::RevealSector(loc)).is_err());
+        assert!(!is_revealed(&r));
+
+        r.wizard(true, WizardCommand::RevealSector(loc)).unwrap();
+        assert!(is_revealed(&r));
+    }
+
+    #[test]
+    fn spawn_monster() {
+        let mut r = runtime();
+        let loc = r.player().unwrap().loc(&r).unwrap();
+
+        let count = |r: &Runtime| {
+            r.live_entities()
+                .filter(|e| e.base_desc(r) == "goblin")
+                .count()
+        };
+        let n = count(&r);
+
+        r.wizard(true, WizardCommand::Spawn("goblin".into(), loc))
+            .unwrap();
+        assert_eq!(count(&r), n + 1);
+
+        assert!(r
+            .wizard(true, WizardCommand::Spawn("no such thing".into(), loc))
+            .is_err());
+    }
+}
@@ -110,7 +110,7 @@ fn main() -> anyhow::Result<()> {
         data += md;
     }
 
-    world::register_data(idm::transmute(&data)?);
+    world::register_data(idm::transmute(&data)?)?;
 
     let now = std::time::Instant::now();
     let mut map = match args.command {
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use engine::prelude::*;
+use util::Silo;
+
+#[derive(Parser, Debug)]
+#[command(about = "Simulate games without a user interface")]
+struct Args {
+    /// Load game data from a given path.
+    #[arg(long, value_name = "PATH", default_value = "data")]
+    data_dir: PathBuf,
+
+    /// Number of games to simulate.
+    #[arg(short, long, default_value_t = 10)]
+    num_games: usize,
+
+    /// Maximum number of turns per game.
+    #[arg(long, default_value_t = 10_000)]
+    max_turns: i64,
+
+    #[arg(
+        long,
+        value_name = "SEED",
+        value_parser = |e: &str| Ok::<Silo, &str>(Silo::new(e)),
+        help = "Base seed, each game gets a seed derived from it"
+    )]
+    seed: Option<Silo>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    world::register_data_from(&args.data_dir)?;
+
+    let base = if let Some(seed) = args.seed {
+        seed.to_string()
+    } else {
+        Silo::sample(&mut rand::thread_rng(), 6).to_string()
+    };
+
+    let (mut won, mut lost) = (0, 0);
+    for i in 0..args.num_games {
+        let seed = Silo::new(format!("{base}{i}"));
+        let report = Runtime::simulate(seed.clone(), args.max_turns)?;
+        println!(
+            "{seed}: {:?} after {} turns, {} kills, {} cells explored",
+            report.status, report.turns, report.kills, report.explored
+        );
+
+        match report.status {
+            ScenarioStatus::Won => won += 1,
+            ScenarioStatus::Lost => lost += 1,
+            ScenarioStatus::Ongoing => {}
+        }
+    }
+
+    println!(
+        "won {won}, lost {lost}, unfinished {}",
+        args.num_games - won - lost
+    );
+
+    Ok(())
+}
@@ -8,8 +8,10 @@ use clap::Parser;
 use glam::{ivec2, ivec3, IVec3};
 use serde::{Deserialize, Serialize};
 
-use util::{HashMap, HashSet, StrExt};
-use world::{Rect, Region, Scenario, SectorMap, SECTOR_HEIGHT, SECTOR_WIDTH};
+use util::{HashMap, HashSet, IndexMap, LazyRes};
+use world::{
+    Pod, Rect, Region, Scenario, SectorMap, SECTOR_HEIGHT, SECTOR_WIDTH,
+};
 
 // NB. This thing is sort of weird and janky, the actual spatial positions of
 // prefab maps aren't stable with the presence of procgen segments and
@@ -25,19 +27,42 @@ use world::{Rect, Region, Scenario, SectorMap, SECTOR_HEIGHT, SECTOR_WIDTH};
 // and can't be specified in Tiled, you just paint the ASCII letters with
 // Tiled and then specify per-sector meanings by editing the IDM.
 //
+// Inject warns about tiles drawn outside the prefab maps, since those are
+// ignored.
+//
 // XXX: Above-ground must have at least one initial non-'_' tile to show up in
 // Tiled export.
 //
+// Legend spawns are exported as tile objects on a separate object layer for
+// each z-level. Moving, copying or deleting the objects in Tiled moves the
+// spawns. New spawn objects can be added by duplicating an existing one and
+// renaming it, inject will add legend letters for names it hasn't seen
+// before.
+//
 // You need to have mapedit-tiles.png available in the directory of the
-// exported Tiled json file.
+// exported Tiled json file, or use `--tileset` to point the export to a
+// different tileset image or an external `.tsx` tileset file.
+//
+// Tile layers are matched to z-levels by their ":z N" names. Layers renamed
+// in Tiled are stacked by their order, counting from a layer named ":z 0" or
+// one with "ground" in its name, or the topmost layer if there is neither.
 
 const TILE_W: u32 = 8;
 const TILE_H: u32 = 8;
 
+/// First tile id of the tileset used for spawn objects.
+const SPAWN_FIRSTGID: u32 = 257;
+
+/// Tile id bits of a Tiled gid value, the rest are flip flags.
+const GID_MASK: u32 = 0x0fff_ffff;
+
+/// Column width to wrap the rewritten scenario file to.
+const TEXT_WIDTH: usize = 80;
+
 #[derive(Parser, Debug)]
 enum Args {
     /// Generate a Tiled map file from the given IDM scenario file.
-    Extract(Param),
+    Extract(ExtractParam),
     /// Rewrite the contents of an IDM scenario file based on a Tiled map file
     /// generated from it.
     Inject(Param),
@@ -48,19 +73,64 @@ struct Param {
     path: PathBuf,
 }
 
+#[derive(Parser, Debug)]
+struct ExtractParam {
+    path: PathBuf,
+
+    /// Tileset image or external .tsx tileset file to use in the Tiled map.
+    #[arg(long)]
+    tileset: Option<PathBuf>,
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
     match args {
-        Args::Extract(param) => extract(&param.path),
+        Args::Extract(param) => extract(&param.path, param.tileset.as_deref()),
         Args::Inject(param) => inject(&param.path),
     }
 }
 
-fn extract(path: &Path) -> Result<()> {
+fn extract(path: &Path, tileset: Option<&Path>) -> Result<()> {
     let scenario: Scenario = idm::from_str(&fs::read_to_string(path)?)?;
+    let mut tiled = export(&scenario)?;
+    if let Some(tileset) = tileset {
+        tiled.set_tileset(tileset);
+    }
+    let output = path.with_extension("json");
+    fs::write(&output, serde_json::to_string(&tiled)?)?;
+    eprintln!("Wrote Tiled world map to {}", output.display());
+    Ok(())
+}
 
+fn inject(path: &Path) -> Result<()> {
+    let scenario_path = path.with_extension("idm");
+    let scenario_text = fs::read_to_string(&scenario_path)?;
+    let mut scenario: Scenario = idm::from_str(&scenario_text)?;
+    let tiled: Map = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let ignored = import(&mut scenario, &tiled)?;
+    if ignored > 0 {
+        eprintln!("Warning: Ignored {ignored} tiles outside prefab maps");
+    }
+
+    fs::write(
+        &scenario_path,
+        util::wrap_idm::<Scenario>(
+            &idm::to_string_styled_like(&scenario_text, &scenario)?,
+            TEXT_WIDTH,
+        )?,
+    )?;
+    eprintln!(
+        "Rewrote scenario file {} with Tiled map",
+        scenario_path.display()
+    );
+    Ok(())
+}
+
+/// Build a Tiled map from the prefab maps of a scenario.
+fn export(scenario: &Scenario) -> Result<Map> {
     let mut cells = Vec::new();
+    let mut spawns = Vec::new();
 
     // In case the scenario overworld reuses a region with maps attached to it,
     // only extract the first instance of it. This makes it unambiguous which
@@ -82,47 +152,60 @@ fn extract(path: &Path) -> Result<()> {
             .enumerate()
             .map(|(i, m)| (overground.len() as i32 - 1 - i as i32, m))
         {
-            let p = p.extend(z);
-            for (q, c) in map.map.char_grid() {
-                // XXX: Special case, '_' is used to represent holes in maps,
-                // Tiled version does transparency instead so just no-op here.
-                if c == '_' {
-                    continue;
-                }
-                cells.push((p + q.extend(0), c as u32));
-            }
+            push_cells(&mut cells, &mut spawns, p.extend(z), map);
         }
         for (z, map) in underground
             .iter()
             .enumerate()
             .map(|(i, m)| (-1 - i as i32, m))
         {
-            let p = p.extend(z);
-            for (q, c) in map.map.char_grid() {
-                if c == '_' {
-                    continue;
-                }
-                cells.push((p + q.extend(0), c as u32));
-            }
+            push_cells(&mut cells, &mut spawns, p.extend(z), map);
         }
     }
 
-    let tiled: Map = cells.into_iter().collect();
-    let output = path.with_extension("json");
-    fs::write(&output, serde_json::to_string(&tiled)?)?;
-    eprintln!("Wrote Tiled world map to {}", output.display());
-    Ok(())
+    let mut tiled: Map = cells.into_iter().collect();
+    tiled.add_spawns(spawns);
+    Ok(tiled)
 }
 
-fn inject(path: &Path) -> Result<()> {
-    let scenario_path = path.with_extension("idm");
-    let scenario_text = fs::read_to_string(&scenario_path)?;
-    let mut scenario: Scenario = idm::from_str(&scenario_text)?;
-    let tiled: Map = serde_json::from_str(&fs::read_to_string(path)?)?;
+/// Add terrain cells and legend spawns of a single map to the export lists.
+fn push_cells(
+    cells: &mut Vec<(IVec3, u32)>,
+    spawns: &mut Vec<(IVec3, char, String)>,
+    origin: IVec3,
+    map: &SectorMap,
+) {
+    for (q, token) in map.cells() {
+        let c = map.glyph(token);
+        // XXX: Special case, '_' is used to represent holes in maps, Tiled
+        // version does transparency instead so just no-op here.
+        if c == '_' {
+            continue;
+        }
+        let q = origin + q.extend(0);
+        cells.push((q, c as u32));
+        if let Some(spawn) = map.legend.get(token) {
+            // Spawns go to the object layer, show the first character of a
+            // multi-character token.
+            let c = token.chars().next().unwrap_or('?');
+            spawns.push((q, c, spawn.to_string()));
+        }
+    }
+}
+
+/// Rewrite the prefab maps of a scenario from a Tiled map.
+///
+/// Return the number of tiles and spawns that were outside every prefab map
+/// and got ignored.
+fn import(scenario: &mut Scenario, tiled: &Map) -> Result<usize> {
     let cells: HashMap<IVec3, u32> =
         tiled.iter().map(|(p, c)| (p.into(), c)).collect();
+    let spawns: HashMap<IVec3, &str> =
+        tiled.spawns().map(|(p, name)| (p.into(), name)).collect();
 
     let mut seen_regions = HashSet::default();
+    // Origins of all the rebuilt maps.
+    let mut covered = HashSet::default();
 
     let regions = scenario.indexed_map()?;
     for (p, idx) in regions {
@@ -140,24 +223,15 @@ fn inject(path: &Path) -> Result<()> {
             .enumerate()
             .map(|(i, m)| (overground.len() as i32 - 1 - i as i32, m))
         {
-            // Build a new text map.
-            let mut map = String::new();
-            for y in p[1]..(p[1] + SECTOR_HEIGHT) {
-                for x in p[0]..(p[0] + SECTOR_WIDTH) {
-                    let p = ivec3(x, y, z);
-                    if let Some(c) = cells.get(&p) {
-                        map.push(char::from_u32(*c).unwrap());
-                    } else {
-                        // Default to empty void overground.
-                        map.push('_');
-                    }
-                }
-                map.push('\n');
-            }
-
-            let mut new_map = old_map.clone();
-            new_map.map = map;
-            new_overground.push(new_map);
+            covered.insert(p.extend(z));
+            // Default to empty void overground.
+            new_overground.push(rebuild_map(
+                &cells,
+                &spawns,
+                p.extend(z),
+                old_map,
+                '_',
+            ));
         }
 
         let mut new_underground = Vec::new();
@@ -166,24 +240,15 @@ fn inject(path: &Path) -> Result<()> {
             .enumerate()
             .map(|(i, m)| (-1 - i as i32, m))
         {
-            // Build a new text map.
-            let mut map = String::new();
-            for y in p[1]..(p[1] + SECTOR_HEIGHT) {
-                for x in p[0]..(p[0] + SECTOR_WIDTH) {
-                    let p = ivec3(x, y, z);
-                    if let Some(c) = cells.get(&p) {
-                        map.push(char::from_u32(*c).unwrap());
-                    } else {
-                        // Default to solid overground.
-                        map.push('#');
-                    }
-                }
-                map.push('\n');
-            }
-
-            let mut new_map = old_map.clone();
-            new_map.map = map;
-            new_underground.push(new_map);
+            covered.insert(p.extend(z));
+            // Default to solid underground.
+            new_underground.push(rebuild_map(
+                &cells,
+                &spawns,
+                p.extend(z),
+                old_map,
+                '#',
+            ));
         }
 
         new_overground.reverse();
@@ -196,15 +261,103 @@ fn inject(path: &Path) -> Result<()> {
         );
     }
 
-    fs::write(
-        &scenario_path,
-        idm::to_string_styled_like(&scenario_text, &scenario)?,
-    )?;
-    eprintln!(
-        "Rewrote scenario file {} with Tiled map",
-        scenario_path.display()
-    );
-    Ok(())
+    let sector_origin = |p: &IVec3| {
+        ivec3(
+            p.x.div_euclid(SECTOR_WIDTH) * SECTOR_WIDTH,
+            p.y.div_euclid(SECTOR_HEIGHT) * SECTOR_HEIGHT,
+            p.z,
+        )
+    };
+    Ok(cells
+        .keys()
+        .chain(spawns.keys())
+        .filter(|p| !covered.contains(&sector_origin(p)))
+        .count())
+}
+
+/// Build a new text map for the sector at `origin` from Tiled data.
+fn rebuild_map(
+    cells: &HashMap<IVec3, u32>,
+    spawns: &HashMap<IVec3, &str>,
+    origin: IVec3,
+    old_map: &SectorMap,
+    default: char,
+) -> SectorMap {
+    let mut legend = old_map.legend.clone();
+
+    let mut rows = Vec::new();
+    for y in origin.y..(origin.y + SECTOR_HEIGHT) {
+        let mut row = Vec::new();
+        for x in origin.x..(origin.x + SECTOR_WIDTH) {
+            let p = ivec3(x, y, origin.z);
+            if let Some(name) = spawns.get(&p) {
+                row.push(spawn_token(&mut legend, name));
+            } else if let Some(c) = cells.get(&p) {
+                row.push(char::from_u32(*c).unwrap().to_string());
+            } else {
+                row.push(default.to_string());
+            }
+        }
+        rows.push(row);
+    }
+
+    // Drop entries for spawns that were removed in Tiled.
+    legend.retain(|k, _| rows.iter().flatten().any(|a| a == k));
+
+    let mut ret = SectorMap {
+        map: String::new(),
+        legend,
+        ..old_map.clone()
+    };
+
+    // Terrain cells of a wide map are written doubled.
+    let w = ret.cell_width();
+    for row in rows {
+        let mut line = String::new();
+        for token in row {
+            if ret.legend.contains_key(&token) {
+                line.push_str(&format!("{token:w$}"));
+            } else {
+                line.push_str(&token.repeat(w));
+            }
+        }
+        ret.map.push_str(line.trim_end());
+        ret.map.push('\n');
+    }
+
+    ret
+}
+
+/// Find the legend token for a named spawn, adding a new legend entry if
+/// the spawn isn't in the legend yet.
+fn spawn_token(
+    legend: &mut IndexMap<String, LazyRes<Pod>>,
+    name: &str,
+) -> String {
+    if let Some((k, _)) = legend.iter().find(|(_, a)| a.to_string() == name) {
+        return k.clone();
+    }
+
+    // Prefer the initial of the name, then any free letter. 'S' is reserved
+    // for secret doors. When the letters run out, switch to two-character
+    // tokens.
+    let initial = name.chars().next().unwrap_or('a');
+    let letters = || {
+        [initial.to_ascii_lowercase(), initial.to_ascii_uppercase()]
+            .into_iter()
+            .chain('a'..='z')
+            .chain('A'..='Z')
+            .filter(|&c| c.is_ascii_alphabetic() && c != 'S')
+    };
+    let token = letters()
+        .map(String::from)
+        .chain(
+            letters().flat_map(|c| ('1'..='9').map(move |d| format!("{c}{d}"))),
+        )
+        .find(|k| !legend.contains_key(k))
+        .expect("Out of tokens for legend");
+    legend.insert(token.clone(), LazyRes::new(name.to_owned()));
+    token
 }
 
 /// Extract maps into compacted overground and underground stacks.
@@ -319,7 +472,7 @@ pub struct Map {
     pub tileheight: u32,
     pub tilewidth: u32,
     pub version: String,
-    pub tilesets: Vec<Tileset>,
+    pub tilesets: Vec<TilesetRef>,
 }
 
 impl Default for Map {
@@ -340,7 +493,14 @@ impl Default for Map {
             tileheight: TILE_H,
             tilewidth: TILE_W,
             version: "1.10".into(),
-            tilesets: vec![Tileset::new("mapedit-tiles.png")],
+            tilesets: vec![
+                TilesetRef::Embedded(Tileset::new("mapedit-tiles.png")),
+                TilesetRef::Embedded(Tileset {
+                    firstgid: SPAWN_FIRSTGID,
+                    name: "spawns".into(),
+                    ..Tileset::new("mapedit-tiles.png")
+                }),
+            ],
         }
     }
 }
@@ -386,18 +546,126 @@ impl<P: Into<[i32; 3]>> FromIterator<(P, u32)> for Map {
 
 impl Map {
     pub fn iter(&self) -> impl Iterator<Item = ([i32; 3], u32)> + '_ {
+        // Terrain tiles come from the first tileset.
+        let firstgid = self
+            .tilesets
+            .iter()
+            .map(|a| a.firstgid())
+            .min()
+            .unwrap_or(1);
+
         // If no layer is clearly marked ground, assume we're looking at a
         // flat overland plus dungeons map and the ground layer is the topmost
         // one.
-        let dz = self
-            .layers
-            .iter()
-            .position(|x| x.is_ground())
-            .unwrap_or(self.layers.len() - 1) as i32;
+        let tile_layers =
+            move || self.layers.iter().filter(|a| a.is_tile_layer());
+        let dz = tile_layers()
+            .position(|x| x.z() == Some(0))
+            .or_else(|| tile_layers().position(|x| x.is_ground()))
+            .unwrap_or(tile_layers().count().saturating_sub(1))
+            as i32;
+
+        // Layers with a ":z N" name go to that level, the rest are stacked in
+        // order relative to the ground layer.
+        tile_layers().enumerate().flat_map(move |(i, a)| {
+            let z = a.z().unwrap_or(i as i32 - dz);
+            a.iter()
+                .filter(move |&(_, a)| a >= firstgid)
+                .map(move |([x, y], a)| ([x, y, z], a - firstgid))
+        })
+    }
+
+    /// Make the map use the given tileset for both terrain and spawns.
+    ///
+    /// A `.tsx` file is referenced as an external tileset, anything else is
+    /// assumed to be a tileset image.
+    pub fn set_tileset(&mut self, path: &Path) {
+        for a in self.tilesets.iter_mut() {
+            let firstgid = a.firstgid();
+            *a = if path.extension().is_some_and(|e| e == "tsx") {
+                TilesetRef::External {
+                    firstgid,
+                    source: path.into(),
+                }
+            } else {
+                let name = match a {
+                    TilesetRef::Embedded(t) => t.name.clone(),
+                    TilesetRef::External { .. } => Default::default(),
+                };
+                TilesetRef::Embedded(Tileset {
+                    firstgid,
+                    name,
+                    ..Tileset::new(path)
+                })
+            };
+        }
+    }
 
-        self.layers.iter().enumerate().flat_map(move |(i, a)| {
-            let z = i as i32 - dz;
-            a.iter().map(move |([x, y], a)| ([x, y, z], a))
+    /// Add spawn objects with glyphs and names, one object layer per
+    /// z-level placed right above the terrain layer of that level.
+    pub fn add_spawns<P: Into<[i32; 3]>>(
+        &mut self,
+        spawns: impl IntoIterator<Item = (P, char, String)>,
+    ) {
+        let mut layers: HashMap<i32, Vec<Object>> = HashMap::default();
+        for (p, c, name) in spawns {
+            let [x, y, z] = p.into();
+            // Glyphs outside the tileset are shown as '?'.
+            let c = if (c as u32) < 256 {
+                c as u32
+            } else {
+                '?' as u32
+            };
+            let id = self.nextobjectid;
+            self.nextobjectid += 1;
+
+            layers.entry(z).or_default().push(Object {
+                type_: "spawn".into(),
+                gid: SPAWN_FIRSTGID + c,
+                id,
+                name,
+                // Tile objects are anchored at their bottom left corner.
+                x: (x * TILE_W as i32) as f32,
+                y: ((y + 1) * TILE_H as i32) as f32,
+                width: TILE_W as f32,
+                height: TILE_H as f32,
+                rotation: 0.0,
+            });
+        }
+
+        let mut id = self.layers.iter().map(|a| a.id).max().unwrap_or(0);
+        let mut zs: Vec<i32> = layers.keys().copied().collect();
+        zs.sort();
+        for z in zs {
+            id += 1;
+            let layer = Layer::objects(
+                format!(":z {z} spawns"),
+                id,
+                layers.remove(&z).unwrap(),
+            );
+            let pos = self
+                .layers
+                .iter()
+                .position(|a| a.is_tile_layer() && a.z() == Some(z))
+                .map_or(self.layers.len(), |i| i + 1);
+            self.layers.insert(pos, layer);
+        }
+        self.nextlayerid = id + 1;
+    }
+
+    /// Iterate spawn object positions and names.
+    pub fn spawns(&self) -> impl Iterator<Item = ([i32; 3], &str)> + '_ {
+        self.layers.iter().flat_map(|a| {
+            let objects = match &a.variant {
+                LayerVariant::ObjectGroup { objects, .. } => objects.as_slice(),
+                _ => &[],
+            };
+            let z = a.z();
+            objects.iter().filter_map(move |o| {
+                let x = (o.x / TILE_W as f32).floor() as i32;
+                let y = (o.y / TILE_H as f32).floor() as i32 - 1;
+                Some(([x, y, z?], o.name.as_str()))
+            })
         })
     }
 
@@ -443,13 +711,49 @@ impl Layer {
         }
     }
 
+    pub fn objects(
+        name: impl AsRef<str>,
+        id: u32,
+        objects: Vec<Object>,
+    ) -> Self {
+        Layer {
+            name: name.as_ref().to_owned(),
+            id,
+            visible: true,
+            opacity: 1.0,
+            x: 0,
+            y: 0,
+            variant: LayerVariant::ObjectGroup {
+                draworder: "topdown".into(),
+                objects,
+            },
+        }
+    }
+
     /// Is this layer the ground level (z = 0)?
     ///
-    /// The ground layer is either recognized by a case-insensitive occurrence
-    /// of the word "ground" somewhere in the layer name or the layer being
-    /// named "Tile Layer 1", Tiled's default name for the first layer.
+    /// The ground layer is either recognized by being named ":z 0", by a
+    /// case-insensitive occurrence of the word "ground" somewhere in the
+    /// layer name or the layer being named "Tile Layer 1", Tiled's default
+    /// name for the first layer.
     pub fn is_ground(&self) -> bool {
-        self.name == ":z 0"
+        self.z() == Some(0)
+            || self.name.to_lowercase().contains("ground")
+            || self.name == "Tile Layer 1"
+    }
+
+    pub fn is_tile_layer(&self) -> bool {
+        matches!(self.variant, LayerVariant::TileLayer { .. })
+    }
+
+    /// Z-level from a layer name of the form ":z 1" or ":z 1 spawns".
+    pub fn z(&self) -> Option<i32> {
+        self.name
+            .strip_prefix(":z ")?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
     }
 
     pub fn iter(&self) -> impl Iterator<Item = ([i32; 2], u32)> + '_ {
@@ -468,8 +772,10 @@ impl Layer {
                 [c.x, c.y],
                 [c.x + c.width as i32, c.y + c.height as i32],
             );
+            // Strip the flip flags from the high bits of the tile ids.
             c.data.iter().enumerate().filter_map(move |(i, &a)| {
-                (a != 0).then(|| (cell.get(i), a - 1))
+                let a = a & GID_MASK;
+                (a != 0).then(|| (cell.get(i), a))
             })
         })
     }
@@ -602,6 +908,23 @@ pub struct Object {
     pub rotation: f32,
 }
 
+/// Tileset embedded in the map or loaded from an external `.tsx` file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TilesetRef {
+    External { firstgid: u32, source: PathBuf },
+    Embedded(Tileset),
+}
+
+impl TilesetRef {
+    pub fn firstgid(&self) -> u32 {
+        match self {
+            TilesetRef::External { firstgid, .. } => *firstgid,
+            TilesetRef::Embedded(t) => t.firstgid,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Tileset {
     pub columns: u32,
@@ -618,7 +941,7 @@ pub struct Tileset {
 }
 
 impl Tileset {
-    pub fn new(file: impl AsRef<str>) -> Tileset {
+    pub fn new(file: impl Into<PathBuf>) -> Tileset {
         Tileset {
             columns: 16,
             tilecount: 256,
@@ -626,7 +949,7 @@ impl Tileset {
             tileheight: TILE_H,
             spacing: 0,
             firstgid: 1,
-            image: file.as_ref().into(),
+            image: file.into(),
             imagewidth: 16 * TILE_W,
             imageheight: 16 * TILE_H,
             margin: 0,
@@ -634,3 +957,141 @@ impl Tileset {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    /// Spawn names with their map-local positions.
+    fn spawn_set(scenario: &Scenario) -> BTreeSet<(i32, [i32; 2], String)> {
+        let mut ret = BTreeSet::new();
+        for (_, regions) in &scenario.legend {
+            let (overground, underground) = extract_maps(regions);
+            for (z, map) in overground.iter().chain(&underground).enumerate() {
+                for (p, c) in map.cells() {
+                    if let Some(spawn) = map.legend.get(c) {
+                        ret.insert((z as i32, p.into(), spawn.to_string()));
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn spawn_objects() {
+        let mut scenario: Scenario = idm::from_str(
+            "\
+map a
+legend
+  a
+    site
+      map
+        ....
+        .b..
+      legend
+        b bat
+    hall
+      map
+        ####
+        #g.#
+        #.G#
+      legend
+        g goblin
+        G goblin king",
+        )
+        .unwrap();
+        let spawns = spawn_set(&scenario);
+        assert_eq!(spawns.len(), 3);
+
+        let tiled = export(&scenario).unwrap();
+        assert_eq!(tiled.spawns().count(), 3);
+        // Spawn cells are plain floor in the terrain layers.
+        assert!(tiled.iter().all(|(_, c)| !"bgG".contains(c as u8 as char)));
+
+        // Go through JSON like the actual tool does.
+        let tiled: Map =
+            serde_json::from_str(&serde_json::to_string(&tiled).unwrap())
+                .unwrap();
+        import(&mut scenario, &tiled).unwrap();
+        assert_eq!(spawn_set(&scenario), spawns);
+    }
+
+    /// Tiled map with user-named tile layers and an external tileset.
+    const USER_MAP: &str = r#"{
+        "type": "map", "width": 0, "height": 0, "infinite": true,
+        "nextlayerid": 4, "nextobjectid": 1, "orientation": "orthogonal",
+        "renderorder": "right-down", "tiledversion": "1.10.0",
+        "tileheight": 8, "tilewidth": 8, "version": "1.10",
+        "tilesets": [{ "firstgid": 1, "source": "mapedit-tiles.tsx" }],
+        "layers": [
+            {
+                "name": "Caves", "id": 1, "visible": true, "opacity": 1,
+                "x": 0, "y": 0, "type": "tilelayer", "width": 2, "height": 1,
+                "chunks": [{ "data": [36, 0], "width": 2, "height": 1,
+                             "x": 0, "y": 0 }]
+            },
+            {
+                "name": "Ground floor", "id": 2, "visible": true,
+                "opacity": 1, "x": 0, "y": 0, "type": "tilelayer",
+                "width": 2, "height": 1,
+                "chunks": [{ "data": [47, 2147483695], "width": 2,
+                             "height": 1, "x": 0, "y": 0 }]
+            },
+            {
+                "name": "Towers", "id": 3, "visible": true, "opacity": 1,
+                "x": 0, "y": 0, "type": "tilelayer", "width": 2, "height": 1,
+                "chunks": [{ "data": [0, 36], "width": 2, "height": 1,
+                             "x": 0, "y": 0 }]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn user_named_layers() {
+        let tiled: Map = serde_json::from_str(USER_MAP).unwrap();
+        assert!(tiled.layers[1].is_ground());
+
+        let cells: BTreeSet<([i32; 3], char)> = tiled
+            .iter()
+            .map(|(p, c)| (p, char::from_u32(c).unwrap()))
+            .collect();
+        // Flipped tiles show up as the plain tile.
+        assert_eq!(
+            cells,
+            BTreeSet::from([
+                ([0, 0, -1], '#'),
+                ([0, 0, 0], '.'),
+                ([1, 0, 0], '.'),
+                ([1, 0, 1], '#'),
+            ])
+        );
+    }
+
+    #[test]
+    fn external_tileset() {
+        let tiled: Map = serde_json::from_str(USER_MAP).unwrap();
+        assert!(matches!(
+            &tiled.tilesets[0],
+            TilesetRef::External { firstgid: 1, source }
+                if source == Path::new("mapedit-tiles.tsx")
+        ));
+
+        // External reference survives reserialization.
+        let json = serde_json::to_string(&tiled).unwrap();
+        assert!(json.contains(r#""source":"mapedit-tiles.tsx""#));
+
+        // Exported maps can be switched over to an external tileset.
+        let mut map = Map::default();
+        map.set_tileset(Path::new("custom.tsx"));
+        for a in &map.tilesets {
+            let TilesetRef::External { source, .. } = a else {
+                panic!("Tileset not external");
+            };
+            assert_eq!(source, Path::new("custom.tsx"));
+        }
+        assert_eq!(map.tilesets[1].firstgid(), SPAWN_FIRSTGID);
+    }
+}
@@ -7,8 +7,9 @@ use glam::{ivec2, IVec2};
 use navni::{CharCell, Rgba};
 use util::{hash_map::Entry, HashMap};
 
-use crate::{Image, Pixel, Rect};
+use crate::{Image, Pixel, Rect, TILE_H, TILE_W};
 
+#[derive(Clone)]
 pub struct Buffer<P> {
     width: u32,
     height: u32,
@@ -126,6 +127,57 @@ impl Buffer<Rgba> {
 }
 
 impl Buffer<CharCell> {
+    /// Render the character cells into pixels using the default font.
+    ///
+    /// The first 16 colors come from the palette, the rest use the standard
+    /// xterm 256 color values.
+    pub fn rasterize(&self, palette: &[Rgba; 16]) -> Buffer<Rgba> {
+        use navni::X256Color;
+
+        let rgba = |col: X256Color| -> Rgba {
+            const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+            match col.0 {
+                i @ 0..16 => palette[i as usize],
+                i @ 16..232 => {
+                    let i = (i - 16) as usize;
+                    Rgba::new(
+                        LEVELS[i / 36],
+                        LEVELS[i / 6 % 6],
+                        LEVELS[i % 6],
+                        0xff,
+                    )
+                }
+                i => {
+                    let g = 8 + 10 * (i - 232);
+                    Rgba::new(g, g, g, 0xff)
+                }
+            }
+        };
+
+        let font = Rgba::default_font();
+        let bounds = self.area();
+
+        Buffer::from_fn(
+            self.width * TILE_W as u32,
+            self.height * TILE_H as u32,
+            |x, y| {
+                let cell = self.data[bounds.idx([x / TILE_W, y / TILE_H])];
+                let is_lit = char::from_u32(cell.c as u32)
+                    .and_then(|c| font.idx(c))
+                    .is_some_and(|i| {
+                        !font[i].get([x % TILE_W, y % TILE_H]).is_transparent()
+                    });
+
+                if is_lit {
+                    rgba(cell.foreground)
+                } else {
+                    rgba(cell.background)
+                }
+            },
+        )
+    }
+
     /// Create a screenshot ANSI coded string of the buffer.
     pub fn to_ansi(&self) -> String {
         use navni::X256Color;
@@ -226,6 +278,50 @@ impl<P: Pixel> Buffer<P> {
         &mut self.data
     }
 
+    /// Iterate the positions and new values of cells that differ from the
+    /// previous frame's buffer.
+    ///
+    /// If the buffers are of different size, every cell counts as changed.
+    pub fn diff<'a>(
+        &'a self,
+        prev: &'a Buffer<P>,
+    ) -> impl Iterator<Item = (IVec2, P)> + 'a {
+        let area = self.area();
+        let is_resized = self.dim() != prev.dim();
+
+        self.data
+            .iter()
+            .enumerate()
+            .filter(move |&(i, p)| is_resized || prev.data[i] != *p)
+            .map(move |(i, p)| (IVec2::from(area.get(i)), *p))
+    }
+
+    /// Draw an overlay buffer on top of this one at the given offset.
+    ///
+    /// Transparent overlay cells (default valued, eg. `c == 0` for character
+    /// cells) leave the base cell visible. Parts of the overlay outside this
+    /// buffer are clipped.
+    pub fn composite(
+        &mut self,
+        overlay: &Buffer<P>,
+        offset: impl Into<[i32; 2]>,
+    ) {
+        let offset = IVec2::from(offset.into());
+        let area = self.area();
+        let overlay_area = overlay.area();
+
+        for (i, p) in overlay.data.iter().enumerate() {
+            if p.is_transparent() {
+                continue;
+            }
+
+            let pos = IVec2::from(overlay_area.get(i)) + offset;
+            if area.contains(pos) {
+                self.data[area.idx(pos)] = *p;
+            }
+        }
+    }
+
     pub fn set_key_to_transparent(&mut self, key: P) {
         for p in self.pixels_mut() {
             if *p == key {
@@ -267,3 +363,60 @@ impl<P: Pixel> Buffer<P> {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use navni::{CharCell, Rgba, X256Color};
+
+    use super::Buffer;
+    use crate::{TILE_H, TILE_W};
+
+    #[test]
+    fn rasterize() {
+        let palette = [Rgba::new(0x10, 0x20, 0x30, 0xff); 16];
+        let mut buf: Buffer<CharCell> = Buffer::new(3, 2);
+        buf.data_mut()[0] = CharCell::new('#', X256Color(1), X256Color(0));
+
+        let img = buf.rasterize(&palette);
+        assert_eq!(img.width(), 3 * TILE_W);
+        assert_eq!(img.height(), 2 * TILE_H);
+
+        // Blank cells show the background color.
+        assert_eq!(img.data()[img.data().len() - 1], palette[0]);
+
+        // PNG encoding works for the result.
+        assert!(!img.to_png().is_empty());
+    }
+
+    #[test]
+    fn diff() {
+        let prev: Buffer<CharCell> = Buffer::new(4, 3);
+        let mut buf = prev.clone();
+        assert_eq!(buf.diff(&prev).count(), 0);
+
+        let cell = CharCell::new('@', X256Color(1), X256Color(0));
+        buf.data_mut()[6] = cell;
+        assert_eq!(
+            buf.diff(&prev).collect::<Vec<_>>(),
+            vec![(glam::ivec2(2, 1), cell)]
+        );
+
+        // Resizing invalidates everything.
+        let buf: Buffer<CharCell> = Buffer::new(2, 2);
+        assert_eq!(buf.diff(&prev).count(), 4);
+    }
+
+    #[test]
+    fn composite() {
+        let cell = |c| CharCell::new(c, X256Color(1), X256Color(0));
+
+        let mut base = Buffer::from_fn(3, 1, |_, _| cell('.'));
+        let mut overlay: Buffer<CharCell> = Buffer::new(3, 1);
+        overlay.data_mut()[0] = cell('@');
+        // Rest of overlay is transparent and the last cell goes out of
+        // bounds.
+
+        base.composite(&overlay, [1, 0]);
+        assert_eq!(base.data(), &[cell('.'), cell('@'), cell('.')]);
+    }
+}
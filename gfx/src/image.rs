@@ -26,6 +26,18 @@ impl<'a, P: Pixel> Field<P> for &'a Buffer<P> {
     }
 }
 
+impl<P: Pixel> Field<P> for Buffer<P> {
+    fn get(&self, pos: [i32; 2]) -> P {
+        <&Buffer<P> as Field<P>>::get(&self, pos)
+    }
+}
+
+impl<'a, P: Pixel, F: Field<P>> Field<P> for &'a Image<P, F> {
+    fn get(&self, pos: [i32; 2]) -> P {
+        Image::get(self, pos)
+    }
+}
+
 impl<P: Pixel, F: Fn([i32; 2]) -> P> Field<P> for F {
     fn get(&self, pos: [i32; 2]) -> P {
         self(pos)
@@ -116,6 +128,15 @@ impl FromStr for SubImage<Rgba> {
     }
 }
 
+impl Image<Rgba, Buffer<Rgba>> {
+    /// Decode PNG data into an image that owns its pixels.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let buf = Buffer::from_bytes(bytes)?;
+        let bounds = buf.area();
+        Ok(Image::new(buf, bounds))
+    }
+}
+
 impl<P: Pixel, F: Field<P>> Image<P, F> {
     pub fn new(field: F, bounds: Rect) -> Image<P, F> {
         Image {
@@ -125,6 +146,30 @@ impl<P: Pixel, F: Field<P>> Image<P, F> {
         }
     }
 
+    /// Slice the `n`th `dim` sized tile from the image when treating it as
+    /// a tilesheet. Tiles are counted left to right, top to bottom.
+    ///
+    /// Returns `None` if the sheet doesn't have tile `n`.
+    pub fn tile(
+        &self,
+        dim: impl Into<[i32; 2]>,
+        n: usize,
+    ) -> Option<Image<P, &Self>> {
+        let [w, h] = dim.into();
+        if w <= 0 || h <= 0 {
+            return None;
+        }
+
+        let pitch = (self.width() / w) as usize;
+        let rows = (self.height() / h) as usize;
+        if n >= pitch * rows {
+            return None;
+        }
+
+        let (x, y) = ((n % pitch) as i32, (n / pitch) as i32);
+        Some(Image::new(self, Rect::sized([w, h]) + [w * x, h * y]))
+    }
+
     pub fn get(&self, pos: impl Into<[i32; 2]>) -> P {
         self.field
             .get((v2(pos.into()) + v2(self.bounds.min())).into())
@@ -168,3 +213,41 @@ impl<P: Pixel, F: Field<P>> Image<P, F> {
         self.set_area(Rect::sized([w, self.height()]));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use navni::Rgba;
+
+    use crate::{Buffer, Image};
+
+    #[test]
+    fn png_tilesheet() {
+        let red = Rgba::new(0xff, 0, 0, 0xff);
+        let black = Rgba::new(0, 0, 0, 0xff);
+
+        // Two 8x8 tiles side by side, one red pixel in the second tile.
+        let sheet =
+            Buffer::from_fn(
+                16,
+                8,
+                |x, y| {
+                    if (x, y) == (9, 2) {
+                        red
+                    } else {
+                        black
+                    }
+                },
+            );
+
+        let img = Image::from_png(&sheet.to_png()).unwrap();
+        assert_eq!(img.width(), 16);
+        assert_eq!(img.get([9, 2]), red);
+
+        let tile = img.tile([8, 8], 1).unwrap();
+        assert_eq!(tile.width(), 8);
+        assert_eq!(tile.get([1, 2]), red);
+        assert_eq!(tile.get([0, 0]), black);
+
+        assert!(img.tile([8, 8], 2).is_none());
+    }
+}
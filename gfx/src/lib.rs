@@ -13,3 +13,9 @@ pub use crate::image::{Field, Image, SubImage};
 pub mod prelude;
 
 pub type Rect = util::Rect<i32>;
+
+/// Pixel width of a character cell rendered with the default font.
+pub const TILE_W: i32 = 8;
+
+/// Pixel height of a character cell rendered with the default font.
+pub const TILE_H: i32 = 8;
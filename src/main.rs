@@ -1,13 +1,13 @@
 // Release builds made for Windows don't create a terminal window when run.
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::OnceLock};
 
 use clap::Parser;
 use engine::prelude::*;
-use ui::{ask, game};
-use util::{IncrementalOutline, Outline, Silo};
+use ui::{ask, game, InputMap, Replay};
+use util::{IncrementalOutline, Layout, Outline, Silo};
 use version::VERSION;
-use world::settings;
+use world::{settings, Data};
 
 mod map_view;
 mod run;
@@ -17,13 +17,19 @@ mod view;
 #[derive(Parser, Debug)]
 struct Args {
     /// Start a new game, optionally with specific seed
+    ///
+    /// The seed can also be given in the word form shown in the log.
     #[arg(
         long,
         value_name = "SEED",
-        value_parser = |e: &str| Ok::<Silo, &str>(Silo::new(e)),
+        value_parser = |e: &str| Ok::<Silo, &str>(Silo::from_seed(e)),
     )]
     new_game: Option<Option<Silo>>,
 
+    /// Play back a recorded input log instead of loading the save
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+
     /// Load game data from a given path instead of using default data.
     #[arg(long, value_name = "PATH")]
     data_dir: Option<PathBuf>,
@@ -36,23 +42,26 @@ struct Args {
     )]
     mods: Vec<PathBuf>,
 
+    /// Keyboard layout (qwerty, colemak or dvorak), detected from the
+    /// system if not given
+    #[arg(
+        long,
+        value_name = "LAYOUT",
+        value_parser = |e: &str| e.parse::<Layout>().map_err(|e| e.to_string()),
+    )]
+    layout: Option<Layout>,
+
     /// Display game version and exit
     #[arg(short = 'v', long)]
     version: bool,
 }
 
-fn main() -> anyhow::Result<()> {
-    util::panic_handler();
-
-    let args = Args::parse();
-
-    if args.version {
-        println!("{} version {VERSION}", settings().title);
-        return Ok(());
-    }
+static ARGS: OnceLock<Args> = OnceLock::new();
 
+/// Load the game data and apply mods to it.
+fn load_data(args: &Args) -> anyhow::Result<Data> {
     let mut mods: Vec<IncrementalOutline> = Default::default();
-    for path in args.mods {
+    for path in &args.mods {
         let md = util::dir_to_idm(path)?;
         mods.push(md);
     }
@@ -71,13 +80,46 @@ fn main() -> anyhow::Result<()> {
         data += md;
     }
 
-    world::register_data(idm::transmute(&data)?);
+    Ok(idm::transmute(&data)?)
+}
+
+/// Re-read data and mod files from disk and replace the current game data.
+///
+/// Used from wizard mode to try out content changes without restarting.
+pub fn reload_data() -> anyhow::Result<()> {
+    let args = ARGS.get().expect("reload_data: args not initialized");
+    world::reload_data(load_data(args)?)
+}
+
+fn main() -> anyhow::Result<()> {
+    util::panic_handler();
+
+    let args = ARGS.get_or_init(Args::parse);
+
+    if args.version {
+        println!("{} version {VERSION}", settings().title);
+        return Ok(());
+    }
+
+    world::register_data(load_data(args)?)?;
+
+    let replay: Option<Replay> = if let Some(path) = args.replay.as_ref() {
+        Some(idm::from_str(&std::fs::read_to_string(path)?)?)
+    } else {
+        None
+    };
+    // Don't let a replayed session overwrite the real save.
+    let is_replay = replay.is_some();
 
     navni::logger::start(&settings().id);
 
     navni::run(&settings().id, async move {
         ui::init_game();
 
+        if let Some(layout) = args.layout {
+            game().input_map = InputMap::for_layout(layout);
+        }
+
         if args.new_game.is_some() {
             log::info!("New game requested, deleting any existing saves");
             game().delete_save(&settings().id);
@@ -85,12 +127,34 @@ fn main() -> anyhow::Result<()> {
 
         let user_name = util::user_name();
 
-        loop {
+        if let Some(replay) = replay {
+            log::info!("replaying seed: {}", replay.seed);
+            if let Err(e) = game().play_back(replay) {
+                log::warn!("{e}");
+                msg!(Warning: "{e}");
+            }
+        }
+
+        if !is_replay {
+            game().autosave_interval = settings().autosave_interval;
+        }
+        game().save_on_exit = !is_replay;
+
+        while !is_replay {
             // Restore game or init a new one.
-            match game().load(&settings().id) {
+            let loaded = game().load(&settings().id);
+
+            // Fall back to the newest autosave if the main save is missing
+            // or corrupt.
+            if !matches!(loaded, Ok(Some(_))) && restore_autosave().await {
+                msg!(System: "Restored the game from an autosave.");
+                break;
+            }
+
+            match loaded {
                 Ok(None) => {
                     // No save file found, initialize a new game.
-                    let seed = if let Some(Some(seed)) = args.new_game {
+                    let seed = if let Some(Some(seed)) = args.new_game.clone() {
                         // A fixed seed was given, use that.
                         seed
                     } else {
@@ -101,9 +165,9 @@ fn main() -> anyhow::Result<()> {
                         )
                     };
 
-                    log::info!("seed: {seed}");
+                    log::info!("seed: {seed} ({})", seed.to_words());
 
-                    game().r = Runtime::new(seed).unwrap();
+                    game().start(seed).unwrap();
 
                     if user_name == "Unknown" {
                         msg!("Welcome to {}!", settings().title);
@@ -123,6 +187,16 @@ fn main() -> anyhow::Result<()> {
                 }
                 Err(_) => {
                     game().draw().await;
+                    if let Ok((save, lost)) = game().salvage(&settings().id) {
+                        if ask("Corrupt save file detected. Recover it?").await
+                        {
+                            game().replace_runtime(save);
+                            msg!(Warning: "Save was damaged, lost {}.",
+                                lost.join(", "));
+                            break;
+                        }
+                    }
+
                     if ask("Corrupt save file detected. Delete it?").await {
                         game().delete_save(&settings().id);
                         continue;
@@ -135,7 +209,7 @@ fn main() -> anyhow::Result<()> {
             }
             break;
         }
-        msg!("Build version {}", VERSION);
+        msg!(System: "Build version {}", VERSION);
 
         game().viewpoint = game()
             .r
@@ -144,12 +218,15 @@ fn main() -> anyhow::Result<()> {
             .unwrap_or_default();
         game().camera = game().viewpoint;
 
-        navni::set_palette(&ui::LIGHT_PALETTE);
+        game().load_prefs(&settings().id);
+        navni::set_palette(&game().palette);
 
         run::main_gameplay().await;
 
         // Save the game if we exited with the game still running.
-        if !game().is_game_over() {
+        if !game().save_on_exit {
+            // Leave existing saves alone.
+        } else if !game().is_game_over() {
             game().save(&settings().id);
         } else {
             game().delete_save(&settings().id);
@@ -158,3 +235,24 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Ask whether to restore the newest autosave, return true if one was
+/// loaded.
+async fn restore_autosave() -> bool {
+    let Some(info) = game().newest_autosave(&settings().id) else {
+        return false;
+    };
+
+    game().draw().await;
+    if !ask(format!("Restore autosave from turn {}?", info.turns)).await {
+        return false;
+    }
+
+    match game().load_autosave(&settings().id, info.slot) {
+        Ok(Some(save)) => {
+            game().replace_runtime(save);
+            true
+        }
+        _ => false,
+    }
+}
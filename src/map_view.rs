@@ -1,7 +1,8 @@
 use engine::prelude::*;
-use glam::{ivec3, IVec3};
 use navni::{prelude::*, X256Color as X};
-use ui::{prelude::*, render_fog, DisplayTile, SectorView};
+use ui::{
+    health_bar, prelude::*, render_fog, AllyState, DisplayTile, SectorView,
+};
 use util::{v2, PolyLineIter};
 use world::{Level, Zone};
 
@@ -26,7 +27,10 @@ pub enum MapAction {
     SelectActive(Vec<Entity>),
 
     BecomePlayer(Entity),
-    NextEntity,
+
+    /// Keyboard command that goes through `Game::process_action` so it gets
+    /// recorded in the replay log.
+    Input(InputAction),
 }
 
 use MapAction::*;
@@ -46,18 +50,8 @@ pub fn view_map(win: &Window) -> Option<MapAction> {
 
     let mut camera = game().camera;
 
-    // Get scroll input.
-    let mut scroll = match input_press() {
-        Some(InputAction::ScrollNorth) => ivec3(0, -1, 0),
-        Some(InputAction::ScrollEast) => ivec3(1, 0, 0),
-        Some(InputAction::ScrollSouth) => ivec3(0, 1, 0),
-        Some(InputAction::ScrollWest) => ivec3(-1, 0, 0),
-        _ => Default::default(),
-    };
-    if scroll == IVec3::ZERO {
-        scroll = v2(navni::mouse_state().scroll_delta()).extend(0);
-    }
-    camera += scroll * SCROLL_STEP;
+    // Get mouse wheel scroll, scroll keys go through `Game::process_action`.
+    camera += v2(navni::mouse_state().scroll_delta()).extend(0) * SCROLL_STEP;
 
     let view = SectorView::new(win.dim(), camera);
 
@@ -83,6 +77,8 @@ pub fn view_map(win: &Window) -> Option<MapAction> {
         )
     };
 
+    let visible = game().r.visible_cells();
+
     for (p, loc) in view.iter(win.dim()) {
         DisplayTile::new(game(), loc).render(win, p);
 
@@ -91,23 +87,27 @@ pub fn view_map(win: &Window) -> Option<MapAction> {
             win.put(p, cell);
         }
 
-        if let Some(e) = loc.snap_above_floor(&game().r).mob_at(game()) {
+        let mob_loc = loc.snap_above_floor(r);
+        if !loc.is_visible(r, &visible) {
+            // Show remembered enemies instead of the actual ones out of
+            // view, the fog will dim them.
+            if let Some((_, icon)) = r.last_seen_at(mob_loc) {
+                win.put(p, CharCell::c(icon));
+            }
+        } else if let Some(e) = mob_loc.mob_at(game()) {
             let mut cell = CharCell::c(e.icon(r));
             if e.is_player_aligned(game()) {
                 if game().r.player() == Some(e) {
                     cell.set_c('@');
-                } else if !e.can_be_commanded(r) {
-                    // Friendly mob out of moves.
-                    cell = cell.col(X::GRAY);
-                } else if e.goal(r) != Goal::FollowPlayer {
-                    // Frindly mob out on a mission.
-                    cell = cell.col(X::GREEN);
-                } else if e.acts_before_next_player_frame(r) {
-                    // Friendly mob ready for next command
-                    cell = cell.col(X::AQUA);
                 } else {
-                    // Friendly mob still building up it's actions.
-                    cell = cell.col(X::TEAL);
+                    // Show what the friendly mob is up to with both color
+                    // and a marker glyph next to it.
+                    let state = AllyState::new(r, e);
+                    cell = cell.col(state.color());
+                    win.put(
+                        p + ivec2(1, 0),
+                        CharCell::c(state.marker()).col(state.color()),
+                    );
                 }
 
                 if game().selected().any(|a| a == e) {
@@ -115,6 +115,15 @@ pub fn view_map(win: &Window) -> Option<MapAction> {
                 }
             }
             win.put(p, cell);
+
+            if game().show_health_bars && e.wounds(r) > 0 {
+                for (i, cell) in health_bar(e.wounds(r), e.max_wounds(r))
+                    .into_iter()
+                    .enumerate()
+                {
+                    win.put(p + ivec2(i as i32, -1), cell);
+                }
+            }
         }
     }
 
@@ -122,7 +131,7 @@ pub fn view_map(win: &Window) -> Option<MapAction> {
     game().draw_ground_anims(win, view);
 
     for (p, loc) in view.iter(win.dim()) {
-        render_fog(game(), win, p, loc);
+        render_fog(game(), win, p, loc, &visible);
     }
 
     // Cover up area outside the sector if viewport is big enough to show it.
@@ -140,6 +149,7 @@ pub fn view_map(win: &Window) -> Option<MapAction> {
     {
         let player_pos = game().current_active().and_then(|a| a.loc(game()));
         let path = game().planned_path.posns();
+        let attack_move = game().attack_move;
 
         for p in PolyLineIter::new(
             player_pos
@@ -150,9 +160,28 @@ pub fn view_map(win: &Window) -> Option<MapAction> {
         .skip(1)
         {
             if let Some(c) = win.get_mut(p) {
+                // Show attack-move paths in red.
+                if attack_move {
+                    *c = c.col(X::RED);
+                }
                 c.invert();
             }
         }
+
+        // Show how long the trip will take next to the destination.
+        if let (Some(&dest), Some(turns)) = (
+            path.first(),
+            game()
+                .current_active()
+                .and_then(|a| a.travel_turns(game(), path)),
+        ) {
+            let text = if turns == 1 {
+                "1 turn".to_string()
+            } else {
+                format!("{turns} turns")
+            };
+            win.write(view.project(dest) + ivec2(2, 0), &text);
+        }
     }
 
     let mut mouse = navni::mouse_state();
@@ -272,14 +301,10 @@ pub fn view_map(win: &Window) -> Option<MapAction> {
 
     // Capture direct commands.
     if let Some(a) = input_press() {
-        if let Ok(act) = Action::try_from(a) {
-            ret = Some(DirectCommand(act));
-        } else if a == InputAction::Cycle {
-            ret = Some(NextEntity);
-        } else if a == InputAction::BecomePlayer {
-            if let Some(p) = game().current_active() {
-                ret = Some(BecomePlayer(p));
-            }
+        if Action::try_from(a).is_ok()
+            || matches!(a, InputAction::Cycle | InputAction::BecomePlayer)
+        {
+            ret = Some(Input(a));
         }
     }
 
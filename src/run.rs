@@ -1,10 +1,13 @@
 use std::fmt::Write;
 
-use engine::prelude::*;
+use engine::{prelude::*, WizardCommand};
 use navni::X256Color as X;
-use ui::{ask, prelude::*};
+use ui::{
+    ask, msg_color, prelude::*, settings_menu, wizard_menu, SettingsAction,
+    WizardAction,
+};
 use util::{wizard_mode, writeln};
-use world::{settings, DOWN, EAST, NORTH, SOUTH, UP, WEST};
+use world::settings;
 
 use crate::{
     map_view::{view_map, MapAction::*},
@@ -12,6 +15,9 @@ use crate::{
 };
 
 pub async fn main_gameplay() {
+    // Last map cell the mouse was over, used by wizard commands.
+    let mut cursor_loc = None;
+
     loop {
         game().tick().await;
         game().draw().await;
@@ -35,6 +41,8 @@ pub async fn main_gameplay() {
             Some(Order(goal)) => game().act(goal),
             Some(RepositionCamera(loc)) => game().camera = loc,
             Some(HoverOver(loc)) => {
+                cursor_loc = Some(loc);
+
                 if loc.is_explored(game()) {
                     if let Some(desc) = loc.describe(game()) {
                         let (mut text_box, _) = main.split_bottom(1);
@@ -67,14 +75,29 @@ pub async fn main_gameplay() {
                 }
             }
             Some(SelectActive(sel)) => game().set_selection(sel),
-            Some(BecomePlayer(e)) => e.become_player(game()),
-            Some(NextEntity) => game().select_next_commandable(false),
+            Some(BecomePlayer(e)) => {
+                // Make sure the player doesn't leave their current character
+                // in the middle of a fight by accident.
+                let leaving_fight = e.can_become_player(game())
+                    && game()
+                        .r
+                        .player()
+                        .is_some_and(|p| p != e && p.is_threatened(game()));
+                if !leaving_fight
+                    || ask("Leave your character while under threat?").await
+                {
+                    game().become_player(e);
+                }
+            }
+            Some(Input(a)) => game().process_action(a),
             None => {}
         }
 
         // Print messages.
         let mut cur = Cursor::new(main);
-        for m in game().msg.iter() {
+        for (kind, m) in game().msg.iter() {
+            cur.win.foreground_col =
+                msg_color(*kind).unwrap_or(main.foreground_col);
             writeln!(cur, "{m}");
         }
 
@@ -100,7 +123,7 @@ pub async fn main_gameplay() {
         // where to save the game.
         if navni::keypress().is("Esc") && !game().is_game_over() {
             game().save(&settings().id);
-            msg!("Game saved.");
+            msg!(System: "Game saved.");
         }
 
         // Debug keys, not for regular gameplay.
@@ -117,6 +140,10 @@ pub async fn main_gameplay() {
                     msg!("Powered up to level {}", player.level_up(game()));
                 }
             }
+
+            if navni::keypress().is("~") {
+                wizard_command(cursor_loc).await;
+            }
         }
 
         match input_press().or(side_action) {
@@ -135,6 +162,13 @@ pub async fn main_gameplay() {
                             }
                         }
                     }
+                    Some(e) if e.can_be_eaten(game()) => {
+                        if ask(format!("Eat {}?", e.noun(game()).the_name()))
+                            .await
+                        {
+                            game().act(Action::Eat(e));
+                        }
+                    }
                     Some(e) if e.can_be_equipped(game()) => {
                         if e.is_equipped(game()) {
                             game().act(Action::Unequip(e));
@@ -170,14 +204,7 @@ pub async fn main_gameplay() {
                 }
             }
             Some(InputAction::Cancel) if !side.is_zero() => {
-                if let Some(p) = game().current_active() {
-                    if p.is_player(game()) {
-                        p.clear_goal(game());
-                    } else {
-                        p.set_goal(game(), Goal::FollowPlayer);
-                    }
-                }
-                game().set_selection([]);
+                game().process_action(InputAction::Cancel)
             }
             Some(InputAction::Roam) if !side.is_zero() => {
                 if let Some(p) = game().current_active() {
@@ -188,23 +215,88 @@ pub async fn main_gameplay() {
                     }
                 }
             }
-            Some(InputAction::QuitGame) => {
-                break;
-            }
-            Some(InputAction::Retire) => {
-                if ask("Really retire your character?").await {
-                    game().retire();
-                    break;
+            Some(InputAction::DumpMap) if wizard_mode() => {
+                const DUMP_FILE: &str = "map-dump.txt";
+                match std::fs::write(DUMP_FILE, game().dump_map()) {
+                    Ok(()) => msg!(System: "Wrote map to {DUMP_FILE}."),
+                    Err(e) => msg!(Warning: "{e}"),
                 }
             }
-            Some(InputAction::TravelNorth) => game().travel(NORTH),
-            Some(InputAction::TravelEast) => game().travel(EAST),
-            Some(InputAction::TravelSouth) => game().travel(SOUTH),
-            Some(InputAction::TravelWest) => game().travel(WEST),
-            Some(InputAction::TravelUp) => game().travel(UP),
-            Some(InputAction::TravelDown) => game().travel(DOWN),
+            Some(InputAction::ToggleTheme) => {
+                game().process_action(InputAction::ToggleTheme);
+                navni::set_palette(&game().palette);
+                game().save_prefs(&settings().id);
+            }
+            Some(InputAction::Settings) => match settings_menu().await {
+                SettingsAction::SetTheme(theme) => {
+                    game().set_theme(theme);
+                    navni::set_palette(&game().palette);
+                    game().save_prefs(&settings().id);
+                }
+                SettingsAction::ToggleConfirm(danger) => {
+                    if !game().confirm.remove(&danger) {
+                        game().confirm.insert(danger);
+                    }
+                    game().save_prefs(&settings().id);
+                }
+                SettingsAction::Leave => {}
+            },
+            Some(
+                a @ (InputAction::TravelNorth
+                | InputAction::TravelEast
+                | InputAction::TravelSouth
+                | InputAction::TravelWest
+                | InputAction::TravelUp
+                | InputAction::TravelDown
+                | InputAction::ScrollNorth
+                | InputAction::ScrollEast
+                | InputAction::ScrollSouth
+                | InputAction::ScrollWest
+                | InputAction::ToggleHealthBars
+                | InputAction::AttackMove
+                | InputAction::QuitGame
+                | InputAction::Retire),
+            ) => game().process_action(a),
             _ => {}
         }
+
+        if let Some(question) = game().pending_question() {
+            let confirmed = ask(question).await;
+            game().confirm_pending(confirmed);
+        }
+
+        if game().is_quitting() || game().is_game_over() {
+            break;
+        }
+    }
+}
+
+async fn wizard_command(cursor_loc: Option<Location>) {
+    use WizardAction::*;
+
+    let Some(loc) =
+        cursor_loc.or_else(|| game().r.player().and_then(|p| p.loc(game())))
+    else {
+        return;
+    };
+
+    let cmd = match wizard_menu().await {
+        RevealSector => WizardCommand::RevealSector(loc),
+        Teleport => WizardCommand::Teleport(loc),
+        Spawn(name) => WizardCommand::Spawn(name, loc),
+        ToggleInvulnerable => WizardCommand::ToggleInvulnerable,
+        ReloadData => {
+            match crate::reload_data() {
+                Ok(()) => msg!(System: "Reloaded game data."),
+                Err(e) => msg!(Warning: "{e}"),
+            }
+            return;
+        }
+        Leave => return,
+    };
+
+    if let Err(e) = game().r.wizard(wizard_mode(), cmd) {
+        msg!(Warning: "{e}");
     }
 }
 
@@ -14,23 +14,32 @@ pub fn item_list(
     win.clear();
     let g = game();
 
-    let keys = "abcdefghijklmnopqrstuvwxyz";
-    let items: Vec<Entity> = mob.contents(g).filter(filter).collect();
+    // Items keep their letters while they stay in the inventory, list them
+    // in letter order. Items that ran out of letters go last.
+    let mut items: Vec<(Option<char>, Entity)> = mob
+        .contents(g)
+        .filter(filter)
+        .map(|e| (e.inventory_letter(g), e))
+        .collect();
+    items.sort_by_key(|&(k, _)| (k.is_none(), k));
 
     let mut cur = Cursor::new(*win);
 
     let keypress = navni::keypress();
 
-    for (k, e) in keys.chars().zip(items.into_iter()) {
+    for (k, e) in items {
         if cur.print_button(&format!(
-            "{k}) {}{}",
+            "{}) {}{}",
+            k.unwrap_or(' '),
             e.desc(&g.r),
             if e.is_equipped(&g.r) {
                 " (equipped)"
+            } else if e.is_upgrade_for(&g.r, &mob) {
+                " (upgrade)"
             } else {
                 ""
             }
-        )) || keypress.key() == Key::Char(k)
+        )) || k.is_some_and(|k| keypress.key() == Key::Char(k))
         {
             return Some(e);
         }
@@ -100,6 +109,13 @@ impl Widget for StatusPanel {
         let max_hp = player.max_wounds(g);
         let hp = max_hp - player.wounds(&g.r).min(max_hp);
         writeln!(cur, "Health: {hp} / {max_hp}");
+        writeln!(
+            cur,
+            "Experience: {} / {}",
+            player.xp(g),
+            player.xp_to_level_up(g)
+        );
+        writeln!(cur, "Turn: {}", g.r.turn_count());
 
         let cash = player.carried_cash(&g.r);
         if cash > 0 {
@@ -107,6 +123,20 @@ impl Widget for StatusPanel {
         } else {
             writeln!(cur);
         }
+        if let Some(loc) = player.loc(g) {
+            writeln!(cur, "{}", loc.region_name(g));
+        }
+
+        // Stealth mode and status effects with turns remaining.
+        let mut status: Vec<String> = player
+            .active_buffs(&g.r)
+            .into_iter()
+            .map(|(buff, turns)| format!("{buff} {turns}"))
+            .collect();
+        if player.is_sneaking(&g.r) {
+            status.insert(0, "Sneaking".into());
+        }
+        writeln!(cur, "{}", status.join(", "));
 
         writeln!(cur);
         writeln!(cur, "------- Controls -------");
@@ -177,6 +207,14 @@ impl Widget for StatusPanel {
         command_help(&mut cur, Cancel, "cancel");
         writeln!(cur);
 
+        command_help(&mut cur, Search, "search");
+        cur.pos.x = win.width() / 2;
+        command_help(&mut cur, Sneak, "sneak");
+        writeln!(cur);
+
+        command_help(&mut cur, AttackMove, "attack-move");
+        writeln!(cur);
+
         cur.pos.y = win.height() - 2;
         cur.pos.x = 0;
         writeln!(cur, "{}", g.r.now());
@@ -149,6 +149,56 @@ impl Anim for Particle {
     }
 }
 
+/// Damage amount floating up from a mob.
+pub struct DamageNumber {
+    origin: Anchor,
+    text: String,
+    lifetime: usize,
+}
+
+impl DamageNumber {
+    pub const LIFETIME: usize = 24;
+
+    pub fn new(at: impl Into<Anchor>, amount: i32) -> Self {
+        DamageNumber {
+            origin: at.into(),
+            text: amount.to_string(),
+            lifetime: Self::LIFETIME,
+        }
+    }
+}
+
+impl Anim for DamageNumber {
+    fn render(
+        &mut self,
+        r: &Runtime,
+        n_updates: u32,
+        win: &Window,
+        view: SectorView,
+    ) -> bool {
+        let Some(origin) = self.origin.project(r, view) else {
+            return false;
+        };
+
+        // Rise up a cell every third of the lifetime and fade out at the
+        // end.
+        let rise = ((Self::LIFETIME - self.lifetime) * 3 / Self::LIFETIME)
+            .min(2) as i32;
+        let col = if self.lifetime > Self::LIFETIME / 3 {
+            X::RED
+        } else {
+            X::MAROON
+        };
+
+        let pos = origin - ivec2(0, 1 + rise);
+        for (i, c) in self.text.chars().enumerate() {
+            win.put(pos + ivec2(i as i32, 0), CharCell::c(c).col(col));
+        }
+
+        countdown(n_updates, &mut self.lifetime)
+    }
+}
+
 pub struct Explosion {
     origin: Anchor,
     lifetime: usize,
@@ -243,3 +293,28 @@ impl Anim for Lightning {
         countdown(n_updates, &mut self.lifetime)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use util::Silo;
+
+    use super::{Anim, DamageNumber};
+    use crate::{prelude::*, SectorView};
+    use engine::prelude::*;
+
+    #[test]
+    fn damage_number_expires() {
+        world::register_data_from("../data").unwrap();
+        let r = Runtime::new(Silo::new("rand0m")).unwrap();
+        let loc = r.player().unwrap().loc(&r).unwrap();
+        let view = SectorView::new([80, 40], loc);
+        // Zero-sized window, nothing gets drawn.
+        let win = Window::default();
+
+        let mut anim = DamageNumber::new(loc, 12);
+        for _ in 0..DamageNumber::LIFETIME {
+            assert!(anim.render(&r, 1, &win, view));
+        }
+        assert!(!anim.render(&r, 1, &win, view));
+    }
+}
@@ -28,16 +28,18 @@ async fn render(win: &Window) -> Option<CampAction> {
         return None;
     };
 
-    // Dim out the level-up when you don't have enough cash
+    // Dim out the level-up when you don't have enough experience or cash
     if !player.can_afford_level_up(game()) {
         cur.win.foreground_col = X::GRAY;
     }
 
-    if cur.print_button(&format!(
-        "Raise e)ssence ({}$)",
-        player.level_up_cost(game())
-    )) || key.is("e")
-    {
+    let cost = if player.has_xp_for_level_up(game()) {
+        format!("{} XP", player.xp_to_level_up(game()))
+    } else {
+        format!("{}$", player.level_up_cost(game()))
+    };
+
+    if cur.print_button(&format!("Raise e)ssence ({cost})")) || key.is("e") {
         // There needs to be a level-up menu here if there's stat or perk
         // selections involved.
         return Some(LevelUp);
@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 
-use crate::{prelude::*, ConfirmationDialog};
+use engine::prelude::*;
+use util::StrExt;
+
+use crate::{prelude::*, ConfirmationDialog, DialogueBox};
 use navni::X256Color as X;
 
 pub async fn ask(msg: impl Into<Cow<'_, str>>) -> bool {
@@ -19,3 +22,42 @@ pub async fn ask(msg: impl Into<Cow<'_, str>>) -> bool {
         }
     }
 }
+
+/// Run a conversation with an NPC until it ends or the player leaves.
+pub async fn talk(speaker: Entity) {
+    let Some(dialogue) = speaker.dialogue(game()) else {
+        return;
+    };
+    let Some((mut node, _)) = dialogue.first() else {
+        return;
+    };
+    let name = speaker.noun(game()).the_name().capitalize();
+
+    loop {
+        let dialog = DialogueBox::new(name.clone(), &dialogue[node]);
+        let mut win = Window::root().center(dialog.preferred_size().unwrap());
+        win.foreground_col = X::BROWN;
+
+        let _backdrop = Backdrop::from(win);
+        let choice = loop {
+            if game().draw().await.is_none() {
+                return;
+            }
+
+            if let Some(ret) = dialog.render(&win) {
+                break ret;
+            }
+        };
+
+        let Some(n) = choice else {
+            return;
+        };
+        game().act(Action::Answer(speaker, node.clone(), n));
+
+        let ((_,), choice) = &dialogue[node].choices[n];
+        match &choice.next {
+            Some(next) => node = next,
+            None => return,
+        }
+    }
+}
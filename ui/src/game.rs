@@ -1,17 +1,26 @@
-use anyhow::{anyhow, Result};
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::{bail, Result};
 use engine::prelude::*;
 use glam::{ivec3, IVec3};
 use navni::{prelude::*, X256Color as X};
-use util::{s4, s8, Layout, SameThread, StrExt};
-use world::{Level, Zone, DOWN, EAST, NORTH, SOUTH, UP, WEST};
+use util::{s4, s8, Layout, Neighbors2D, SameThread, Silo, StrExt};
+use world::{settings, Block, Level, Zone, DOWN, EAST, NORTH, SOUTH, UP, WEST};
 
-use crate::{anim, camp_menu::camp, prelude::*, Command, InputMap, SectorView};
+use crate::{
+    anim, camp_menu::camp, prelude::*, Command, Danger, Input, InputMap,
+    Replay, SectorView, Theme,
+};
 
 // Maximum GUI terminal size.
 // Get just about to a size where a whole sector fits on map screen.
 const WIDTH: u32 = 150;
 const HEIGHT: u32 = 45;
 
+/// How many frames it takes for a smoothly scrolling camera to catch up with
+/// the viewpoint.
+const CAMERA_EASE_FRAMES: i32 = 6;
+
 /// Toplevel context object for game state.
 pub struct Game {
     same_thread: SameThread,
@@ -27,13 +36,16 @@ pub struct Game {
     pub viewpoint: Location,
     /// Camera position on screen, can be scrolled away from viewpoint.
     pub camera: Location,
+    /// Scroll the camera smoothly instead of snapping it to the viewpoint.
+    pub smooth_camera: bool,
+    camera_frames_left: i32,
 
     selection: Vec<Entity>,
     pub planned_path: PlannedPath,
 
     /// Receiver for engine events.
     recv: Receiver,
-    pub msg: Vec<String>,
+    pub msg: Vec<(MsgKind, String)>,
 
     /// Animations below the fog of war.
     ground_anims: Vec<Box<dyn Anim>>,
@@ -42,7 +54,36 @@ pub struct Game {
 
     pub input_map: InputMap,
 
+    /// Show health bars over wounded mobs.
+    pub show_health_bars: bool,
+
+    /// The next move order the player group gets is an attack-move.
+    pub attack_move: bool,
+
+    /// Colors used for the 16 base terminal colors.
+    pub palette: [navni::Rgba; 16],
+    /// Color scheme the palette comes from.
+    pub theme: Theme,
+
+    /// Actions that need to be confirmed before they go through.
+    pub confirm: BTreeSet<Danger>,
+    /// Action waiting for the player to confirm it.
+    pending: Option<(InputAction, Danger)>,
+
+    /// Log of player inputs for replaying the session.
+    pub replay: Replay,
+    /// An input is being run, inputs it makes in turn are not logged.
+    running_input: bool,
+    /// The game gets saved when the player quits.
+    pub save_on_exit: bool,
+
+    /// Turns between autosaves, zero disables autosaving.
+    pub autosave_interval: i64,
+    last_autosave: Instant,
+    next_autosave: usize,
+
     retired: bool,
+    quitting: bool,
 }
 
 static mut GAME: Option<Game> = None;
@@ -87,7 +128,7 @@ impl Default for Game {
     fn default() -> Self {
         let layout = Layout::system_layout();
         log::info!("detected {layout:?} keyboard layout");
-        let input_map = InputMap::for_layout(Layout::system_layout());
+        let input_map = InputMap::for_layout(layout);
 
         Game {
             same_thread: Default::default(),
@@ -96,6 +137,8 @@ impl Default for Game {
             s: Buffer::new(WIDTH, HEIGHT),
             viewpoint: Default::default(),
             camera: Default::default(),
+            smooth_camera: true,
+            camera_frames_left: Default::default(),
             selection: Default::default(),
             planned_path: Default::default(),
             recv: Default::default(),
@@ -103,7 +146,20 @@ impl Default for Game {
             ground_anims: Default::default(),
             sky_anims: Default::default(),
             input_map,
+            show_health_bars: true,
+            attack_move: false,
+            palette: crate::LIGHT_PALETTE,
+            theme: Theme::Light,
+            confirm: Danger::ALL.into_iter().collect(),
+            pending: Default::default(),
+            replay: Default::default(),
+            running_input: Default::default(),
+            save_on_exit: true,
+            autosave_interval: Default::default(),
+            last_autosave: Default::default(),
+            next_autosave: Default::default(),
             retired: Default::default(),
+            quitting: Default::default(),
         }
     }
 }
@@ -116,20 +172,61 @@ impl Game {
         }
     }
 
-    pub async fn tick(&mut self) {
-        // Clear the dead from selection.
-        for i in (0..self.selection.len()).rev() {
-            if !self.selection[i].is_alive(self) {
-                self.selection.swap_remove(i);
+    /// Start a new game from the given seed.
+    pub fn start(&mut self, seed: Silo) -> Result<()> {
+        self.r = Runtime::new(seed.clone())?;
+        self.replay = Replay::new(seed);
+        self.last_autosave = self.r.now();
+        Ok(())
+    }
+
+    /// Start a new game from the replay's seed and feed it the recorded
+    /// inputs.
+    pub fn play_back(&mut self, replay: Replay) -> Result<()> {
+        self.start(replay.seed)?;
+
+        for (t, input) in replay.inputs {
+            while self.r.now() < t {
+                let prev = self.r.now();
+                self.update_logic();
+                if self.r.now() == prev {
+                    bail!("Replay went out of sync at {t:?}");
+                }
+            }
+            match input {
+                // Only confirmed actions were recorded.
+                Input::Key(action) => self.run_action(action),
+                Input::Command(cmd) => self.act(cmd),
+                Input::Select(sel) => self.set_selection(sel),
+                Input::BecomePlayer(e) => self.become_player(e),
+                Input::Rest(loc) => self.rest(loc),
             }
         }
 
-        // If player doesn't exist, player is not acting this frame or player
-        // is executing a goal, run in real time.
-        if self.r.player().map_or(true, |p| {
-            !p.acts_this_frame(self) || p.goal(self).is_some()
-        }) {
-            self.r.tick();
+        Ok(())
+    }
+
+    /// Run a player input and log it in the replay.
+    ///
+    /// Inputs made while running the input are part of it and don't get
+    /// logged separately.
+    fn record(&mut self, input: Input, f: impl FnOnce(&mut Self)) {
+        if self.running_input {
+            f(self);
+            return;
+        }
+
+        self.replay.record(self.r.now(), input);
+        self.running_input = true;
+        f(self);
+        self.running_input = false;
+    }
+
+    pub async fn tick(&mut self) {
+        self.update_logic();
+
+        if self.autosave_due() {
+            self.autosave(&settings().id);
         }
 
         // Update camera in case engine tick moved player.
@@ -146,18 +243,7 @@ impl Game {
         while let Ok(msg) = self.recv.try_recv() {
             use Msg::*;
             match msg {
-                Message(text) => {
-                    if let Some(repeat) = self
-                        .msg
-                        .last()
-                        .and_then(|prev| prev.deduplicate_message(&text))
-                    {
-                        self.msg.pop();
-                        self.msg.push(repeat);
-                    } else {
-                        self.msg.push(text);
-                    }
-                }
+                Message(kind, text) => push_message(&mut self.msg, kind, text),
                 Fire(e, dir) => {
                     self.add_anim(Box::new(
                         anim::Particle::new(e, 10).offset(dir).c(dir.to_char()),
@@ -168,6 +254,9 @@ impl Game {
                         anim::Particle::new(e, 10).c('*').col(X::RED),
                     ));
                 }
+                Damage(e, amount) => {
+                    self.add_anim(Box::new(anim::DamageNumber::new(e, amount)));
+                }
                 Miss(e) => {
                     self.add_anim(Box::new(anim::Particle::new(e, 3).c('·')));
                 }
@@ -243,7 +332,13 @@ impl Game {
                         }
                     }
 
-                    self.r.rest_respawn(loc);
+                    self.rest(loc);
+                }
+                Trade(merchant) => {
+                    crate::trade(merchant).await;
+                }
+                Talk(speaker) => {
+                    crate::talk(speaker).await;
                 }
             }
         }
@@ -349,8 +444,32 @@ impl Game {
         (!was_resized).then_some(())
     }
 
+    /// Advance the game logic by one frame if the player isn't waiting for
+    /// input.
+    pub fn update_logic(&mut self) {
+        // Clear the dead from selection.
+        for i in (0..self.selection.len()).rev() {
+            if !self.selection[i].is_alive(self) {
+                self.selection.swap_remove(i);
+            }
+        }
+
+        // If player doesn't exist, player is not acting this frame or player
+        // is executing a goal, run in real time.
+        if self.r.player().map_or(true, |p| {
+            !p.acts_this_frame(self) || p.goal(self).is_some()
+        }) {
+            self.r.tick();
+        }
+    }
+
     pub fn act(&mut self, cmd: impl Into<Command>) {
-        match (cmd.into(), self.current_active()) {
+        let cmd = cmd.into();
+        self.record(Input::Command(cmd.clone()), |g| g.run_command(cmd));
+    }
+
+    fn run_command(&mut self, cmd: Command) {
+        match (cmd, self.current_active()) {
             (Command::Direct(act), Some(p)) => {
                 let r = &mut self.r;
 
@@ -378,11 +497,22 @@ impl Game {
                 } else {
                     // Commanding a NPC past its actions makes it become the
                     // new main player.
-                    p.become_player(r);
-                    p.execute_direct(r, act);
+                    if p.become_player(r) {
+                        p.execute_direct(r, act);
+                    }
                 }
             }
-            (Command::Indirect(Goal::GoTo { destination, .. }), Some(_)) => {
+            (
+                Command::Indirect(Goal::GoTo {
+                    destination,
+                    is_attack_move,
+                    ..
+                }),
+                Some(_),
+            ) => {
+                let is_attack_move =
+                    is_attack_move || std::mem::take(&mut self.attack_move);
+
                 if self.player_is_selected() {
                     // For player group, player gets the goal, others follow
                     // player.
@@ -398,6 +528,16 @@ impl Game {
                         }
                     }
 
+                    if is_attack_move {
+                        // Player fights whatever shows up on the way, no
+                        // need to guess intent.
+                        p.order_attack_move(
+                            &mut self.r,
+                            destination.center().into(),
+                        );
+                        return;
+                    }
+
                     if p.is_threatened(&self.r) {
                         // If player is threatened, see if it looks like
                         // you're trying to fight or flee.
@@ -542,21 +682,92 @@ impl Game {
         }
     }
 
+    /// Return the danger in performing the action that the player wants to
+    /// be asked about first.
+    pub fn danger(&self, action: InputAction) -> Option<Danger> {
+        let danger = match action {
+            InputAction::Retire => Some(Danger::Retire),
+            InputAction::QuitGame
+                if !self.save_on_exit && self.r.now() > self.last_autosave =>
+            {
+                Some(Danger::Quit)
+            }
+            InputAction::TravelDown => {
+                let p = self.current_active()?;
+                let loc = p.loc(self)?;
+                loc.ns_8()
+                    .any(|loc| {
+                        loc.mob_at(self).is_some_and(|e| e.is_enemy(self, &p))
+                    })
+                    .then_some(Danger::Descend)
+            }
+            a => {
+                let Ok(Action::Bump(dir)) = Action::try_from(a) else {
+                    return None;
+                };
+                let p = self.current_active()?;
+                let dest = p.walk_step(self, p.loc(self)?, dir)?;
+                (dest.mob_at(self).is_none()
+                    && matches!(
+                        dest.below().voxel(&self.r),
+                        Some(Block::Trap(_))
+                    ))
+                .then_some(Danger::Trap)
+            }
+        }?;
+
+        self.confirm.contains(&danger).then_some(danger)
+    }
+
+    /// Question to ask the player about the action waiting for confirmation.
+    pub fn pending_question(&self) -> Option<&'static str> {
+        self.pending.map(|(_, danger)| danger.question())
+    }
+
+    /// Perform or drop the action waiting for confirmation.
+    pub fn confirm_pending(&mut self, confirmed: bool) {
+        if let Some((action, _)) = self.pending.take() {
+            if confirmed {
+                self.run_action(action);
+            }
+        }
+    }
+
+    /// Perform an input action, or hold it until the player confirms it if
+    /// it's dangerous.
     pub fn process_action(&mut self, action: InputAction) {
+        if let Some(danger) = self.danger(action) {
+            self.pending = Some((action, danger));
+        } else {
+            self.run_action(action);
+        }
+    }
+
+    fn run_action(&mut self, action: InputAction) {
+        // Quitting isn't part of the game session.
+        if action == InputAction::QuitGame {
+            self.quitting = true;
+        } else {
+            self.record(Input::Key(action), |g| g.apply_action(action));
+        }
+    }
+
+    fn apply_action(&mut self, action: InputAction) {
         use InputAction::*;
+
         match action {
             North => self.act(Action::Bump(s4::DIR[0])),
             East => self.act(Action::Bump(s4::DIR[1])),
             South => self.act(Action::Bump(s4::DIR[2])),
             West => self.act(Action::Bump(s4::DIR[3])),
-            FireNorth => {}
-            FireEast => {}
-            FireSouth => {}
-            FireWest => {}
-            SouthEast => {}
-            SouthWest => {}
-            NorthWest => {}
-            NorthEast => {}
+            FireNorth => self.act(Action::Shoot(s4::DIR[0])),
+            FireEast => self.act(Action::Shoot(s4::DIR[1])),
+            FireSouth => self.act(Action::Shoot(s4::DIR[2])),
+            FireWest => self.act(Action::Shoot(s4::DIR[3])),
+            NorthEast => self.act(Action::Bump(s8::DIR[1])),
+            SouthEast => self.act(Action::Bump(s8::DIR[3])),
+            SouthWest => self.act(Action::Bump(s8::DIR[5])),
+            NorthWest => self.act(Action::Bump(s8::DIR[7])),
             TravelNorth => self.travel(NORTH),
             TravelEast => self.travel(EAST),
             TravelSouth => self.travel(SOUTH),
@@ -566,10 +777,12 @@ impl Game {
             Cycle => self.select_next_commandable(false),
             BecomePlayer => {
                 if let Some(p) = self.current_active() {
-                    p.become_player(&mut self.r);
+                    self.become_player(p);
                 }
             }
             Pass => self.act(Action::Pass),
+            Search => self.act(Action::Search),
+            Sneak => self.act(Action::Sneak),
             /*
             Inventory => {
                 if let Some(p) = self.current_active() {
@@ -587,7 +800,7 @@ impl Game {
             Throw => {}
             Use => {}
             QuitGame => {}
-            Retire => {}
+            Retire => self.retire(),
             Cancel => {
                 if let Some(p) = self.current_active() {
                     if p.is_player(self) {
@@ -597,6 +810,8 @@ impl Game {
                     }
                 }
                 self.selection = Default::default();
+                self.attack_move = false;
+                self.recenter_camera();
             }
             Roam => {
                 if let Some(p) = self.current_active() {
@@ -606,24 +821,74 @@ impl Game {
                     }
                 }
             }
-            ScrollNorth => {}
-            ScrollEast => {}
-            ScrollSouth => {}
-            ScrollWest => {}
+            ScrollNorth => self.scroll_camera(s4::DIR[0]),
+            ScrollEast => self.scroll_camera(s4::DIR[1]),
+            ScrollSouth => self.scroll_camera(s4::DIR[2]),
+            ScrollWest => self.scroll_camera(s4::DIR[3]),
+            ToggleHealthBars => self.show_health_bars = !self.show_health_bars,
+            AttackMove => self.attack_move = true,
+            ToggleTheme => self.set_theme(self.theme.toggled()),
+            Settings => {}
+            DumpMap => {}
         }
     }
 
+    /// Switch to a color theme.
+    ///
+    /// The frontend needs to be told about the new palette separately.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.palette = theme.palette();
+    }
+
+    /// Move the camera away from the viewpoint.
+    ///
+    /// The camera can't leave the expanded sector of the viewpoint.
+    pub fn scroll_camera(&mut self, dir: IVec2) {
+        const SCROLL_STEP: i32 = 4;
+
+        let bounds = self.viewpoint.sector().wide();
+        self.camera =
+            bounds.clamp_inclusive(self.camera + dir.extend(0) * SCROLL_STEP);
+        self.camera_frames_left = 0;
+
+        // Same mouse position now points at a different map cell, the old
+        // path is no longer valid.
+        self.planned_path = Default::default();
+    }
+
+    /// Snap a scrolled camera back to the viewpoint.
+    pub fn recenter_camera(&mut self) {
+        self.camera = self.viewpoint;
+        self.camera_frames_left = 0;
+    }
+
     pub fn clear_selection(&mut self) {
         self.selection.clear();
     }
 
     pub fn set_selection(&mut self, sel: impl IntoIterator<Item = Entity>) {
-        self.selection = sel.into_iter().collect();
+        let sel: Vec<Entity> = sel.into_iter().collect();
+        self.record(Input::Select(sel.clone()), |g| {
+            g.selection = sel;
 
-        // Just the player amounts to no selection.
-        if self.selection.len() == 1 && self.selection[0].is_player(self) {
-            self.selection.clear();
-        }
+            // Just the player amounts to no selection.
+            if g.selection.len() == 1 && g.selection[0].is_player(g) {
+                g.selection.clear();
+            }
+        });
+    }
+
+    /// Make a mob the new player character if it can become one.
+    pub fn become_player(&mut self, e: Entity) {
+        self.record(Input::BecomePlayer(e), |g| {
+            e.become_player(&mut g.r);
+        });
+    }
+
+    /// Rest at the altar at `loc` and have the world respawn.
+    pub fn rest(&mut self, loc: Location) {
+        self.record(Input::Rest(loc), |g| g.r.rest_respawn(loc));
     }
 
     pub fn selected(&self) -> impl Iterator<Item = Entity> + '_ {
@@ -653,60 +918,28 @@ impl Game {
     }
 
     pub fn save(&mut self, game_name: &str) {
-        let saved =
-            idm::to_string(&self.r).expect("runtime serialization failed");
-
-        let saved = snap::raw::Encoder::new()
-            .compress_vec(saved.as_bytes())
-            .expect("Save compression failed");
-        navni::Directory::data(game_name)
-            .expect("data dir not found")
-            .write_bytes("saved.idm.sz", &saved)
-            .expect("writing save failed");
+        self.save_slot(game_name, 0);
     }
 
     pub fn delete_save(&self, game_name: &str) {
-        if navni::Directory::data(game_name)
-            .expect("data dir not found")
-            .exists("saved.idm.sz")
-        {
-            navni::Directory::data(game_name)
-                .expect("data dir not found")
-                .delete("saved.idm.sz")
-                .expect("deleting save failed");
-        }
+        self.delete_slot(game_name, 0);
+        self.delete_autosaves(game_name);
     }
 
     pub fn savefile_exists(&self, game_name: &str) -> bool {
-        navni::Directory::data(game_name)
-            .expect("data dir not found")
-            .exists("saved.idm.sz")
+        self.slot_exists(game_name, 0)
     }
 
     /// Return Ok(Some(save)) if save file is found and parsed successfully.
     /// Return Ok(None) if there is no save file. Return an error if save file
     /// is present but could not be parsed.
     pub fn load(&mut self, game_name: &str) -> Result<Option<Runtime>> {
-        if let Ok(save) = navni::Directory::data(game_name)
-            .expect("data dir not found")
-            .read_bytes("saved.idm.sz")
-        {
-            // Return an error if deserialization fails.
-            let save = snap::raw::Decoder::new()
-                .decompress_vec(&save)
-                .map_err(|_| anyhow!("Failed to decompress save"))?;
-            let save = std::str::from_utf8(&save)?;
-            let mut ret: Runtime = idm::from_str(save)?;
-
-            ret.bump_cache();
-            Ok(Some(ret))
-        } else {
-            Ok(None)
-        }
+        self.load_slot(game_name, 0)
     }
 
     pub fn replace_runtime(&mut self, r: Runtime) {
         self.r = r;
+        self.last_autosave = self.r.now();
 
         // If player was in the middle of a long action when game was saved,
         // abort that. It's confusing to load back into game where the player
@@ -720,10 +953,26 @@ impl Game {
         if let Some(loc) = self.current_active().and_then(|p| p.loc(self)) {
             if loc != self.viewpoint {
                 self.viewpoint = loc;
-                self.camera = self.viewpoint;
                 self.planned_path.clear();
+
+                // Always snap when moving to a different sector, the view
+                // doesn't scroll across sector boundaries.
+                if self.smooth_camera
+                    && self.camera.sector() == self.viewpoint.sector()
+                {
+                    self.camera_frames_left = CAMERA_EASE_FRAMES;
+                } else {
+                    self.camera = self.viewpoint;
+                    self.camera_frames_left = 0;
+                }
             }
         }
+
+        if self.camera_frames_left > 0 {
+            self.camera =
+                ease_step(self.camera, self.viewpoint, self.camera_frames_left);
+            self.camera_frames_left -= 1;
+        }
     }
 
     pub fn retire(&mut self) {
@@ -734,6 +983,11 @@ impl Game {
         self.retired
     }
 
+    /// The player has asked to leave the game.
+    pub fn is_quitting(&self) -> bool {
+        self.quitting
+    }
+
     /// Return a monochrome text screenshot.
     pub fn screenshot(&self) -> String {
         let mut ret = String::new();
@@ -753,6 +1007,95 @@ impl Game {
 
         ret
     }
+
+    /// Return a text map of the explored part of the current sector.
+    ///
+    /// Unlike a screenshot, this covers the whole sector whether it fits on
+    /// the screen or not, with mobs and items drawn over the terrain.
+    pub fn dump_map(&self) -> String {
+        let r = &self.r;
+        let sector = self.viewpoint.sector();
+        let z = self.viewpoint.z;
+
+        let mut ret = String::new();
+        for y in sector.min()[1]..sector.max()[1] {
+            for x in sector.min()[0]..sector.max()[0] {
+                let loc = ivec3(x, y, z);
+                let floor = loc.snap_above_floor(r);
+
+                let c = if !loc.is_explored(r) {
+                    ' '
+                } else if let Some(e) = floor.mob_at(r) {
+                    if r.player() == Some(e) {
+                        '@'
+                    } else {
+                        e.icon(r)
+                    }
+                } else if let Some(e) = floor.item_at(r) {
+                    e.icon(r)
+                } else {
+                    world::terrain_cell(r, loc)
+                };
+                ret.push(c);
+            }
+            ret.push('\n');
+        }
+
+        ret
+    }
+
+    /// Write a color PNG screenshot of the display to the given path.
+    pub fn screenshot_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.s.rasterize(&self.palette).to_png())?;
+        Ok(())
+    }
+}
+
+/// Add a message to the message queue.
+///
+/// A message that repeats the last queued message is merged into it as a
+/// repeat count, "You hit the rat. (x3)".
+fn push_message(
+    queue: &mut Vec<(MsgKind, String)>,
+    kind: MsgKind,
+    text: String,
+) {
+    // Only messages of the same kind get merged.
+    if let Some(repeat) = queue
+        .last()
+        .and_then(|(k, prev)| (*k == kind).then_some(prev))
+        .and_then(|prev| prev.deduplicate_message(&text))
+    {
+        queue.pop();
+        queue.push((kind, repeat));
+    } else {
+        queue.push((kind, text));
+    }
+}
+
+/// Color for displaying a message of the given kind.
+///
+/// Neutral messages have no color of their own and use the window's
+/// foreground color.
+pub fn msg_color(kind: MsgKind) -> Option<X256Color> {
+    match kind {
+        MsgKind::Info => None,
+        MsgKind::Combat => Some(X::RED),
+        MsgKind::Discovery => Some(X::AQUA),
+        MsgKind::Warning => Some(X::YELLOW),
+        MsgKind::System => Some(X::GRAY),
+    }
+}
+
+/// Move `pos` towards `target` so that it arrives in `frames_left` steps.
+fn ease_step(pos: IVec3, target: IVec3, frames_left: i32) -> IVec3 {
+    if frames_left <= 1 {
+        return target;
+    }
+
+    // Round the step away from zero so the camera starts moving right away.
+    let delta = target - pos;
+    pos + (delta + delta.signum() * (frames_left - 1)) / frames_left
 }
 
 fn draw_anims(
@@ -801,9 +1144,13 @@ impl PlannedPath {
         self.mouse_pos = mouse_pos;
 
         self.posns.clear();
-        if let Some(path) =
-            r.find_path(FogPathing::Explore, orig, &Cube::unit(dest))
-        {
+        // Preview the route around difficult terrain.
+        if let Some(path) = r.find_path_with_cost(
+            FogPathing::Explore,
+            orig,
+            &Cube::unit(dest),
+            |loc| loc.move_cost(r),
+        ) {
             self.posns = path;
         }
     }
@@ -812,3 +1159,273 @@ impl PlannedPath {
         &self.posns
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use engine::MsgKind;
+    use glam::ivec3;
+    use navni::X256Color as X;
+    use util::{s4, Silo};
+    use world::{Coordinates, Zone};
+
+    use super::{ease_step, msg_color, push_message, CAMERA_EASE_FRAMES};
+    use crate::{Danger, Game, InputAction};
+
+    #[test]
+    fn camera_easing_converges() {
+        let target = ivec3(37, -5, 2);
+        for start in [ivec3(0, 0, 2), ivec3(40, 10, 2), ivec3(36, -5, 2)] {
+            let mut pos = start;
+            for frames_left in (1..=CAMERA_EASE_FRAMES).rev() {
+                let next = ease_step(pos, target, frames_left);
+                // Never overshoot.
+                assert!((target - next)
+                    .abs()
+                    .cmple((target - pos).abs())
+                    .all());
+                pos = next;
+            }
+            assert_eq!(pos, target);
+        }
+    }
+
+    #[test]
+    fn scroll_camera() {
+        world::register_data_from("../data").unwrap();
+
+        let mut game = Game::default();
+        game.start(Silo::new("rand0m")).unwrap();
+        game.update_camera();
+        game.recenter_camera();
+        let start = game.viewpoint;
+
+        game.process_action(InputAction::ScrollEast);
+        assert!(game.camera.x > start.x);
+        assert_eq!(game.viewpoint, start);
+
+        // Scrolling can't leave the expanded sector.
+        let bounds = start.sector().wide();
+        for _ in 0..100 {
+            game.process_action(InputAction::ScrollSouth);
+        }
+        assert!(bounds.contains(game.camera));
+        assert_eq!(game.viewpoint, start);
+
+        game.process_action(InputAction::Cancel);
+        assert_eq!(game.camera, start);
+    }
+
+    #[test]
+    fn map_dump() {
+        use engine::{prelude::*, WizardCommand};
+        use world::{Block, Environs};
+
+        world::register_data_from("../data").unwrap();
+
+        let mut game = Game::default();
+        game.start(Silo::new("rand0m")).unwrap();
+        game.update_camera();
+
+        let player = game.r.player().unwrap();
+        let loc = player.loc(&game.r).unwrap();
+
+        // Put an empty floor cell and a wall next to the player.
+        let floor = loc + ivec3(1, 0, 0);
+        let wall = loc + ivec3(2, 0, 0);
+        for p in [floor, wall] {
+            for e in [p.mob_at(&game.r), p.item_at(&game.r)]
+                .into_iter()
+                .flatten()
+            {
+                e.destroy(&mut game.r);
+            }
+            game.r.set_voxel(p.below(), Some(Block::Stone));
+        }
+        game.r.set_voxel(floor, None);
+        game.r.set_voxel(wall, Some(Block::Stone));
+        game.r
+            .wizard(true, WizardCommand::RevealSector(loc))
+            .unwrap();
+
+        let dump = game.dump_map();
+        let origin = loc.sector().min();
+        let cell = |p: Location| {
+            dump.lines()
+                .nth((p.y - origin[1]) as usize)
+                .and_then(|line| line.chars().nth((p.x - origin[0]) as usize))
+        };
+
+        assert_eq!(cell(loc), Some('@'));
+        assert_eq!(cell(floor), Some('.'));
+        assert_eq!(cell(wall), Some('#'));
+        assert_eq!(
+            dump.lines().count(),
+            (loc.sector().max()[1] - origin[1]) as usize
+        );
+    }
+
+    #[test]
+    fn attack_move_order() {
+        use engine::prelude::*;
+        use world::Cube;
+
+        world::register_data_from("../data").unwrap();
+
+        let mut game = Game::default();
+        game.start(Silo::new("rand0m")).unwrap();
+        let player = game.r.player().unwrap();
+        let origin = player.loc(&game.r).unwrap();
+
+        let go_to = Goal::GoTo {
+            origin,
+            destination: Cube::unit(origin + ivec3(3, 0, 0)),
+            is_attack_move: false,
+            is_exploring: false,
+        };
+        let is_attack_move = |game: &Game| {
+            matches!(
+                player.goal(&game.r),
+                Goal::GoTo {
+                    is_attack_move: true,
+                    ..
+                }
+            )
+        };
+
+        // Plain move.
+        game.act(go_to);
+        assert!(matches!(player.goal(&game.r), Goal::GoTo { .. }));
+        assert!(!is_attack_move(&game));
+
+        // Attack-move applies to the next order only.
+        game.process_action(InputAction::AttackMove);
+        game.act(go_to);
+        assert!(is_attack_move(&game));
+        assert!(!game.attack_move);
+
+        game.act(go_to);
+        assert!(!is_attack_move(&game));
+    }
+
+    #[test]
+    fn toggle_theme() {
+        use crate::{Theme, COLORBLIND_PALETTE, DARK_PALETTE, LIGHT_PALETTE};
+
+        assert_eq!(DARK_PALETTE.len(), 16);
+        assert!(DARK_PALETTE != LIGHT_PALETTE);
+
+        let mut game = Game::default();
+        assert_eq!(game.theme, Theme::Light);
+        assert!(game.palette == LIGHT_PALETTE);
+
+        game.process_action(InputAction::ToggleTheme);
+        assert_eq!(game.theme, Theme::Dark);
+        assert!(game.palette == DARK_PALETTE);
+
+        game.process_action(InputAction::ToggleTheme);
+        assert_eq!(game.theme, Theme::Colorblind);
+        assert!(game.palette == COLORBLIND_PALETTE);
+        assert!(COLORBLIND_PALETTE != DARK_PALETTE);
+
+        game.process_action(InputAction::ToggleTheme);
+        assert_eq!(game.theme, Theme::Light);
+        assert!(game.palette == LIGHT_PALETTE);
+    }
+
+    #[test]
+    fn retire_needs_confirmation() {
+        world::register_data_from("../data").unwrap();
+
+        let mut game = Game::default();
+        game.start(Silo::new("rand0m")).unwrap();
+
+        game.process_action(InputAction::Retire);
+        assert!(!game.is_game_over());
+        assert!(game.pending_question().is_some());
+
+        // Saying no drops the action.
+        game.confirm_pending(false);
+        assert!(!game.is_game_over());
+        assert!(game.pending_question().is_none());
+
+        game.process_action(InputAction::Retire);
+        assert!(!game.is_game_over());
+        game.confirm_pending(true);
+        assert!(game.is_game_over());
+    }
+
+    #[test]
+    fn known_trap_needs_confirmation() {
+        use engine::prelude::*;
+        use world::{Block, Environs, TrapKind};
+
+        world::register_data_from("../data").unwrap();
+
+        let mut game = Game::default();
+        game.start(Silo::new("rand0m")).unwrap();
+        let player = game.r.player().unwrap();
+        let loc = player.loc(&game.r).unwrap();
+
+        let (action, dest) = [
+            InputAction::North,
+            InputAction::East,
+            InputAction::South,
+            InputAction::West,
+        ]
+        .into_iter()
+        .zip(s4::DIR)
+        .find_map(|(a, d)| {
+            let dest = loc.walk_step(&game.r, d)?;
+            dest.mob_at(&game.r).is_none().then_some((a, dest))
+        })
+        .expect("Player can't move anywhere");
+
+        // Hidden traps are a surprise.
+        game.r
+            .set_voxel(dest.below(), Some(Block::HiddenTrap(TrapKind::Damage)));
+        assert_eq!(game.danger(action), None);
+
+        game.r
+            .set_voxel(dest.below(), Some(Block::Trap(TrapKind::Damage)));
+        assert_eq!(game.danger(action), Some(Danger::Trap));
+
+        game.process_action(action);
+        assert_eq!(player.loc(&game.r), Some(loc));
+        game.confirm_pending(false);
+        assert_eq!(player.loc(&game.r), Some(loc));
+
+        // Confirmations can be turned off.
+        game.confirm.remove(&Danger::Trap);
+        assert_eq!(game.danger(action), None);
+    }
+
+    #[test]
+    fn warning_message_color() {
+        assert!(msg_color(MsgKind::Warning) == Some(X::YELLOW));
+        // Plain messages use whatever color the message area has.
+        assert!(msg_color(MsgKind::Info).is_none());
+    }
+
+    #[test]
+    fn repeated_messages_merge() {
+        let mut queue = Vec::new();
+        for _ in 0..3 {
+            push_message(
+                &mut queue,
+                MsgKind::Combat,
+                "You hit the rat.".into(),
+            );
+        }
+        assert_eq!(
+            queue,
+            vec![(MsgKind::Combat, "You hit the rat. (x3)".to_string())]
+        );
+
+        // Different messages keep their order.
+        push_message(&mut queue, MsgKind::Combat, "The rat dies.".into());
+        push_message(&mut queue, MsgKind::Combat, "You hit the rat.".into());
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue[1].1, "The rat dies.");
+        assert_eq!(queue[2].1, "You hit the rat.");
+    }
+}
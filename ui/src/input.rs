@@ -38,6 +38,8 @@ pub enum InputAction {
     TravelDown,
     Cycle,
     Pass,
+    Search,
+    Sneak,
     Inventory,
     Powers,
     Drop,
@@ -52,6 +54,15 @@ pub enum InputAction {
     ScrollSouth,
     ScrollWest,
     ScrollEast,
+    ToggleHealthBars,
+    /// Make the next move order an attack-move.
+    AttackMove,
+    /// Cycle through the color themes.
+    ToggleTheme,
+    /// Open the interface settings menu.
+    Settings,
+    /// Write a text map of the current sector to a file, wizard mode only.
+    DumpMap,
 }
 
 #[derive(Clone, Deref, Eq, PartialEq, Serialize, Deserialize)]
@@ -90,6 +101,8 @@ impl Default for InputMap {
             (">", TravelDown),
             ("Tab", Cycle),
             ("Sp", Pass),
+            ("f", Search),
+            ("v", Sneak),
             ("h", Inventory),
             ("z", Powers),
             ("x", Drop),
@@ -104,6 +117,11 @@ impl Default for InputMap {
             ("S-Left", ScrollWest),
             ("S-Down", ScrollSouth),
             ("S-Right", ScrollEast),
+            ("H", ToggleHealthBars),
+            ("g", AttackMove),
+            ("T", ToggleTheme),
+            ("O", Settings),
+            ("M", DumpMap),
         ] {
             ret.insert(
                 k.parse::<KeyTyped>()
@@ -156,12 +174,46 @@ impl TryFrom<InputAction> for Action {
             South => Ok(Action::Bump(ivec2(0, 1))),
             West => Ok(Action::Bump(ivec2(-1, 0))),
             East => Ok(Action::Bump(ivec2(1, 0))),
+            NorthEast => Ok(Action::Bump(ivec2(1, -1))),
+            SouthEast => Ok(Action::Bump(ivec2(1, 1))),
+            SouthWest => Ok(Action::Bump(ivec2(-1, 1))),
+            NorthWest => Ok(Action::Bump(ivec2(-1, -1))),
             FireNorth => Ok(Action::Shoot(ivec2(0, -1))),
             FireSouth => Ok(Action::Shoot(ivec2(0, 1))),
             FireWest => Ok(Action::Shoot(ivec2(-1, 0))),
             FireEast => Ok(Action::Shoot(ivec2(1, 0))),
             Pass => Ok(Action::Pass),
+            Search => Ok(Action::Search),
+            Sneak => Ok(Action::Sneak),
             _ => Err(()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use navni::Key;
+    use util::Layout;
+
+    use super::{InputAction::*, InputMap};
+
+    #[test]
+    fn layout_movement_keys() {
+        // Movement keys stay in the same physical WASD positions.
+        for (layout, keys) in [
+            (Layout::Qwerty, "wasd"),
+            (Layout::Colemak, "wars"),
+            (Layout::Dvorak, ",aoe"),
+        ] {
+            let map = InputMap::for_layout(layout);
+            let bound: String = [North, West, South, East]
+                .into_iter()
+                .map(|a| match map.key_for(a).unwrap().key() {
+                    Key::Char(c) => c,
+                    k => panic!("{layout:?} bound {a:?} to {k:?}"),
+                })
+                .collect();
+            assert_eq!(bound, keys, "{layout:?}");
+        }
+    }
+}
@@ -1,5 +1,7 @@
 //! Game user interface machinery
 
+use serde::{Deserialize, Serialize};
+
 pub mod prelude {
     use navni::prelude::*;
 
@@ -27,23 +29,40 @@ mod cursor;
 pub use cursor::Cursor;
 
 mod dialog;
-pub use dialog::ask;
+pub use dialog::{ask, talk};
 
 mod game;
-pub use game::{game, init_game, Game};
+pub use game::{game, init_game, msg_color, Game};
 
 mod input;
 pub use input::{input_press, InputAction, InputMap};
 
+mod replay;
+pub use replay::{Input, Replay};
+
+mod save;
+pub use save::{Prefs, SaveInfo, NUM_AUTOSAVES, NUM_SAVE_SLOTS};
+
+mod settings_menu;
+pub use settings_menu::{settings_menu, SettingsAction};
+
 mod tile_display;
-pub use tile_display::{render_fog, DisplayTile, SectorView};
+pub use tile_display::{
+    health_bar, render_fog, AllyState, DisplayTile, SectorView,
+};
+
+mod trade_menu;
+pub use trade_menu::{trade, TradeAction, TradeMenu};
 
 mod widget;
-pub use widget::{Centered, ConfirmationDialog, Widget};
+pub use widget::{Centered, ConfirmationDialog, DialogueBox, Widget};
 
 mod window;
 pub use window::Window;
 
+mod wizard_menu;
+pub use wizard_menu::{wizard_menu, WizardAction};
+
 pub fn cell(
     c: char,
     fore: impl Into<navni::X256Color>,
@@ -73,3 +92,132 @@ pub const LIGHT_PALETTE: [navni::Rgba; 16] = {
         Rgba::new(0x00, 0x00, 0x00, 0xff), // black
     ]
 };
+
+pub const DARK_PALETTE: [navni::Rgba; 16] = {
+    use navni::Rgba;
+    [
+        Rgba::new(0x00, 0x00, 0x00, 0xff), // black
+        Rgba::new(0xaa, 0x00, 0x00, 0xff), // maroon
+        Rgba::new(0x00, 0xaa, 0x00, 0xff), // green
+        Rgba::new(0xaa, 0x55, 0x00, 0xff), // brown
+        Rgba::new(0x00, 0x00, 0xaa, 0xff), // navy
+        Rgba::new(0xaa, 0x00, 0xaa, 0xff), // purple
+        Rgba::new(0x00, 0xaa, 0xaa, 0xff), // teal
+        Rgba::new(0xaa, 0xaa, 0xaa, 0xff), // silver
+        Rgba::new(0x55, 0x55, 0x55, 0xff), // gray
+        Rgba::new(0xff, 0x55, 0x55, 0xff), // red
+        Rgba::new(0x55, 0xff, 0x55, 0xff), // lime
+        Rgba::new(0xff, 0xff, 0x55, 0xff), // yellow
+        Rgba::new(0x55, 0x55, 0xff, 0xff), // blue
+        Rgba::new(0xff, 0x55, 0xff, 0xff), // fuchsia
+        Rgba::new(0x55, 0xff, 0xff, 0xff), // aqua
+        Rgba::new(0xff, 0xff, 0xff, 0xff), // white
+    ]
+};
+
+/// Dark palette with the hues picked to stay distinct for the common forms of
+/// color blindness.
+pub const COLORBLIND_PALETTE: [navni::Rgba; 16] = {
+    use navni::Rgba;
+    [
+        Rgba::new(0x00, 0x00, 0x00, 0xff), // black
+        Rgba::new(0x99, 0x3d, 0x00, 0xff), // maroon
+        Rgba::new(0x00, 0x9e, 0x73, 0xff), // green
+        Rgba::new(0x99, 0x6b, 0x00, 0xff), // brown
+        Rgba::new(0x00, 0x50, 0xa0, 0xff), // navy
+        Rgba::new(0x99, 0x4f, 0x7d, 0xff), // purple
+        Rgba::new(0x00, 0x72, 0xb2, 0xff), // teal
+        Rgba::new(0xaa, 0xaa, 0xaa, 0xff), // silver
+        Rgba::new(0x55, 0x55, 0x55, 0xff), // gray
+        Rgba::new(0xd5, 0x5e, 0x00, 0xff), // red
+        Rgba::new(0x66, 0xd9, 0xb0, 0xff), // lime
+        Rgba::new(0xf0, 0xe4, 0x42, 0xff), // yellow
+        Rgba::new(0x55, 0x99, 0xff, 0xff), // blue
+        Rgba::new(0xcc, 0x79, 0xa7, 0xff), // fuchsia
+        Rgba::new(0x56, 0xb4, 0xe9, 0xff), // aqua
+        Rgba::new(0xff, 0xff, 0xff, 0xff), // white
+    ]
+};
+
+/// Display color scheme.
+#[derive(
+    Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Dark text on a light background.
+    #[default]
+    Light,
+    /// Light text on a dark background.
+    Dark,
+    /// Dark background with colors that stay apart for color blind eyes.
+    Colorblind,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Light, Theme::Dark, Theme::Colorblind];
+
+    pub fn palette(self) -> [navni::Rgba; 16] {
+        match self {
+            Theme::Light => LIGHT_PALETTE,
+            Theme::Dark => DARK_PALETTE,
+            Theme::Colorblind => COLORBLIND_PALETTE,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::Colorblind => "colorblind",
+        }
+    }
+
+    /// The next theme in the cycle.
+    pub fn toggled(self) -> Self {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::Colorblind,
+            Theme::Colorblind => Theme::Light,
+        }
+    }
+}
+
+/// Player actions that ask for confirmation before they go through.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Danger {
+    /// Ending the current character's run.
+    Retire,
+    /// Quitting when the session won't be saved on exit.
+    Quit,
+    /// Taking the stairs down with enemies next to you.
+    Descend,
+    /// Stepping onto a trap you know is there.
+    Trap,
+}
+
+impl Danger {
+    pub const ALL: [Danger; 4] =
+        [Danger::Retire, Danger::Quit, Danger::Descend, Danger::Trap];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Danger::Retire => "retiring",
+            Danger::Quit => "quitting unsaved",
+            Danger::Descend => "descending next to enemies",
+            Danger::Trap => "stepping on known traps",
+        }
+    }
+
+    pub fn question(self) -> &'static str {
+        match self {
+            Danger::Retire => "Really retire your character?",
+            Danger::Quit => "Quit without saving?",
+            Danger::Descend => "Descend with enemies next to you?",
+            Danger::Trap => "Really step on the trap?",
+        }
+    }
+}
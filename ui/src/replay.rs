@@ -0,0 +1,90 @@
+use engine::prelude::*;
+use serde::{Deserialize, Serialize};
+use util::Silo;
+
+use crate::{Command, InputAction};
+
+/// Log of player inputs that recreates a game session when played back on a
+/// runtime started from the same seed.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: Silo,
+    /// Inputs paired with the runtime time they were given at.
+    pub inputs: Vec<(Instant, Input)>,
+}
+
+/// Player input that changes the game state.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Input {
+    /// Mapped key command.
+    Key(InputAction),
+    /// Action or order for the active mobs from a mouse click or a menu.
+    Command(Command),
+    /// Change of the mobs being commanded.
+    Select(Vec<Entity>),
+    /// Take control of a different mob.
+    BecomePlayer(Entity),
+    /// Rest at the altar at a location.
+    Rest(Location),
+}
+
+impl Replay {
+    pub fn new(seed: Silo) -> Self {
+        Replay {
+            seed,
+            inputs: Default::default(),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, input: Input) {
+        self.inputs.push((now, input));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::prelude::*;
+    use util::Silo;
+
+    use crate::{Game, InputAction};
+
+    #[test]
+    fn replay_matches_recording() {
+        use InputAction::*;
+
+        world::register_data_from("../data").unwrap();
+
+        let seed = Silo::new("rand0m");
+        let mut game = Game::default();
+        game.start(seed.clone()).unwrap();
+
+        for a in [North, Pass, East, East, Pass, South, West, Pass] {
+            game.process_action(a);
+            // Run the clock until the player gets to act again.
+            for _ in 0..100 {
+                game.update_logic();
+            }
+        }
+
+        // Commands from mouse and menus are recorded as well.
+        game.act(Action::Search);
+        for _ in 0..100 {
+            game.update_logic();
+        }
+
+        let end = game.r.now();
+        let recorded = idm::to_string(&game.r).unwrap();
+        let replay = game.replay.clone();
+        assert_eq!(replay.seed, seed);
+        assert_eq!(replay.inputs.len(), 9);
+
+        let mut game = Game::default();
+        game.play_back(replay).unwrap();
+        // Catch up with the ticks that happened after the last input.
+        while game.r.now() < end {
+            game.update_logic();
+        }
+
+        assert_eq!(idm::to_string(&game.r).unwrap(), recorded);
+    }
+}
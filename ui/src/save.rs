@@ -0,0 +1,657 @@
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, bail, Result};
+use engine::{prelude::*, PHASES_IN_TURN};
+use serde::{Deserialize, Serialize};
+use util::Silo;
+
+use crate::{Danger, Game, Replay, Theme};
+
+/// How many save slots are looked at when listing saves.
+pub const NUM_SAVE_SLOTS: usize = 10;
+
+/// How many autosaves are kept in rotation.
+pub const NUM_AUTOSAVES: usize = 3;
+
+/// Version of the save format written by this build.
+///
+/// Bump this and add a migration to `MIGRATIONS` when a change to `Runtime`
+/// makes old saves fail to deserialize.
+pub const SAVE_VERSION: usize = 1;
+
+/// Header line that tags the save text with its version.
+const VERSION_TAG: &str = "save-version";
+
+/// Save upgrade steps, `MIGRATIONS[n]` turns a version `n` save into a
+/// version `n + 1` one.
+const MIGRATIONS: [fn(&str) -> Result<String>; SAVE_VERSION] = [
+    // Version 0 saves have no version tag but are otherwise identical to
+    // version 1.
+    |save| Ok(save.to_owned()),
+];
+
+/// User interface preferences that are kept between games.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Prefs {
+    pub theme: Theme,
+    /// Actions that ask for confirmation.
+    pub confirm: BTreeSet<Danger>,
+}
+
+impl Default for Prefs {
+    fn default() -> Self {
+        Prefs {
+            theme: Default::default(),
+            confirm: Danger::ALL.into_iter().collect(),
+        }
+    }
+}
+
+/// Summary of a saved game for showing in a load menu.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveInfo {
+    pub slot: usize,
+    pub seed: Silo,
+    /// Turns played in the saved game.
+    pub turns: i64,
+    /// Wall clock time when the game was saved.
+    pub timestamp: f64,
+}
+
+/// Name of a file belonging to a save slot.
+///
+/// Slot 0 is the original single save file and gets no slot suffix.
+fn slot_file(stem: &str, ext: &str, slot: usize) -> String {
+    if slot == 0 {
+        format!("{stem}.{ext}")
+    } else {
+        format!("{stem}-{slot}.{ext}")
+    }
+}
+
+#[cfg(not(test))]
+fn data_dir(game_name: &str) -> navni::Directory {
+    navni::Directory::data(game_name).expect("data dir not found")
+}
+
+/// Tests keep their files in a scratch directory instead of the user's data
+/// directory.
+#[cfg(test)]
+fn data_dir(game_name: &str) -> tests::ScratchDir {
+    tests::ScratchDir(std::env::temp_dir().join(game_name))
+}
+
+fn read_info(game_name: &str, stem: &str, slot: usize) -> Option<SaveInfo> {
+    let info = data_dir(game_name)
+        .read_bytes(&slot_file(stem, "info.idm", slot))
+        .ok()?;
+    idm::from_str(std::str::from_utf8(&info).ok()?).ok()
+}
+
+/// Return Ok(Some(text)) with the decompressed save text if the save file
+/// is found. Return Ok(None) if there is no save file. Return an error if
+/// the save file is present but could not be decompressed.
+fn read_save_text(
+    game_name: &str,
+    stem: &str,
+    slot: usize,
+) -> Result<Option<String>> {
+    let Ok(save) =
+        data_dir(game_name).read_bytes(&slot_file(stem, "idm.sz", slot))
+    else {
+        return Ok(None);
+    };
+
+    let save = snap::raw::Decoder::new()
+        .decompress_vec(&save)
+        .map_err(|_| anyhow!("Failed to decompress save"))?;
+    Ok(Some(String::from_utf8(save)?))
+}
+
+/// Return Ok(Some(save)) if save file is found and parsed successfully.
+/// Return Ok(None) if there is no save file. Return an error if save file is
+/// present but could not be parsed.
+fn read_runtime(
+    game_name: &str,
+    stem: &str,
+    slot: usize,
+) -> Result<Option<Runtime>> {
+    let Some(save) = read_save_text(game_name, stem, slot)? else {
+        return Ok(None);
+    };
+
+    // Return an error if deserialization fails.
+    let mut ret = parse_save(&save)?;
+
+    ret.bump_cache();
+    Ok(Some(ret))
+}
+
+/// Name of the input log file that goes with a save file.
+fn replay_stem(stem: &str) -> &'static str {
+    match stem {
+        "autosave" => "autosave-replay",
+        _ => "replay",
+    }
+}
+
+/// Read the input log of a save, or start a new log from the runtime's seed
+/// if there is no log for the same world.
+fn read_replay(
+    game_name: &str,
+    stem: &str,
+    slot: usize,
+    r: &Runtime,
+) -> Replay {
+    data_dir(game_name)
+        .read_bytes(&slot_file(replay_stem(stem), "idm", slot))
+        .ok()
+        .and_then(|log| {
+            idm::from_str::<Replay>(std::str::from_utf8(&log).ok()?).ok()
+        })
+        .filter(|replay| &replay.seed == r.seed())
+        .unwrap_or_else(|| Replay::new(r.seed().clone()))
+}
+
+/// Serialize the runtime with a version tag.
+fn format_save(r: &Runtime) -> String {
+    let saved = idm::to_string(r).expect("runtime serialization failed");
+    format!("{VERSION_TAG} {SAVE_VERSION}\n{saved}")
+}
+
+/// Deserialize a runtime from save text of any supported version.
+fn parse_save(save: &str) -> Result<Runtime> {
+    Ok(idm::from_str(&migrate_save(save)?)?)
+}
+
+/// Turn save text of any supported version into a current version save
+/// body without the version tag.
+fn migrate_save(save: &str) -> Result<String> {
+    // Saves from before versioning have no tag and are version 0.
+    let (mut version, body) = match save.split_once('\n') {
+        Some((header, body)) if header.starts_with(VERSION_TAG) => {
+            (header[VERSION_TAG.len()..].trim().parse::<usize>()?, body)
+        }
+        _ => (0, save),
+    };
+
+    if version > SAVE_VERSION {
+        bail!("Unsupported future save version {version}");
+    }
+
+    let mut body = body.to_owned();
+    while version < SAVE_VERSION {
+        body = MIGRATIONS[version](&body)
+            .map_err(|e| anyhow!("Failed to migrate save v{version}: {e}"))?;
+        version += 1;
+    }
+
+    Ok(body)
+}
+
+/// Split IDM outline text into its top-level sections.
+fn outline_sections(text: &str) -> Vec<&str> {
+    let mut starts = Vec::new();
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        if !line.starts_with(char::is_whitespace) {
+            starts.push(pos);
+        }
+        pos += line.len();
+    }
+    starts.push(text.len());
+
+    starts.windows(2).map(|w| &text[w[0]..w[1]]).collect()
+}
+
+/// Name of the field an outline section holds.
+fn section_name(section: &str) -> &str {
+    section
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .trim_start_matches(':')
+}
+
+/// Dig out the world seed from save sections when the world field itself
+/// can't be read.
+fn salvage_seed(sections: &[&str]) -> Option<Silo> {
+    #[derive(Deserialize)]
+    struct Seed {
+        seed: Silo,
+    }
+
+    let world = sections.iter().find(|s| section_name(s) == "world")?;
+
+    // Dedent the fields of the world into an outline of their own.
+    let body = world.split_once('\n')?.1;
+    let indent = body.len() - body.trim_start().len();
+    let body: String = body
+        .split_inclusive('\n')
+        .map(|line| line.get(indent..).unwrap_or("\n"))
+        .collect();
+
+    let seed = outline_sections(&body)
+        .into_iter()
+        .find(|s| section_name(s) == "seed")?;
+    Some(idm::from_str::<Seed>(seed).ok()?.seed)
+}
+
+/// Recover what can be read from a damaged save.
+///
+/// The runtime fields in the save are read in order until one fails to
+/// parse, and that field and the ones after it are lost. If the game world
+/// or the player doesn't make it, only the world seed is kept and a new
+/// game is started in the same world. Return the runtime and the names of
+/// the lost fields, or an error if not even the seed could be recovered.
+fn salvage_save(save: &str) -> Result<(Runtime, Vec<String>)> {
+    let body = migrate_save(save)?;
+    let sections = outline_sections(&body);
+
+    let mut valid = 0;
+    while valid < sections.len()
+        && idm::from_str::<Runtime>(&sections[..=valid].concat()).is_ok()
+    {
+        valid += 1;
+    }
+
+    if let Ok(r) = idm::from_str::<Runtime>(&sections[..valid].concat()) {
+        if *r.seed() != Silo::default()
+            && r.player().and_then(|p| p.loc(&r)).is_some()
+        {
+            let lost = sections[valid..]
+                .iter()
+                .map(|s| section_name(s).to_owned())
+                .collect();
+            return Ok((r, lost));
+        }
+    }
+
+    let Some(seed) = salvage_seed(&sections) else {
+        bail!("Save is damaged beyond recovery");
+    };
+    let lost = sections
+        .iter()
+        .map(|s| section_name(s).to_owned())
+        .collect();
+    Ok((Runtime::new(seed)?, lost))
+}
+
+fn delete_files(game_name: &str, files: impl IntoIterator<Item = String>) {
+    for file in files {
+        if data_dir(game_name).exists(&file) {
+            data_dir(game_name)
+                .delete(&file)
+                .expect("deleting save failed");
+        }
+    }
+}
+
+impl Game {
+    /// Write the compressed runtime, its info file and the input log.
+    fn write_runtime(&self, game_name: &str, stem: &str, slot: usize) {
+        let saved = format_save(&self.r);
+
+        let saved = snap::raw::Encoder::new()
+            .compress_vec(saved.as_bytes())
+            .expect("Save compression failed");
+        data_dir(game_name)
+            .write_bytes(&slot_file(stem, "idm.sz", slot), &saved)
+            .expect("writing save failed");
+
+        let info = SaveInfo {
+            slot,
+            seed: self.r.seed().clone(),
+            turns: self.r.turn_count(),
+            timestamp: navni::now(),
+        };
+        let info = idm::to_string(&info).expect("info serialization failed");
+        data_dir(game_name)
+            .write_bytes(&slot_file(stem, "info.idm", slot), info.as_bytes())
+            .expect("writing save info failed");
+
+        // Write the input log next to the save in plain text so it can be
+        // attached to bug reports.
+        let replay =
+            idm::to_string(&self.replay).expect("replay serialization failed");
+        data_dir(game_name)
+            .write_bytes(
+                &slot_file(replay_stem(stem), "idm", slot),
+                replay.as_bytes(),
+            )
+            .expect("writing replay failed");
+    }
+
+    pub fn save_slot(&mut self, game_name: &str, slot: usize) {
+        self.write_runtime(game_name, "saved", slot);
+    }
+
+    pub fn delete_slot(&self, game_name: &str, slot: usize) {
+        delete_files(
+            game_name,
+            [
+                slot_file("saved", "idm.sz", slot),
+                slot_file("saved", "info.idm", slot),
+                slot_file("replay", "idm", slot),
+            ],
+        );
+    }
+
+    pub fn slot_exists(&self, game_name: &str, slot: usize) -> bool {
+        data_dir(game_name).exists(&slot_file("saved", "idm.sz", slot))
+    }
+
+    /// Return Ok(Some(save)) if save file is found in slot and parsed
+    /// successfully. Return Ok(None) if there is no save file. Return an
+    /// error if save file is present but could not be parsed.
+    pub fn load_slot(
+        &mut self,
+        game_name: &str,
+        slot: usize,
+    ) -> Result<Option<Runtime>> {
+        let Some(ret) = read_runtime(game_name, "saved", slot)? else {
+            return Ok(None);
+        };
+
+        // Keep recording to the existing input log if there is one.
+        self.replay = read_replay(game_name, "saved", slot, &ret);
+
+        Ok(Some(ret))
+    }
+
+    /// Recover what can be read from a main save file that fails to load.
+    ///
+    /// Return the runtime and the names of the save fields that were lost.
+    /// Return an error if the save is missing or can't be recovered at all.
+    pub fn salvage(
+        &mut self,
+        game_name: &str,
+    ) -> Result<(Runtime, Vec<String>)> {
+        let Some(save) = read_save_text(game_name, "saved", 0)? else {
+            bail!("No save file found");
+        };
+
+        let (mut ret, lost) = salvage_save(&save)?;
+        ret.bump_cache();
+
+        // A game restarted from the seed starts a new input log.
+        self.replay = if lost.iter().any(|s| s == "world") {
+            Replay::new(ret.seed().clone())
+        } else {
+            read_replay(game_name, "saved", 0, &ret)
+        };
+
+        Ok((ret, lost))
+    }
+
+    /// Read the saved interface preferences and apply them, if there are
+    /// any.
+    pub fn load_prefs(&mut self, game_name: &str) {
+        let Some(prefs) = data_dir(game_name)
+            .read_bytes("prefs.idm")
+            .ok()
+            .and_then(|s| {
+                idm::from_str::<Prefs>(std::str::from_utf8(&s).ok()?).ok()
+            })
+        else {
+            return;
+        };
+
+        self.set_theme(prefs.theme);
+        self.confirm = prefs.confirm;
+    }
+
+    pub fn save_prefs(&self, game_name: &str) {
+        let prefs = Prefs {
+            theme: self.theme,
+            confirm: self.confirm.clone(),
+        };
+        let prefs = idm::to_string(&prefs).expect("prefs serialization failed");
+        data_dir(game_name)
+            .write_bytes("prefs.idm", prefs.as_bytes())
+            .expect("writing prefs failed");
+    }
+
+    /// List the occupied save slots.
+    pub fn list_slots(&self, game_name: &str) -> Vec<SaveInfo> {
+        (0..NUM_SAVE_SLOTS)
+            .filter(|&slot| self.slot_exists(game_name, slot))
+            .map(|slot| {
+                // Saves from before info files existed still get listed,
+                // they just don't have much to show.
+                read_info(game_name, "saved", slot).unwrap_or(SaveInfo {
+                    slot,
+                    seed: Default::default(),
+                    turns: 0,
+                    timestamp: 0.0,
+                })
+            })
+            .collect()
+    }
+
+    /// Return whether enough turns have passed since the last autosave to
+    /// make a new one. Resets the autosave timer when returning true.
+    pub fn autosave_due(&mut self) -> bool {
+        let interval = self.autosave_interval * PHASES_IN_TURN;
+        if interval <= 0 || self.r.now() - self.last_autosave < interval {
+            return false;
+        }
+
+        self.last_autosave = self.r.now();
+        true
+    }
+
+    /// Write the next autosave in the rotation.
+    pub fn autosave(&mut self, game_name: &str) {
+        // Autosaves are numbered from 1 up.
+        let slot = self.next_autosave % NUM_AUTOSAVES + 1;
+        self.write_runtime(game_name, "autosave", slot);
+        self.next_autosave = slot % NUM_AUTOSAVES;
+    }
+
+    /// Return the info of the most recently written autosave.
+    pub fn newest_autosave(&self, game_name: &str) -> Option<SaveInfo> {
+        (1..=NUM_AUTOSAVES)
+            .filter(|&slot| {
+                data_dir(game_name)
+                    .exists(&slot_file("autosave", "idm.sz", slot))
+            })
+            .filter_map(|slot| read_info(game_name, "autosave", slot))
+            .max_by(|a, b| a.timestamp.total_cmp(&b.timestamp))
+    }
+
+    pub fn load_autosave(
+        &mut self,
+        game_name: &str,
+        slot: usize,
+    ) -> Result<Option<Runtime>> {
+        let Some(ret) = read_runtime(game_name, "autosave", slot)? else {
+            return Ok(None);
+        };
+
+        self.replay = read_replay(game_name, "autosave", slot, &ret);
+
+        Ok(Some(ret))
+    }
+
+    pub fn delete_autosaves(&self, game_name: &str) {
+        delete_files(
+            game_name,
+            (1..=NUM_AUTOSAVES).flat_map(|slot| {
+                [
+                    slot_file("autosave", "idm.sz", slot),
+                    slot_file("autosave", "info.idm", slot),
+                    slot_file("autosave-replay", "idm", slot),
+                ]
+            }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::PathBuf};
+
+    use engine::{prelude::*, PHASES_IN_TURN};
+    use util::Silo;
+
+    use super::{
+        data_dir, format_save, outline_sections, parse_save, section_name,
+        slot_file, SAVE_VERSION, VERSION_TAG,
+    };
+    use crate::Game;
+
+    /// Stand-in for the data directory that lives under the system temp
+    /// directory.
+    pub struct ScratchDir(pub PathBuf);
+
+    impl ScratchDir {
+        pub fn read_bytes(&self, file: &str) -> io::Result<Vec<u8>> {
+            fs::read(self.0.join(file))
+        }
+
+        pub fn write_bytes(&self, file: &str, data: &[u8]) -> io::Result<()> {
+            fs::create_dir_all(&self.0)?;
+            fs::write(self.0.join(file), data)
+        }
+
+        pub fn exists(&self, file: &str) -> bool {
+            self.0.join(file).exists()
+        }
+
+        pub fn delete(&self, file: &str) -> io::Result<()> {
+            fs::remove_file(self.0.join(file))
+        }
+    }
+
+    /// Clear out any files left in the scratch directory of a test game.
+    fn clear_scratch(game_name: &str) {
+        let _ = fs::remove_dir_all(data_dir(game_name).0);
+    }
+
+    #[test]
+    fn save_migration() {
+        world::register_data_from("../data").unwrap();
+
+        let r = Runtime::new(Silo::new("rand0m")).unwrap();
+        let current = idm::to_string(&r).unwrap();
+
+        // Current version goes through as is.
+        let loaded = parse_save(&format_save(&r)).unwrap();
+        assert_eq!(idm::to_string(&loaded).unwrap(), current);
+
+        // Untagged save from before versioning gets migrated.
+        let loaded = parse_save(&current).unwrap();
+        assert_eq!(idm::to_string(&loaded).unwrap(), current);
+
+        // Saves from the future can't be loaded.
+        let future = format!("{VERSION_TAG} {}\n{current}", SAVE_VERSION + 1);
+        assert!(parse_save(&future).is_err());
+    }
+
+    #[test]
+    fn save_slots() {
+        const NAME: &str = "gametemplate-slot-test";
+
+        world::register_data_from("../data").unwrap();
+
+        let mut game = Game::default();
+        clear_scratch(NAME);
+        assert!(game.list_slots(NAME).is_empty());
+
+        game.start(Silo::new("slot0")).unwrap();
+        game.save(NAME);
+        game.start(Silo::new("slot2")).unwrap();
+        game.save_slot(NAME, 2);
+
+        let slots = game.list_slots(NAME);
+        assert_eq!(slots.len(), 2);
+        assert_eq!(slots[0].slot, 0);
+        assert_eq!(slots[0].seed, Silo::new("slot0"));
+        assert_eq!(slots[1].slot, 2);
+        assert_eq!(slots[1].seed, Silo::new("slot2"));
+
+        let r = game.load_slot(NAME, 2).unwrap().unwrap();
+        assert_eq!(r.seed(), &Silo::new("slot2"));
+        assert!(game.load_slot(NAME, 1).unwrap().is_none());
+
+        // Slot 0 is the same as the plain save.
+        assert!(game.savefile_exists(NAME));
+        game.delete_save(NAME);
+        game.delete_slot(NAME, 2);
+        assert!(game.list_slots(NAME).is_empty());
+    }
+
+    #[test]
+    fn salvage_truncated_save() {
+        const NAME: &str = "gametemplate-salvage-test";
+
+        world::register_data_from("../data").unwrap();
+
+        let mut game = Game::default();
+        clear_scratch(NAME);
+        let seed = Silo::new("salvage");
+        game.start(seed.clone()).unwrap();
+
+        // Cut the save off in the middle of the world data.
+        let save = format_save(&game.r);
+        let world = outline_sections(&save)
+            .into_iter()
+            .find(|s| section_name(s) == "world")
+            .unwrap();
+        let end =
+            world.as_ptr() as usize - save.as_ptr() as usize + world.len() / 2;
+        let truncated = &save[..end];
+
+        let compressed = snap::raw::Encoder::new()
+            .compress_vec(truncated.as_bytes())
+            .unwrap();
+        data_dir(NAME)
+            .write_bytes(&slot_file("saved", "idm.sz", 0), &compressed)
+            .unwrap();
+
+        assert!(game.load(NAME).is_err());
+        let (r, lost) = game.salvage(NAME).unwrap();
+        assert_eq!(r.seed(), &seed);
+        assert!(r.player().is_some());
+        assert!(lost.iter().any(|s| s == "world"));
+        assert_eq!(game.replay.seed, seed);
+        assert!(game.replay.inputs.is_empty());
+
+        // Garbage that doesn't even have a seed can't be recovered.
+        let compressed =
+            snap::raw::Encoder::new().compress_vec(b"garbage").unwrap();
+        data_dir(NAME)
+            .write_bytes(&slot_file("saved", "idm.sz", 0), &compressed)
+            .unwrap();
+        assert!(game.salvage(NAME).is_err());
+
+        game.delete_save(NAME);
+    }
+
+    #[test]
+    fn autosave_interval() {
+        world::register_data_from("../data").unwrap();
+
+        let mut game = Game::default();
+        game.start(Silo::new("rand0m")).unwrap();
+        game.autosave_interval = 2;
+
+        let mut num_autosaves = 0;
+        for _ in 0..10 * PHASES_IN_TURN {
+            game.r.tick();
+            if game.autosave_due() {
+                num_autosaves += 1;
+            }
+        }
+        assert_eq!(num_autosaves, 5);
+
+        // Zero interval disables autosaves.
+        game.autosave_interval = 0;
+        for _ in 0..10 * PHASES_IN_TURN {
+            game.r.tick();
+            assert!(!game.autosave_due());
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt::Write;
+
+use util::writeln;
+
+use crate::{prelude::*, Danger, Theme};
+
+#[derive(Clone, Debug)]
+pub enum SettingsAction {
+    SetTheme(Theme),
+    ToggleConfirm(Danger),
+    Leave,
+}
+
+fn render(win: &Window) -> Option<SettingsAction> {
+    use SettingsAction::*;
+
+    let win = win.box_border();
+
+    let mut cur = Cursor::new(win);
+
+    let key = navni::keypress();
+
+    for theme in Theme::ALL {
+        let name = theme.name();
+        // Select by the first letter of the name.
+        let k = &name[..1];
+        let current = if game().theme == theme { " *" } else { "" };
+        if cur.print_button(&format!("{k}) Use {name} colors{current}"))
+            || key.is(k)
+        {
+            return Some(SetTheme(theme));
+        }
+        writeln!(cur);
+    }
+    writeln!(cur);
+
+    for (i, danger) in Danger::ALL.into_iter().enumerate() {
+        let k = format!("{}", i + 1);
+        let state = if game().confirm.contains(&danger) {
+            "on"
+        } else {
+            "off"
+        };
+        if cur.print_button(&format!("{k}) Confirm {}: {state}", danger.name()))
+            || key.is(&k)
+        {
+            return Some(ToggleConfirm(danger));
+        }
+        writeln!(cur);
+    }
+    writeln!(cur);
+
+    if cur.print_button("Esc) Leave") || key.is("Esc") {
+        return Some(Leave);
+    }
+    writeln!(cur);
+
+    None
+}
+
+/// Interface settings menu.
+pub async fn settings_menu() -> SettingsAction {
+    let win = Window::root();
+
+    loop {
+        if game().draw().await.is_none() {
+            return SettingsAction::Leave;
+        }
+
+        if let Some(ret) = render(&win) {
+            return ret;
+        }
+    }
+}
@@ -99,8 +99,8 @@ impl SectorView {
         let rect = Rect::from_points_inclusive([p1, p2]);
         Cube::new(
             self.unproject_2(rect.min()),
-            // XXX: Why do I need to use unproject_2 instead of _1 for the
-            // second one as well?
+            // The rect max is exclusive, snapping it to the right gives the
+            // exclusive bound of the cells whose centers are inside the rect.
             self.unproject_2(rect.max()) + ivec3(0, 0, 1),
         )
         .into_iter()
@@ -243,8 +243,9 @@ impl DisplayTile {
                     loc,
                 ) {
                     let tileset: &dyn Wallform = match block {
-                        Door => &CROSSED,
+                        Door | LockedDoor(_) => &CROSSED,
                         Glass => &SINGLE_LINE,
+                        Window => &'#',
                         Rubble => &'%',
                         Altar => &'=',
                         _ => &DOUBLE_LINE,
@@ -259,6 +260,7 @@ impl DisplayTile {
                         // Rough tiles make rough lines.
                         let tileset_2: &dyn Wallform = match right {
                             Wall(Glass) if block == Glass => &SINGLE_LINE,
+                            Wall(Window) if block == Window => &'#',
                             Wall(Rubble) if block == Rubble => &'%',
                             Wall(Altar) if block == Altar => &'=',
                             _ => &DOUBLE_LINE,
@@ -291,6 +293,7 @@ pub fn render_fog(
     win: &Window,
     pos: impl Into<IVec2>,
     loc: Location,
+    visible: &HashSet<Location>,
 ) {
     let r = r.as_ref();
     let pos = pos.into();
@@ -302,12 +305,107 @@ pub fn render_fog(
         (!left.is_explored(r), !right.is_explored(r));
     let cover_middle = cover_left || cover_right;
 
+    // Explored cells that aren't currently in view are shown from memory.
+    let (dim_left, dim_right) =
+        (!left.is_visible(r, visible), !right.is_visible(r, visible));
+    let dim_middle = dim_left || dim_right;
+
     if cover_left {
         win.put(pos, CharCell::c('░').col(X::BROWN));
+    } else if dim_left {
+        win.put(pos, remembered(win.get(pos)));
     }
 
+    let pos = pos + ivec2(1, 0);
     if cover_middle {
-        win.put(pos + ivec2(1, 0), CharCell::c('░').col(X::BROWN));
+        win.put(pos, CharCell::c('░').col(X::BROWN));
+    } else if dim_middle {
+        win.put(pos, remembered(win.get(pos)));
+    }
+}
+
+/// Dim a terrain cell that is remembered but not currently seen.
+fn remembered(mut cell: CharCell) -> CharCell {
+    if cell.foreground != X::BACKGROUND {
+        cell.foreground = X::GRAY;
+    }
+    cell
+}
+
+/// Cells for a mini health bar to show above a wounded mob.
+pub fn health_bar(wounds: i32, max_wounds: i32) -> [CharCell; 2] {
+    let max_wounds = max_wounds.max(1);
+    let health = (max_wounds - wounds).clamp(0, max_wounds);
+
+    // The bar has four half-cell steps, round up so that a live mob always
+    // shows some health.
+    let steps = (health * 4 + max_wounds - 1) / max_wounds;
+    let col = match health * 3 / max_wounds {
+        0 => X::RED,
+        1 => X::YELLOW,
+        _ => X::GREEN,
+    };
+
+    std::array::from_fn(|i| match steps - 2 * i as i32 {
+        n if n <= 0 => CharCell::c('─').col(X::GRAY),
+        1 => CharCell::c('▌').col(col),
+        _ => CharCell::c('█').col(col),
+    })
+}
+
+/// What a friendly NPC is up to, for showing on the map.
+///
+/// Each state has both a color and a marker glyph so it can be told apart
+/// without relying on color.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum AllyState {
+    /// Used up its moves for now.
+    OutOfMoves,
+    /// Off doing something other than following the player.
+    OnMission,
+    /// Ready for the next command.
+    Ready,
+    /// Still building up its actions.
+    Waiting,
+}
+
+impl AllyState {
+    pub const ALL: [AllyState; 4] = [
+        AllyState::OutOfMoves,
+        AllyState::OnMission,
+        AllyState::Ready,
+        AllyState::Waiting,
+    ];
+
+    pub fn new(r: &impl AsRef<Runtime>, e: Entity) -> Self {
+        if !e.can_be_commanded(r) {
+            AllyState::OutOfMoves
+        } else if e.goal(r) != Goal::FollowPlayer {
+            AllyState::OnMission
+        } else if e.acts_before_next_player_frame(r) {
+            AllyState::Ready
+        } else {
+            AllyState::Waiting
+        }
+    }
+
+    pub fn color(self) -> X {
+        match self {
+            AllyState::OutOfMoves => X::GRAY,
+            AllyState::OnMission => X::GREEN,
+            AllyState::Ready => X::AQUA,
+            AllyState::Waiting => X::TEAL,
+        }
+    }
+
+    /// Glyph shown next to the mob.
+    pub fn marker(self) -> char {
+        match self {
+            AllyState::OutOfMoves => '_',
+            AllyState::OnMission => '>',
+            AllyState::Ready => '!',
+            AllyState::Waiting => ':',
+        }
     }
 }
 
@@ -322,7 +420,9 @@ fn floor_cell(rng: &mut impl Rng, block: Block, is_center: bool) -> CharCell {
                 CharCell::c(' ')
             }
         }
-        Stone | Glass | Altar | Door | Grass | Rubble => CharCell::c(' '),
+        Stone | Glass | Window | Altar | Door | LockedDoor(_) | SecretDoor
+        | Grass | Rubble | HiddenTrap(_) => CharCell::c(' '),
+        Trap(_) => CharCell::c(if is_center { '^' } else { ' ' }).col(X::RED),
         SplatteredRock => CharCell::c(match rng.gen_range(0..=10) {
             d if d < 4 => ',',
             d if d < 7 => '\'',
@@ -396,3 +496,110 @@ const DOWN_SLOPE: [char; 16] = [
     ' ', '▲', '▶', '●', '▼', '●', '●', '●',
     '◀', '●', '●', '●', '●', '●', '●', '●',
 ];
+
+#[cfg(test)]
+mod tests {
+    use engine::prelude::*;
+    use glam::{ivec3, IVec3};
+    use navni::{prelude::*, X256Color as X};
+
+    use super::{health_bar, remembered, AllyState, SectorView};
+    use crate::prelude::Rect;
+
+    #[test]
+    fn half_health_bar() {
+        let chars = |bar: [navni::CharCell; 2]| {
+            bar.map(|c| char::from_u32(c.c as u32).unwrap())
+        };
+
+        assert_eq!(chars(health_bar(5, 10)), ['█', '─']);
+        assert_eq!(chars(health_bar(0, 10)), ['█', '█']);
+        assert_eq!(chars(health_bar(9, 10)), ['▌', '─']);
+    }
+
+    #[test]
+    fn ally_state_markers() {
+        use std::collections::HashSet;
+
+        // Every state gets its own marker so they can be told apart by
+        // shape alone.
+        let markers: HashSet<char> =
+            AllyState::ALL.iter().map(|s| s.marker()).collect();
+        assert_eq!(markers.len(), AllyState::ALL.len());
+        assert!(markers.iter().all(|c| !c.is_alphanumeric() && *c != ' '));
+    }
+
+    #[test]
+    fn projection_round_trip() {
+        const EXTREMES: [i32; 4] = [i16::MIN as i32, -1, 0, i16::MAX as i32];
+
+        let views = [
+            ivec3(0, 0, 0),
+            ivec3(-7, 3, -2),
+            ivec3(121, -45, 5),
+            ivec3(-80, -24, i16::MIN as i32),
+        ];
+
+        let locs: Vec<IVec3> = (-9..=9)
+            .flat_map(|x| (-5..=5).map(move |y| ivec3(x, y, 0)))
+            .chain(EXTREMES.into_iter().flat_map(|x| {
+                EXTREMES.into_iter().map(move |y| ivec3(x, y, 0))
+            }))
+            .collect();
+
+        for offset in views {
+            let view = SectorView(offset);
+            for &loc in &locs {
+                // Projection drops the z coordinate, unprojection takes it
+                // from the view.
+                let loc = loc + ivec3(0, 0, offset.z);
+                let pos = view.project(loc);
+                assert_eq!(view.unproject_1(pos), loc);
+                assert_eq!(view.unproject_2(pos), loc);
+
+                // Positions between cells snap to the neighboring cells.
+                let between = pos + ivec2(1, 0);
+                assert_eq!(view.unproject_1(between), loc);
+                assert_eq!(view.unproject_2(between), loc + ivec3(1, 0, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn view_rect_covers_cell_centers() {
+        let view = SectorView(ivec3(-3, 2, 1));
+
+        for (p1, p2) in [
+            ([0, 0], [0, 0]),
+            ([1, 0], [4, 2]),
+            ([-5, -3], [-2, 1]),
+            ([2, 5], [3, 5]),
+        ] {
+            // Cells whose centers are inside the rect.
+            let mut expected: Vec<Location> =
+                Rect::from_points_inclusive([p1, p2])
+                    .into_iter()
+                    .map(|p| (view.unproject_1(p), view.unproject_2(p)))
+                    .filter_map(|(a, b)| (a == b).then_some(a))
+                    .collect();
+            expected.sort_by_key(|loc| (loc.y, loc.x));
+
+            let mut got: Vec<Location> =
+                view.view_rect_locations(p1, p2).collect();
+            got.sort_by_key(|loc| (loc.y, loc.x));
+
+            assert_eq!(got, expected);
+        }
+    }
+
+    #[test]
+    fn remembered_terrain_is_dimmed() {
+        let cell = CharCell::c('#').col(X::YELLOW);
+        assert!(remembered(cell).foreground == X::GRAY);
+        assert!(remembered(cell).c == cell.c);
+
+        // Blank cells stay blank.
+        let cell = CharCell::c(' ').col(X::BACKGROUND);
+        assert!(remembered(cell).foreground == X::BACKGROUND);
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt::Write;
+
+use engine::prelude::*;
+use glam::{ivec2, IVec2};
+use navni::X256Color as X;
+use util::{writeln, StrExt};
+
+use crate::prelude::*;
+
+#[derive(Copy, Clone, Debug)]
+pub enum TradeAction {
+    Buy(Entity),
+    Sell(Entity),
+    Leave,
+}
+
+/// Menu for buying from and selling to a merchant.
+///
+/// Merchant's wares are picked with lowercase letters and the player's items
+/// with uppercase letters.
+pub struct TradeMenu {
+    merchant: Entity,
+}
+
+impl TradeMenu {
+    pub fn new(merchant: Entity) -> Self {
+        TradeMenu { merchant }
+    }
+}
+
+impl Widget for TradeMenu {
+    type Output = TradeAction;
+
+    fn render(&self, win: &Window) -> Option<Self::Output> {
+        use TradeAction::*;
+
+        let win = win.box_border();
+        let mut cur = Cursor::new(win);
+
+        let key = navni::keypress();
+        let r = &game().r;
+        let player = r.player()?;
+        let cash = player.carried_cash(r);
+
+        let name = self.merchant.noun(r).the_name().capitalize();
+        writeln!(cur, "{name} has for sale:");
+        for (item, c) in self.merchant.inventory(r).zip('a'..='z') {
+            cur.win.foreground_col = if item.price(r) > cash {
+                X::GRAY
+            } else {
+                X::BROWN
+            };
+
+            let text = format!("{c}) {} {}$", item.desc(r), item.price(r));
+            if cur.print_button(&text) || key.is(&c.to_string()) {
+                return Some(Buy(item));
+            }
+            writeln!(cur);
+        }
+        cur.win.foreground_col = X::BROWN;
+        writeln!(cur);
+
+        writeln!(cur, "You have {cash}$ and can sell:");
+        for (item, c) in player.inventory(r).zip('A'..='Z') {
+            let text = format!("{c}) {} {}$", item.desc(r), item.sell_price(r));
+            if cur.print_button(&text) || key.is(&c.to_string()) {
+                return Some(Sell(item));
+            }
+            writeln!(cur);
+        }
+        writeln!(cur);
+
+        if cur.print_button("Esc) Leave") || key.is("Esc") {
+            return Some(Leave);
+        }
+
+        None
+    }
+
+    fn preferred_size(&self) -> Option<IVec2> {
+        Some(ivec2(40, 30))
+    }
+}
+
+pub async fn trade(merchant: Entity) {
+    use TradeAction::*;
+
+    let menu = TradeMenu::new(merchant);
+    let mut win = Window::root().center(menu.preferred_size().unwrap());
+    win.foreground_col = X::BROWN;
+
+    let _backdrop = Backdrop::from(win);
+    loop {
+        if game().draw().await.is_none() {
+            return;
+        }
+
+        match menu.render(&win) {
+            Some(Buy(item)) => game().act(Action::Buy(merchant, item)),
+            Some(Sell(item)) => game().act(Action::Sell(merchant, item)),
+            Some(Leave) => return,
+            None => {}
+        }
+    }
+}
@@ -1,8 +1,9 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt::Write};
 
 use glam::{ivec2, IVec2};
 use navni::prelude::*;
-use util::StrExt;
+use util::{writeln, StrExt};
+use world::DialogueNode;
 
 use crate::prelude::*;
 
@@ -97,3 +98,67 @@ impl<'a> Widget for ConfirmationDialog<'a> {
         Some(ivec2(32, 8))
     }
 }
+
+/// A line of NPC dialogue with the player's responses.
+pub struct DialogueBox<'a> {
+    speaker: String,
+    node: &'a DialogueNode,
+}
+
+impl<'a> DialogueBox<'a> {
+    pub fn new(speaker: impl Into<String>, node: &'a DialogueNode) -> Self {
+        DialogueBox {
+            speaker: speaker.into(),
+            node,
+        }
+    }
+}
+
+impl<'a> Widget for DialogueBox<'a> {
+    /// Index of the picked response or `None` if the conversation ended.
+    type Output = Option<usize>;
+
+    fn render(&self, win: &Window) -> Option<Self::Output> {
+        let key = navni::keypress();
+
+        let win = win.box_border();
+
+        let num_choices = self.node.choices.len().max(1) as i32;
+        let (choices, mut message) = win.split_bottom(num_choices + 1);
+
+        message.bounds = message.bounds.shrink([1, 0], [1, 0]);
+        let text = format!("{}: {}", self.speaker, self.node.text);
+        Widget::render(&*text, &message);
+
+        let mut cur = Cursor::new(choices);
+        writeln!(cur);
+
+        // Any key ends the conversation when there's nothing to answer.
+        if self.node.choices.is_empty() {
+            if cur.print_button("Esc) Leave") || key.is_some() {
+                return Some(None);
+            }
+            return None;
+        }
+
+        for (i, ((text,), _)) in self.node.choices.iter().enumerate() {
+            let c = i + 1;
+            if cur.print_button(&format!("{c}) {text}"))
+                || key.is(&c.to_string())
+            {
+                return Some(Some(i));
+            }
+            writeln!(cur);
+        }
+
+        if key.is("Esc") {
+            return Some(None);
+        }
+
+        None
+    }
+
+    fn preferred_size(&self) -> Option<IVec2> {
+        Some(ivec2(48, 12))
+    }
+}
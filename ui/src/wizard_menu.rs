@@ -0,0 +1,108 @@
+use std::fmt::Write;
+
+use navni::X256Color as X;
+use util::writeln;
+use world::Data;
+
+use crate::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum WizardAction {
+    RevealSector,
+    Teleport,
+    Spawn(String),
+    ToggleInvulnerable,
+    ReloadData,
+    Leave,
+}
+
+async fn render(win: &Window) -> Option<WizardAction> {
+    use WizardAction::*;
+
+    let win = win.box_border();
+
+    let mut cur = Cursor::new(win);
+
+    let key = navni::keypress();
+
+    if cur.print_button("r) Reveal sector") || key.is("r") {
+        return Some(RevealSector);
+    }
+    writeln!(cur);
+
+    if cur.print_button("t) Teleport to cursor") || key.is("t") {
+        return Some(Teleport);
+    }
+    writeln!(cur);
+
+    if cur.print_button("s) Spawn monster at cursor") || key.is("s") {
+        return Some(match monster_menu().await {
+            Some(name) => Spawn(name),
+            None => Leave,
+        });
+    }
+    writeln!(cur);
+
+    if cur.print_button("i) Toggle invulnerability") || key.is("i") {
+        return Some(ToggleInvulnerable);
+    }
+    writeln!(cur);
+
+    if cur.print_button("d) Reload data files") || key.is("d") {
+        return Some(ReloadData);
+    }
+    writeln!(cur);
+    writeln!(cur);
+
+    if cur.print_button("Esc) Leave") || key.is("Esc") {
+        return Some(Leave);
+    }
+    writeln!(cur);
+
+    None
+}
+
+/// Select a monster from the bestiary.
+async fn monster_menu() -> Option<String> {
+    const KEYS: &str = "abcdefghijklmnopqrstuvwxyz";
+
+    let mut win = Window::root().box_border();
+    win.foreground_col = X::BROWN;
+
+    loop {
+        game().draw().await?;
+
+        let mut cur = Cursor::new(win);
+        let key = navni::keypress();
+
+        for (name, c) in Data::get().bestiary.keys().zip(KEYS.chars()) {
+            if cur.print_button(&format!("{c}) {}", name.0))
+                || key.is(&c.to_string())
+            {
+                return Some(name.0.clone());
+            }
+            writeln!(cur);
+        }
+        writeln!(cur);
+
+        if cur.print_button("Esc) Cancel") || key.is("Esc") {
+            return None;
+        }
+    }
+}
+
+/// Debug command menu, only for wizard mode.
+pub async fn wizard_menu() -> WizardAction {
+    let mut win = Window::root();
+    win.foreground_col = X::BROWN;
+
+    loop {
+        if game().draw().await.is_none() {
+            return WizardAction::Leave;
+        }
+
+        if let Some(ret) = render(&win).await {
+            return ret;
+        }
+    }
+}
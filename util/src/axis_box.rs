@@ -477,6 +477,26 @@ impl<T: Element, const N: usize> AxisBox<T, N> {
     }
 }
 
+impl<T: Element> AxisBox<T, 2> {
+    /// Rotate the rectangle clockwise by quarter turns about the origin.
+    ///
+    /// Clockwise is in screen space with the y axis pointing down, a point
+    /// `[x, y]` moves to `[-y, x]` on each quarter turn. Negative values
+    /// rotate counterclockwise. Translate the rectangle so that the pivot is
+    /// at origin first to rotate around some other point.
+    ///
+    /// For integer rectangles treated as sets of cells, the cell at `[x, y]`
+    /// ends up at `[-y - 1, x]`.
+    pub fn rotated_cw(&self, quarters: i32) -> Self {
+        let mut ret = *self;
+        for _ in 0..quarters.rem_euclid(4) {
+            let ([x0, y0], [x1, y1]) = (ret.p0, ret.p1);
+            ret = AxisBox::new([-y1, x0], [-y0, x1]);
+        }
+        ret
+    }
+}
+
 impl<T: Element> AxisBox<T, 3> {
     pub fn flatten(&self) -> AxisBox<T, 2> {
         AxisBox::new(
@@ -778,6 +798,18 @@ impl IntegerBox<2> {
     }
 }
 
+impl IntegerBox<3> {
+    /// Iterate through the cells on the six faces of the box.
+    ///
+    /// Every cell is visited exactly once, also the ones on edges and corners
+    /// where faces meet.
+    pub fn surface(&self) -> impl Iterator<Item = [i32; 3]> {
+        let (p0, p1) = (self.p0, self.p1);
+        self.into_iter()
+            .filter(move |p| (0..3).any(|i| p[i] == p0[i] || p[i] == p1[i] - 1))
+    }
+}
+
 impl<E, T, const N: usize> Add<E> for AxisBox<T, N>
 where
     E: Into<[T; N]>,
@@ -1093,4 +1125,45 @@ mod tests {
             Rect::new([70, 70], [80, 80])
         );
     }
+
+    #[test]
+    fn rotation() {
+        let rect = Rect::new([2, -1], [7, 2]);
+
+        let once = rect.rotated_cw(1);
+        assert_eq!(once, Rect::new([-2, 2], [1, 7]));
+        assert_eq!(once.dim(), [rect.height(), rect.width()]);
+
+        assert_eq!(rect.rotated_cw(4), rect);
+        assert_eq!(rect.rotated_cw(0), rect);
+        assert_eq!(rect.rotated_cw(-1), rect.rotated_cw(3));
+
+        let mut r = rect;
+        for _ in 0..4 {
+            r = r.rotated_cw(1);
+        }
+        assert_eq!(r, rect);
+
+        // Cells move along with the rectangle.
+        for [x, y] in rect {
+            assert!(once.contains([-y - 1, x]));
+        }
+    }
+
+    #[test]
+    fn surface() {
+        for dim in [[4, 5, 6], [1, 3, 3], [2, 2, 2], [1, 1, 1]] {
+            let bounds =
+                Cube::new([-2, 3, 1], [-2 + dim[0], 3 + dim[1], 1 + dim[2]]);
+            let cells: Vec<[i32; 3]> = bounds.surface().collect();
+
+            // Shell is the whole box minus its interior.
+            let inner: i32 = dim.iter().map(|&d| (d - 2).max(0)).product();
+            assert_eq!(cells.len() as i32, bounds.volume() - inner);
+
+            let unique: std::collections::BTreeSet<_> = cells.iter().collect();
+            assert_eq!(unique.len(), cells.len());
+            assert!(cells.iter().all(|&p| bounds.contains(p)));
+        }
+    }
 }
@@ -1,8 +1,9 @@
 use std::{collections::BTreeMap, fmt, str::FromStr};
 
 use derive_more::Deref;
+use serde::{Deserialize, Serialize};
 
-use crate::{IntegerBox, StrExt};
+use crate::{HashSet, IntegerBox, StrExt};
 
 #[derive(Clone, Eq, PartialEq, Debug, Deref)]
 pub struct Cloud<const N: usize, V> {
@@ -91,6 +92,69 @@ impl<V, const N: usize> Cloud<N, V> {
             self.points.insert(p, v);
         }
     }
+
+    /// Collect the points reachable from `start` through axis-adjacent
+    /// points whose values satisfy `predicate`.
+    ///
+    /// Returns an empty set if `start` is not in the cloud or does not
+    /// satisfy the predicate.
+    pub fn flood_fill(
+        &self,
+        start: impl Into<[i32; N]>,
+        predicate: impl Fn(&V) -> bool,
+    ) -> HashSet<[i32; N]> {
+        let start = start.into();
+        if !self.get(&start).is_some_and(&predicate) {
+            return Default::default();
+        }
+
+        crate::bfs(
+            |&p: &[i32; N]| {
+                axis_neighbors(p)
+                    .filter(|q| self.get(q).is_some_and(&predicate))
+                    .collect::<Vec<_>>()
+            },
+            [start],
+        )
+        .map(|(p, _)| p)
+        .collect()
+    }
+
+    /// Split the points whose values satisfy `predicate` into separate
+    /// axis-connected regions.
+    ///
+    /// Regions are ordered by their smallest point.
+    pub fn connected_components(
+        &self,
+        predicate: impl Fn(&V) -> bool,
+    ) -> Vec<HashSet<[i32; N]>> {
+        let mut seen = HashSet::default();
+        let mut ret = Vec::new();
+
+        for (p, v) in self.points.iter() {
+            if seen.contains(p) || !predicate(v) {
+                continue;
+            }
+            let region = self.flood_fill(*p, &predicate);
+            seen.extend(region.iter().copied());
+            ret.push(region);
+        }
+
+        ret
+    }
+}
+
+/// Points one step away from `p` along each axis.
+fn axis_neighbors<const N: usize>(
+    p: [i32; N],
+) -> impl Iterator<Item = [i32; N]> {
+    (0..N).flat_map(move |i| {
+        [-1, 1].into_iter().map(move |d| {
+            let mut q = p;
+            q[i] += d;
+            q
+        })
+    })
 }
 
 impl<V, const N: usize> Default for Cloud<N, V> {
@@ -133,6 +197,64 @@ impl<V, const N: usize> IntoIterator for Cloud<N, V> {
     }
 }
 
+// Clouds are serialized sparsely as runs of points that are consecutive along
+// the first axis. Each run is stored as its starting point followed by the
+// values of the run. The bounds are recomputed from the points when
+// deserializing, so loose bounds left over from removals get tightened.
+
+impl<V: Serialize, const N: usize> Serialize for Cloud<N, V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let step = |mut p: [i32; N], d| {
+            p[0] += d;
+            p
+        };
+
+        // Points are vecs instead of arrays because serde can't do generic
+        // length arrays.
+        let mut runs: Vec<(Vec<i32>, Vec<&V>)> = Vec::new();
+        for &start in self.points.keys() {
+            // Only start runs from points that don't continue an earlier
+            // run.
+            if self.points.contains_key(&step(start, -1)) {
+                continue;
+            }
+
+            let vals = (0..)
+                .map_while(|i| self.points.get(&step(start, i)))
+                .collect();
+            runs.push((start.to_vec(), vals));
+        }
+
+        runs.serialize(serializer)
+    }
+}
+
+impl<'de, V: Deserialize<'de>, const N: usize> Deserialize<'de>
+    for Cloud<N, V>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let runs = <Vec<(Vec<i32>, Vec<V>)>>::deserialize(deserializer)?;
+
+        let mut ret = Cloud::default();
+        for (start, vals) in runs {
+            let Ok(mut p) = <[i32; N]>::try_from(start) else {
+                return Err(serde::de::Error::custom("bad point dimension"));
+            };
+            for v in vals {
+                ret.insert(p, v);
+                p[0] += 1;
+            }
+        }
+        Ok(ret)
+    }
+}
+
 impl FromStr for Cloud<2, char> {
     type Err = anyhow::Error;
 
@@ -141,6 +263,26 @@ impl FromStr for Cloud<2, char> {
     }
 }
 
+impl Cloud<2, char> {
+    /// Render the cloud as a solid block of text with the points missing
+    /// from within the bounding box shown as `fill`.
+    ///
+    /// Unlike the `Display` output, every line is padded to the full width
+    /// of the bounds, which makes this handy for debug dumps.
+    pub fn to_string_filled(&self, fill: char) -> String {
+        let mut ret = String::new();
+        let [x0, y0] = self.bounds().min();
+        let [x1, y1] = self.bounds().max();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                ret.push(*self.get(&[x, y]).unwrap_or(&fill));
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+}
+
 impl fmt::Display for Cloud<2, char> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut x = 0;
@@ -171,3 +313,71 @@ impl fmt::Display for Cloud<2, char> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_round_trip() {
+        let text = "\
+#####
+#.. #
+#.#.
+#####";
+        let map: Cloud<2, char> = text.parse().unwrap();
+        assert_eq!(map.bounds().dim(), [5, 4]);
+        assert_eq!(map.get(&[3, 1]), None);
+
+        // Display writes gaps as NBSP, normalize those back.
+        let displayed = map.to_string().replace('\u{00a0}', " ");
+        assert_eq!(displayed, text);
+
+        let reparsed: Cloud<2, char> = displayed.parse().unwrap();
+        assert_eq!(reparsed, map);
+
+        assert_eq!(map.to_string_filled('_'), "#####\n#.._#\n#.#._\n#####\n");
+    }
+
+    #[test]
+    fn components() {
+        let map: Cloud<2, char> = "\
+#######
+#..#..#
+#..#..#
+#######"
+            .parse()
+            .unwrap();
+
+        let rooms = map.connected_components(|&c| c == '.');
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(rooms[0].len(), 4);
+        assert_eq!(rooms[1].len(), 4);
+        assert!(rooms[0].contains(&[1, 1]));
+        assert!(rooms[1].contains(&[5, 2]));
+
+        assert_eq!(map.flood_fill([1, 1], |&c| c == '.'), rooms[0]);
+        assert!(map.flood_fill([0, 0], |&c| c == '.').is_empty());
+        assert_eq!(map.connected_components(|&c| c == '#').len(), 1);
+    }
+
+    #[test]
+    fn sparse_serialization() {
+        let mut cloud: Cloud<3, char> = Cloud::default();
+        cloud.extend([
+            ([0, 0, 0], 'a'),
+            ([1, 0, 0], 'b'),
+            ([2, 0, 0], 'c'),
+            ([-50, 20, -3], 'd'),
+            ([99, 99, 9], 'e'),
+            ([40, -60, 2], 'f'),
+        ]);
+
+        let dense_size = cloud.bounds().volume() as usize;
+        let text = idm::to_string(&cloud).unwrap();
+        assert!(text.len() * 100 < dense_size);
+
+        let cloud2: Cloud<3, char> = idm::from_str(&text).unwrap();
+        assert_eq!(cloud2, cloud);
+    }
+}
@@ -1,5 +1,6 @@
 use std::{fmt, ops::RangeInclusive};
 
+use anyhow::bail;
 use nom::{
     branch::alt,
     bytes::complete::tag,
@@ -146,6 +147,101 @@ impl std::str::FromStr for RangeDistribution {
     }
 }
 
+/// Discrete distribution of integer values with relative weights.
+///
+/// Written as space-separated `value:weight` pairs, eg. `1:10 2:5 3:1`.
+#[derive(Clone, Debug, Eq, PartialEq, DeserializeFromStr, SerializeDisplay)]
+pub struct WeightedDistribution(Vec<(i32, u32)>);
+
+impl WeightedDistribution {
+    /// Build a distribution from value, weight pairs.
+    ///
+    /// Panics if the weights don't add up to a positive number.
+    pub fn new(weights: impl IntoIterator<Item = (i32, u32)>) -> Self {
+        let mut weights: Vec<(i32, u32)> = weights.into_iter().collect();
+        assert!(
+            weights.iter().any(|&(_, w)| w > 0),
+            "WeightedDistribution::new: No positive weights"
+        );
+        weights.sort_by_key(|&(v, _)| v);
+        WeightedDistribution(weights)
+    }
+
+    pub fn total_weight(&self) -> u32 {
+        self.0.iter().map(|&(_, w)| w).sum()
+    }
+
+    /// Return the smallest value whose cumulative weight exceeds the fraction
+    /// `p` of the total weight.
+    ///
+    /// This is the inverse of `cdf`, `quantile(0.5)` is the median.
+    pub fn quantile(&self, p: f32) -> i32 {
+        let threshold = p * self.total_weight() as f32;
+
+        let mut acc = 0;
+        for &(value, weight) in &self.0 {
+            acc += weight;
+            if acc as f32 > threshold {
+                return value;
+            }
+        }
+
+        // Out of bounds p at the top end.
+        self.0[self.0.len() - 1].0
+    }
+
+    /// Fraction of the total weight at or below the given value.
+    pub fn cdf(&self, value: i32) -> f32 {
+        let below: u32 = self
+            .0
+            .iter()
+            .take_while(|&&(v, _)| v <= value)
+            .map(|&(_, w)| w)
+            .sum();
+        below as f32 / self.total_weight() as f32
+    }
+}
+
+impl PlottedDistribution for WeightedDistribution {
+    type Item = i32;
+
+    fn plot(&self, x: f32) -> i32 {
+        self.quantile(x)
+    }
+}
+
+impl fmt::Display for WeightedDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (value, weight)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{value}:{weight}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for WeightedDistribution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Vec::new();
+        for pair in s.split_whitespace() {
+            let Some((value, weight)) = pair.split_once(':') else {
+                bail!("Bad weighted value {pair:?}");
+            };
+            weights.push((value.parse()?, weight.parse()?));
+        }
+
+        if !weights.iter().any(|&(_, w)| w > 0) {
+            bail!("No positive weights in distribution {s:?}");
+        }
+
+        Ok(WeightedDistribution::new(weights))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,4 +294,38 @@ mod tests {
         assert!("1,1..2".parse::<RangeDistribution>().is_err());
         assert!("..".parse::<RangeDistribution>().is_err());
     }
+
+    #[test]
+    fn weighted_distribution() {
+        let dist: WeightedDistribution = "3:1 1:1 2:2".parse().unwrap();
+        assert_eq!(dist.to_string(), "1:1 2:2 3:1");
+        assert_eq!(dist.total_weight(), 4);
+
+        assert_eq!(dist.quantile(0.0), 1);
+        assert_eq!(dist.quantile(0.5), 2);
+        assert_eq!(dist.quantile(0.99), 3);
+        assert_eq!(dist.quantile(1.0), 3);
+
+        assert_eq!(dist.cdf(0), 0.0);
+        assert_eq!(dist.cdf(1), 0.25);
+        assert_eq!(dist.cdf(2), 0.75);
+        assert_eq!(dist.cdf(3), 1.0);
+
+        // Sampled frequencies approximate the weights.
+        const N: usize = 10_000;
+        let mut rng = crate::srng(&"weighted");
+        let mut counts = [0; 3];
+        for _ in 0..N {
+            counts[(dist.sample(&mut rng) - 1) as usize] += 1;
+        }
+        for (count, weight) in counts.into_iter().zip([1, 2, 1]) {
+            let freq = count as f32 / N as f32;
+            assert!((freq - weight as f32 / 4.0).abs() < 0.02);
+        }
+
+        assert!("".parse::<WeightedDistribution>().is_err());
+        assert!("1:0".parse::<WeightedDistribution>().is_err());
+        assert!("1:2 3".parse::<WeightedDistribution>().is_err());
+        assert!("x:2".parse::<WeightedDistribution>().is_err());
+    }
 }
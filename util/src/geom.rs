@@ -223,10 +223,63 @@ pub mod s8 {
         DIR[a as usize]
     }
 
+    /// Iterate the cells at exactly chessboard distance `radius` from
+    /// `center`, clockwise starting from the top left corner.
+    ///
+    /// Radius 0 yields just the center.
+    pub fn ring(center: IVec2, radius: i32) -> impl Iterator<Item = IVec2> {
+        let r = radius.max(0);
+        let corner = center - ivec2(r, r);
+        // Walk each of the four edges for 2r steps, or emit the center once
+        // for r = 0.
+        let len = if r == 0 { 1 } else { 8 * r };
+        (0..len).map(move |i| {
+            if r == 0 {
+                return center;
+            }
+            let (edge, n) = (i / (2 * r), i % (2 * r));
+            match edge {
+                0 => corner + ivec2(n, 0),
+                1 => corner + ivec2(2 * r, n),
+                2 => corner + ivec2(2 * r - n, 2 * r),
+                _ => corner + ivec2(0, 2 * r - n),
+            }
+        })
+    }
+
+    /// Iterate cells outward from `center` in shells of increasing
+    /// chessboard distance.
+    ///
+    /// The iterator is infinite, cap it with `take` or `take_while`.
+    pub fn spiral(center: IVec2) -> impl Iterator<Item = IVec2> {
+        (0..).flat_map(move |r| ring(center, r))
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
 
+        #[test]
+        fn rings() {
+            let c = ivec2(3, -2);
+            assert_eq!(ring(c, 0).collect::<Vec<_>>(), vec![c]);
+
+            let mut r1: Vec<IVec2> = ring(c, 1).collect();
+            let mut ns: Vec<IVec2> = DIR.iter().map(|&d| c + d).collect();
+            r1.sort_by_key(|p| (p.x, p.y));
+            ns.sort_by_key(|p| (p.x, p.y));
+            assert_eq!(r1, ns);
+
+            let r3: Vec<IVec2> = ring(c, 3).collect();
+            assert_eq!(r3.len(), 24);
+            assert!(r3.iter().all(|p| d(p, &c) == 3));
+
+            let spiral: Vec<IVec2> = spiral(c).take(1 + 8 + 16).collect();
+            assert_eq!(spiral[0], c);
+            assert!(spiral.windows(2).all(|w| d(&w[0], &c) <= d(&w[1], &c)));
+            assert_eq!(d(&spiral[24], &c), 2);
+        }
+
         #[test]
         fn dirs() {
             eprintln!("s8 test picture");
@@ -651,6 +704,85 @@ impl Iterator for LineIter {
     }
 }
 
+/// Bresenham line through 3D space.
+///
+/// Like `bresenham_line`, yields nothing if the endpoints are the same.
+pub fn bresenham_line_3d(
+    a: impl Into<IVec3>,
+    b: impl Into<IVec3>,
+) -> LineIter3D {
+    LineIter3D::new(a, b)
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct LineIter3D {
+    /// Absolute deltas along each axis.
+    d: IVec3,
+    step: IVec3,
+    /// Index of the axis with the largest delta.
+    major: usize,
+
+    err: IVec3,
+    p: IVec3,
+    end: IVec3,
+}
+
+impl LineIter3D {
+    pub fn new(a: impl Into<IVec3>, b: impl Into<IVec3>) -> Self {
+        let (a, end): (IVec3, IVec3) = (a.into(), b.into());
+
+        let d = end - a;
+        let step = d.signum();
+        let d = d.abs();
+        let major = (0..3).max_by_key(|&i| (d[i], -(i as i32))).unwrap();
+        // Minor axes accumulate error against the major axis, the major
+        // axis error term is unused.
+        let err = IVec3::from_array(std::array::from_fn(|i| {
+            if i == major {
+                0
+            } else {
+                2 * d[i] - d[major]
+            }
+        }));
+        let p = a;
+
+        LineIter3D {
+            d,
+            step,
+            major,
+            err,
+            p,
+            end,
+        }
+    }
+}
+
+impl Iterator for LineIter3D {
+    type Item = IVec3;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.step == IVec3::ZERO {
+            return None;
+        } else if self.p == self.end {
+            self.step = IVec3::ZERO;
+        }
+
+        let ret = self.p;
+
+        let m = self.major;
+        self.p[m] += self.step[m];
+        for i in (0..3).filter(|&i| i != m) {
+            if self.err[i] > 0 {
+                self.p[i] += self.step[i];
+                self.err[i] -= 2 * self.d[m];
+            }
+            self.err[i] += 2 * self.d[i];
+        }
+
+        Some(ret)
+    }
+}
+
 pub struct PolyLineIter<I> {
     inner: I,
     line: LineIter,
@@ -825,6 +957,27 @@ impl Angle {
     pub fn deg(self) -> f32 {
         self.0 * 360.0 / TAU
     }
+
+    /// Interpolate between two angles along the shorter arc between them.
+    pub fn lerp(a: Angle, b: Angle, t: f32) -> Angle {
+        let delta = (b - a).normalize().0;
+        Angle(a.0 + delta * t).normalize()
+    }
+
+    /// Turn the angle towards a target angle by at most `max_step`.
+    ///
+    /// Turns along the shorter arc and stops at the target without
+    /// overshooting.
+    pub fn rotate_toward(self, target: Angle, max_step: Angle) -> Angle {
+        let delta = (target - self).normalize().0;
+        let step = max_step.0.abs();
+
+        if delta.abs() <= step {
+            target.normalize()
+        } else {
+            Angle(self.0 + step * delta.signum()).normalize()
+        }
+    }
 }
 
 impl From<Vec2> for Angle {
@@ -885,6 +1038,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn angle_turning() {
+        let near = |a: Angle, deg: f32| (a - Angle::new(deg)).abs() < 0.01;
+
+        assert!(near(
+            Angle::lerp(Angle::new(10.0), Angle::new(30.0), 0.5),
+            20.0
+        ));
+        assert!(near(
+            Angle::lerp(Angle::new(10.0), Angle::new(30.0), 0.0),
+            10.0
+        ));
+        // Interpolate across the seam.
+        assert!(near(
+            Angle::lerp(Angle::new(350.0), Angle::new(10.0), 0.5),
+            0.0
+        ));
+        assert!(near(
+            Angle::lerp(Angle::new(170.0), Angle::new(-170.0), 0.5),
+            180.0
+        ));
+
+        // Turn clockwise through 0 degrees instead of going the long way.
+        let mut a = Angle::new(350.0);
+        for deg in [355.0, 0.0, 5.0, 10.0, 10.0] {
+            a = a.rotate_toward(Angle::new(10.0), Angle::new(5.0));
+            assert!(near(a, deg), "{a} != {deg}");
+        }
+
+        // Don't overshoot.
+        let a =
+            Angle::new(0.0).rotate_toward(Angle::new(-3.0), Angle::new(5.0));
+        assert!(near(a, -3.0));
+    }
+
     #[test]
     fn bresenham() {
         assert_eq!(
@@ -898,6 +1086,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bresenham_3d() {
+        assert_eq!(
+            bresenham_line_3d([10, 10, 0], [10, 10, 0]).collect::<Vec<_>>(),
+            vec![]
+        );
+
+        assert_eq!(
+            bresenham_line_3d([10, 10, 0], [12, 10, 0]).collect::<Vec<_>>(),
+            vec![ivec3(10, 10, 0), ivec3(11, 10, 0), ivec3(12, 10, 0)]
+        );
+
+        // Pure vertical.
+        assert_eq!(
+            bresenham_line_3d([0, 0, 2], [0, 0, -1]).collect::<Vec<_>>(),
+            vec![
+                ivec3(0, 0, 2),
+                ivec3(0, 0, 1),
+                ivec3(0, 0, 0),
+                ivec3(0, 0, -1)
+            ]
+        );
+
+        // Full diagonal.
+        assert_eq!(
+            bresenham_line_3d([0, 0, 0], [-2, 2, 2]).collect::<Vec<_>>(),
+            vec![ivec3(0, 0, 0), ivec3(-1, 1, 1), ivec3(-2, 2, 2)]
+        );
+
+        // Shallow slope ends at the endpoint and makes unit steps.
+        let line: Vec<IVec3> =
+            bresenham_line_3d([0, 0, 0], [7, 3, -2]).collect();
+        assert_eq!(line.len(), 8);
+        assert_eq!(line[0], ivec3(0, 0, 0));
+        assert_eq!(line[7], ivec3(7, 3, -2));
+        for w in line.windows(2) {
+            let d = w[1] - w[0];
+            assert_eq!(d.x, 1);
+            assert!(d.y.abs() <= 1 && d.z.abs() <= 1);
+        }
+    }
+
     #[test]
     fn polyline() {
         assert_eq!(PolyLineIter::new(vec![]).collect::<Vec<_>>(), vec![]);
@@ -53,7 +53,7 @@ impl Noun {
             // TODO: Add look-up table of irregular words ('honor', 'unit') as they show up in game
             // text.
             let article =
-                if self.name().chars().next().map_or(false, |c| c.is_vowel()) {
+                if self.name().chars().next().is_some_and(|c| c.is_vowel()) {
                     "an"
                 } else {
                     "a"
@@ -169,6 +169,15 @@ impl Noun {
                 }
             }
 
+            // Whole verbs in base form, [hit].
+            verb if VERBS.contains(&verb) => {
+                if self.third_person_singular() {
+                    third_person(verb)
+                } else {
+                    verb.into()
+                }
+            }
+
             _ => {
                 bail!("bad token")
             }
@@ -177,6 +186,42 @@ impl Noun {
     }
 }
 
+/// Verbs that can be used whole in templates, in base form.
+///
+/// Add new verbs here when they show up in game text so that typos in
+/// templates still get caught.
+const VERBS: &[&str] = &[
+    "attack", "crush", "die", "do", "drink", "drop", "eat", "fall", "fly",
+    "focus", "go", "hit", "lie", "miss", "parry", "play", "reach", "stay",
+    "throw", "zap",
+];
+
+/// Irregular verbs as (base form, third person singular form).
+const IRREGULAR_VERBS: [(&str, &str); 2] = [("do", "does"), ("go", "goes")];
+
+/// Third person singular form of a verb in base form, "hit" to "hits".
+fn third_person(verb: &str) -> String {
+    if let Some(&(_, third)) =
+        IRREGULAR_VERBS.iter().find(|(base, _)| *base == verb)
+    {
+        return third.into();
+    }
+
+    if let Some(stem) = verb
+        .strip_suffix('y')
+        .filter(|stem| stem.chars().last().is_some_and(|c| !c.is_vowel()))
+    {
+        format!("{stem}ies")
+    } else if ["s", "ch", "sh", "x", "z", "o"]
+        .iter()
+        .any(|suffix| verb.ends_with(suffix))
+    {
+        format!("{verb}es")
+    } else {
+        format!("{verb}s")
+    }
+}
+
 pub struct Sentence<'a> {
     subject: &'a Noun,
     object: &'a Noun,
@@ -203,9 +248,36 @@ impl<'a> Sentence<'a> {
     }
 }
 
+/// Ordinal number with a numeric suffix, "1st", "2nd", "3rd", "11th".
+pub fn ordinal(n: i32) -> String {
+    let suffix = match (n.abs() % 10, n.abs() % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+/// Cardinal number spelled out for small numbers, "zero" to "twelve", and
+/// written in digits for anything else.
+pub fn cardinal(n: i32) -> String {
+    const WORDS: [&str; 13] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight",
+        "nine", "ten", "eleven", "twelve",
+    ];
+
+    match usize::try_from(n).ok().and_then(|i| WORDS.get(i)) {
+        Some(word) => word.to_string(),
+        None => n.to_string(),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::{
+        cardinal, ordinal,
         Noun::{self, *},
         Sentence,
     };
@@ -410,4 +482,111 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_verb_conjugation() {
+        for (subject, object, template, message) in parse_obj(
+            "PLAYER
+            goblin
+            [One] [hit] [another].
+            You hit the goblin.
+
+            goblin
+            PLAYER
+            [One] [hit] [another].
+            The goblin hits you.
+
+            PLAYER
+            goblin
+            [One] [miss] [another] and [parry] the counter.
+            You miss the goblin and parry the counter.
+
+            goblin
+            PLAYER
+            [One] [miss] [another] and [parry] the counter.
+            The goblin misses you and parries the counter.
+
+            PLAYER
+            goblin
+            [One] [do] not [go] after [them]. [They] [stay].
+            You do not go after it. You stay.
+
+            Athena
+            goblin
+            [One] [go] after [them]. [They] [do] not stop.
+            Athena goes after it. She does not stop.
+
+            2 rocks
+            goblin
+            [One] [crush] [another] and [fly] on.
+            2 rocks crush the goblin and fly on.
+
+            goblin
+            PLAYER
+            [One] [reach] for [another] and [play] dead.
+            The goblin reaches for you and plays dead.
+
+            PLAYER
+            goblin
+            [One] [focus] on [another] and [die].
+            You focus on the goblin and die.
+
+            goblin
+            PLAYER
+            [One] [focus] on [another], [lie] down and [die].
+            The goblin focuses on you, lies down and dies.
+            ",
+        )
+        .into_iter()
+        {
+            let a = make_noun(subject);
+            let b = make_noun(object);
+            assert_eq!(
+                template
+                    .templatize(|e| Sentence::new(&a, &b).convert(e))
+                    .unwrap(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_bad_tokens() {
+        let goblin = make_noun("goblin");
+        // Empty tokens, typos and verbs not in base form are errors.
+        for token in ["", "hitt", "dies"] {
+            assert!(goblin.convert(token).is_err(), "{token:?}");
+        }
+    }
+
+    #[test]
+    fn test_ordinal() {
+        for (n, s) in [
+            (0, "0th"),
+            (1, "1st"),
+            (2, "2nd"),
+            (3, "3rd"),
+            (4, "4th"),
+            (11, "11th"),
+            (12, "12th"),
+            (13, "13th"),
+            (21, "21st"),
+            (22, "22nd"),
+            (23, "23rd"),
+            (101, "101st"),
+            (111, "111th"),
+            (112, "112th"),
+        ] {
+            assert_eq!(ordinal(n), s);
+        }
+    }
+
+    #[test]
+    fn test_cardinal() {
+        assert_eq!(cardinal(0), "zero");
+        assert_eq!(cardinal(3), "three");
+        assert_eq!(cardinal(12), "twelve");
+        assert_eq!(cardinal(13), "13");
+        assert_eq!(cardinal(-1), "-1");
+    }
 }
@@ -1,8 +1,13 @@
-use std::{borrow::Borrow, fmt, fs, path::Path, str::FromStr};
+use std::{
+    borrow::Borrow,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use derive_more::Deref;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 
 /// Read a directory tree into a single IDM outline.
@@ -11,12 +16,21 @@ use serde_with::{DeserializeFromStr, SerializeDisplay};
 /// subdirectories are append headlines and file names are overwrite
 /// headlines, so the output is an `IncrementalOutline`. Use `idm::transmute`
 /// to change it into the type you want.
+///
+/// IDM files can include other files with `!include path` lines. The path is
+/// relative to the including file and the included contents are spliced in
+/// at the indentation of the directive. Files that are only meant to be
+/// included should not use the `.idm` extension so that the directory reader
+/// won't also pick them up on their own.
 pub fn dir_to_idm(path: impl AsRef<Path>) -> Result<IncrementalOutline> {
     use IncrementalHeadline::*;
 
     // If pointed at a file, just read the file.
     if path.as_ref().is_file() {
-        return Ok(idm::from_str(&fs::read_to_string(path)?)?);
+        return Ok(idm::from_str(&read_idm_file(
+            path.as_ref(),
+            &mut Vec::new(),
+        )?)?);
     }
 
     let mut ret = Vec::new();
@@ -46,6 +60,131 @@ pub fn dir_to_idm(path: impl AsRef<Path>) -> Result<IncrementalOutline> {
     Ok(IncrementalOutline(ret))
 }
 
+/// Read an IDM file and expand the `!include` directives in it.
+///
+/// `stack` holds the files currently being included and is used to detect
+/// include cycles.
+fn read_idm_file(path: &Path, stack: &mut Vec<PathBuf>) -> Result<String> {
+    const MAX_INCLUDE_DEPTH: usize = 16;
+
+    let path = path.canonicalize()?;
+    if stack.contains(&path) {
+        bail!("read_idm_file: Include cycle at {path:?}");
+    }
+    if stack.len() >= MAX_INCLUDE_DEPTH {
+        bail!("read_idm_file: Includes nested too deep at {path:?}");
+    }
+
+    let text = fs::read_to_string(&path)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    stack.push(path.clone());
+
+    let mut ret = String::new();
+    for line in text.lines() {
+        let content = line.trim_start();
+        let Some(file) = content.strip_prefix("!include ") else {
+            ret.push_str(line);
+            ret.push('\n');
+            continue;
+        };
+
+        let indent = &line[..line.len() - content.len()];
+        for line in read_idm_file(&dir.join(file.trim()), stack)?.lines() {
+            if !line.trim().is_empty() {
+                ret.push_str(indent);
+            }
+            ret.push_str(line);
+            ret.push('\n');
+        }
+    }
+
+    stack.pop();
+    Ok(ret)
+}
+
+/// Serialize a value into IDM with long lines wrapped to fit in `width`
+/// columns where possible.
+///
+/// See `wrap_idm` for how the wrapping is done.
+pub fn to_idm_wrapped<T: Serialize + DeserializeOwned>(
+    value: &T,
+    width: usize,
+) -> Result<String> {
+    wrap_idm::<T>(&idm::to_string(value)?, width)
+}
+
+/// Rewrap IDM text that deserializes into `T` so that lines stay within
+/// `width` columns where possible.
+///
+/// Lines that run past the width are turned from an inline sequence into an
+/// indented block with one item per line. Whether that's valid depends on
+/// the type being read, so a line is only rewrapped if the text still reads
+/// into the same value afterwards. Lines that can't be rewrapped, like long
+/// strings, are left as they are.
+pub fn wrap_idm<T: Serialize + DeserializeOwned>(
+    text: &str,
+    width: usize,
+) -> Result<String> {
+    fn indent_of(line: &str) -> &str {
+        &line[..line.len() - line.trim_start().len()]
+    }
+
+    let canonical = idm::to_string(&idm::from_str::<T>(text)?)?;
+    let is_same = |lines: &[String]| {
+        idm::from_str::<T>(&lines.join("\n"))
+            .ok()
+            .and_then(|value| idm::to_string(&value).ok())
+            .is_some_and(|s| s == canonical)
+    };
+
+    // Indent blocks the same way the text already does.
+    let unit = text
+        .lines()
+        .map(indent_of)
+        .find(|s| !s.is_empty())
+        .unwrap_or("  ")
+        .to_string();
+
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    // Go backwards so that rewrapping doesn't shift the lines still to be
+    // checked.
+    for i in (0..lines.len()).rev() {
+        let line = &lines[i];
+        if line.chars().count() <= width {
+            continue;
+        }
+
+        // Lines that already have a body can't take in more items.
+        let indent = indent_of(line);
+        if lines.get(i + 1).is_some_and(|next| {
+            !next.trim().is_empty() && indent_of(next).len() > indent.len()
+        }) {
+            continue;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.len() < 2 {
+            continue;
+        }
+
+        let mut block = vec![format!("{indent}{}", words[0])];
+        block.extend(words[1..].iter().map(|w| format!("{indent}{unit}{w}")));
+
+        let mut candidate = lines[..i].to_vec();
+        candidate.extend(block);
+        candidate.extend_from_slice(&lines[i + 1..]);
+        if is_same(&candidate) {
+            lines = candidate;
+        }
+    }
+
+    let mut ret = lines.join("\n");
+    if text.ends_with('\n') {
+        ret.push('\n');
+    }
+    Ok(ret)
+}
+
 /// A wrapper type that converts underscores in serialization to spaces at
 /// runtime.
 ///
@@ -134,6 +273,123 @@ pub struct IncrementalOutline(
     Vec<((IncrementalHeadline,), IncrementalOutline)>,
 );
 
+impl IncrementalOutline {
+    /// Compute a patch that turns `base` into `modified` when added to it.
+    ///
+    /// Top-level sections are matched by headline. Unchanged sections are
+    /// left out, sections that only get new items at the end become append
+    /// sections and other changed sections become overwrite sections.
+    ///
+    /// Patches can't remove or reorder sections, an error is returned if
+    /// `modified` does either to `base`.
+    pub fn diff(base: &Outline, modified: &Outline) -> Result<Self> {
+        use IncrementalHeadline::*;
+
+        // Patching finds the first section with a matching headline.
+        let first_match =
+            |head: &str| base.0.iter().position(|((h,), _)| h == head);
+
+        if modified.0.len() < base.0.len() {
+            bail!("IncrementalOutline::diff: Sections removed");
+        }
+
+        let mut ret = Vec::new();
+        for (i, (((head,), body), ((new_head,), new_body))) in
+            base.0.iter().zip(&modified.0).enumerate()
+        {
+            if head != new_head {
+                bail!("IncrementalOutline::diff: {head:?} removed or moved");
+            }
+
+            if body == new_body {
+                continue;
+            }
+
+            if first_match(head) != Some(i) {
+                bail!("IncrementalOutline::diff: Duplicate section {head:?}");
+            }
+
+            if new_body.0.starts_with(&body.0) {
+                let added = Outline(new_body.0[body.0.len()..].to_vec());
+                ret.push(((Append(head.clone()),), added.into()));
+            } else {
+                ret.push(((Overwrite(head.clone()),), new_body.clone().into()));
+            }
+        }
+
+        for (i, ((head,), body)) in
+            modified.0.iter().enumerate().skip(base.0.len())
+        {
+            // A new section would end up patching an earlier one with the
+            // same headline.
+            if first_match(head).is_some()
+                || modified.0[base.0.len()..i]
+                    .iter()
+                    .any(|((h,), _)| h == head)
+            {
+                bail!("IncrementalOutline::diff: Duplicate section {head:?}");
+            }
+
+            ret.push(((Overwrite(head.clone()),), body.clone().into()));
+        }
+
+        Ok(IncrementalOutline(ret))
+    }
+
+    /// Combine two patches made against the same base into one.
+    ///
+    /// Sections changed by only one of the patches are kept as is. If both
+    /// patches append to the same section, both sets of new items are
+    /// appended. Sections the patches change in other different ways are a
+    /// conflict and return an error.
+    pub fn merge(
+        base: &Outline,
+        a: &IncrementalOutline,
+        b: &IncrementalOutline,
+    ) -> Result<Self> {
+        use IncrementalHeadline::*;
+
+        let normalize = |patch: &IncrementalOutline| {
+            let mut modified = base.clone();
+            modified += patch;
+            IncrementalOutline::diff(base, &modified)
+        };
+
+        let mut ret = normalize(a)?.0;
+        for ((head,), body) in normalize(b)?.0 {
+            match ret.iter_mut().find(|((h,), _)| h.as_ref() == head.as_ref()) {
+                None => ret.push(((head,), body)),
+                Some(((h,), b)) if *h == head && *b == body => {}
+                Some(((Append(_),), b)) if matches!(head, Append(_)) => {
+                    b.0.extend(body.0)
+                }
+                Some(_) => bail!(
+                    "IncrementalOutline::merge: Conflicting changes to {:?}",
+                    head.as_ref()
+                ),
+            }
+        }
+
+        Ok(IncrementalOutline(ret))
+    }
+}
+
+/// Turn a plain outline into an incremental one with only overwrite
+/// headlines.
+impl From<Outline> for IncrementalOutline {
+    fn from(value: Outline) -> Self {
+        IncrementalOutline(
+            value
+                .0
+                .into_iter()
+                .map(|((head,), body)| {
+                    ((IncrementalHeadline::Overwrite(head),), body.into())
+                })
+                .collect(),
+        )
+    }
+}
+
 impl From<IncrementalOutline> for Outline {
     fn from(value: IncrementalOutline) -> Self {
         Outline(
@@ -294,3 +550,153 @@ pub mod dash_option {
             .map(Some)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(s: &str) -> Outline {
+        idm::from_str(s).unwrap()
+    }
+
+    fn patched(base: &Outline, patch: &IncrementalOutline) -> Outline {
+        let mut ret = base.clone();
+        ret += patch;
+        ret
+    }
+
+    const BASE: &str = "\
+items
+  sword
+  axe
+monsters
+  rat
+  orc
+settings
+  normal
+";
+
+    #[test]
+    fn include_files() {
+        let dir = std::env::temp_dir()
+            .join(format!("gametemplate-include-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("monsters.idm"),
+            "animals\n  rat\n  !include undead.inc\n",
+        )
+        .unwrap();
+        fs::write(dir.join("undead.inc"), "zombie\nghost\n").unwrap();
+
+        let data = Outline::from(dir_to_idm(&dir).unwrap());
+        assert_eq!(
+            data,
+            outline("monsters\n  animals\n    rat\n    zombie\n    ghost\n")
+        );
+
+        // Cyclic includes are an error.
+        fs::write(dir.join("undead.inc"), "!include monsters.idm\n").unwrap();
+        assert!(dir_to_idm(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wrapped_idm() {
+        use std::collections::BTreeMap;
+
+        const WIDTH: usize = 40;
+
+        let words: Vec<String> =
+            (0..20).map(|i| format!("word{i:02}")).collect();
+        let mut data: BTreeMap<String, BTreeMap<String, Vec<String>>> =
+            Default::default();
+        for a in ["alpha", "beta"] {
+            for b in ["one", "two", "three"] {
+                data.entry(a.into())
+                    .or_default()
+                    .insert(b.into(), words.clone());
+            }
+        }
+
+        let text = to_idm_wrapped(&data, WIDTH).unwrap();
+        assert!(text.lines().all(|line| line.chars().count() <= WIDTH));
+        assert_eq!(
+            idm::from_str::<BTreeMap<String, BTreeMap<String, Vec<String>>>>(
+                &text
+            )
+            .unwrap(),
+            data
+        );
+
+        // Strings can't be broken up.
+        let long = "a string that will not fit in the width".repeat(3);
+        let text = to_idm_wrapped(&(1, long.clone()), WIDTH).unwrap();
+        assert_eq!(idm::from_str::<(i32, String)>(&text).unwrap(), (1, long));
+    }
+
+    #[test]
+    fn outline_diff() {
+        let base = outline(BASE);
+        let modified = outline(
+            "\
+items
+  sword
+  axe
+  bow
+monsters
+  rat
+  goblin
+settings
+  normal
+spells
+  fireball
+",
+        );
+
+        let diff = IncrementalOutline::diff(&base, &modified).unwrap();
+        assert_eq!(patched(&base, &diff), modified);
+        // Unchanged sections are left out, appends only carry the new items.
+        assert_eq!(
+            diff.to_string(),
+            "items\n  bow\nmonsters\n  rat\n  goblin\nspells\n  fireball\n"
+        );
+
+        assert!(IncrementalOutline::diff(&modified, &base).is_err());
+        assert_eq!(
+            IncrementalOutline::diff(&base, &base).unwrap(),
+            Default::default()
+        );
+    }
+
+    #[test]
+    fn outline_merge() {
+        let base = outline(BASE);
+        let diff = |s| {
+            IncrementalOutline::diff(&base, &outline(&format!("{BASE}{s}")))
+                .unwrap()
+        };
+        let a = diff("spells\n  fireball\n");
+        let b = diff("quests\n  rescue\n");
+
+        let merged = IncrementalOutline::merge(&base, &a, &b).unwrap();
+        assert_eq!(
+            patched(&base, &merged),
+            outline(&format!("{BASE}spells\n  fireball\nquests\n  rescue\n"))
+        );
+
+        // Appends to the same section are combined.
+        let a: IncrementalOutline = idm::from_str("@items\n  bow\n").unwrap();
+        let b: IncrementalOutline = idm::from_str("@items\n  spear\n").unwrap();
+        let merged = IncrementalOutline::merge(&base, &a, &b).unwrap();
+        assert_eq!(
+            patched(&base, &merged),
+            outline(&BASE.replace("axe\n", "axe\n  bow\n  spear\n"))
+        );
+
+        // Overwriting the same section in different ways is a conflict.
+        let a: IncrementalOutline = idm::from_str("monsters\n  rat\n").unwrap();
+        let b: IncrementalOutline = idm::from_str("monsters\n  orc\n").unwrap();
+        assert!(IncrementalOutline::merge(&base, &a, &b).is_err());
+    }
+}
@@ -34,9 +34,21 @@ impl InString {
         InString(INTERNER.lock().unwrap().make(s))
     }
 
+    /// Intern a case-folded version of the string.
+    ///
+    /// Folded strings made from spellings that only differ in case are
+    /// equal.
+    pub fn folded(s: impl AsRef<str>) -> Self {
+        InString::new(s.as_ref().to_lowercase())
+    }
+
     pub fn as_str(&self) -> &str {
         INTERNER.lock().unwrap().get(self.0)
     }
+
+    pub fn eq_ignore_case(&self, other: impl AsRef<str>) -> bool {
+        self.as_str().to_lowercase() == other.as_ref().to_lowercase()
+    }
 }
 
 impl From<&str> for InString {
@@ -190,4 +202,25 @@ mod test {
     fn default() {
         assert_eq!(InString::default().as_str(), "");
     }
+
+    #[test]
+    fn case_folding() {
+        assert_ne!(
+            InString::new("Healing Potion"),
+            InString::new("healing potion")
+        );
+        assert_eq!(
+            InString::folded("Healing Potion"),
+            InString::folded("healing POTION")
+        );
+        assert_eq!(
+            InString::folded("Healing Potion").as_str(),
+            "healing potion"
+        );
+
+        assert!(
+            InString::new("Healing Potion").eq_ignore_case("healing potion")
+        );
+        assert!(!InString::new("Healing Potion").eq_ignore_case("healing"));
+    }
 }
@@ -1,3 +1,6 @@
+use std::str::FromStr;
+
+use anyhow::bail;
 use serde::{Deserialize, Serialize};
 
 #[derive(
@@ -85,6 +88,19 @@ aoeuidhtns-\\
     }
 }
 
+impl FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "qwerty" => Ok(Layout::Qwerty),
+            "colemak" => Ok(Layout::Colemak),
+            "dvorak" => Ok(Layout::Dvorak),
+            _ => bail!("Unknown keyboard layout {s:?}"),
+        }
+    }
+}
+
 /// Try to detect if user has a specific keyboard layout active.
 #[cfg(target_os = "linux")]
 fn is_active(layout_name: &str) -> bool {
@@ -123,4 +139,11 @@ mod test {
             "arst".chars().map(|c| layout.remap_to_qwerty(c)).collect();
         assert_eq!(qwertified, "asdf".to_string());
     }
+
+    #[test]
+    fn parse_layout() {
+        assert_eq!("dvorak".parse::<Layout>().unwrap(), Layout::Dvorak);
+        assert_eq!("Colemak".parse::<Layout>().unwrap(), Layout::Colemak);
+        assert!("azerty".parse::<Layout>().is_err());
+    }
 }
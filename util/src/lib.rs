@@ -12,23 +12,28 @@ mod cloud;
 pub use cloud::Cloud;
 
 mod distribution;
-pub use distribution::{PlottedDistribution, RangeDistribution};
+pub use distribution::{
+    PlottedDistribution, RangeDistribution, WeightedDistribution,
+};
 
 mod geom;
 pub use geom::{
-    a3, bresenham_line, reverse_dir_mask_4, s4, s8, s_hex, v2, v3,
-    wallform_mask, Neighbors2D, Neighbors3D, PlottedPoint, PolyLineIter, Sdf,
-    VecExt, AXIS_DIRS,
+    a3, bresenham_line, bresenham_line_3d, reverse_dir_mask_4, s4, s8, s_hex,
+    v2, v3, wallform_mask, Neighbors2D, Neighbors3D, PlottedPoint,
+    PolyLineIter, Sdf, VecExt, AXIS_DIRS,
 };
 
 mod grammar;
-pub use grammar::{Noun, Sentence};
+pub use grammar::{cardinal, ordinal, Noun, Sentence};
 
 mod grid;
 pub use grid::Grid;
 
 mod idm;
-pub use idm::{_String, dash_option, dir_to_idm, IncrementalOutline, Outline};
+pub use idm::{
+    _String, dash_option, dir_to_idm, to_idm_wrapped, wrap_idm,
+    IncrementalOutline, Outline,
+};
 
 mod interned_string;
 pub use interned_string::InString;
@@ -42,7 +47,7 @@ pub use lazy_res::LazyRes;
 pub mod parse;
 
 mod path;
-pub use path::{bfs, dijkstra_search};
+pub use path::{bfs, dijkstra_map_weighted, dijkstra_search};
 
 mod rng;
 pub use rng::{srng, Odds, RngExt};
@@ -44,6 +44,73 @@ where
     })
 }
 
+/// Generate a shortest paths map according to a neighbors function that
+/// also gives the cost of stepping to each neighbor.
+///
+/// Like `bfs`, but nodes are yielded in order of the total path cost instead
+/// of the number of steps. Use this when some terrain is slower to traverse
+/// than others.
+pub fn dijkstra_map_weighted<'a, T, I>(
+    mut neighbors: impl FnMut(&T) -> I + 'a,
+    starts: impl IntoIterator<Item = T>,
+) -> impl Iterator<Item = (T, usize)> + 'a
+where
+    T: Clone + Eq + Hash + 'a,
+    I: IntoIterator<Item = (T, usize)>,
+{
+    // Heap items are (cost, insertion index, node). The index makes the
+    // ordering total without requiring T: Ord and keeps equal-cost nodes in
+    // FIFO order like in bfs.
+    let mut edge: BinaryHeap<Reverse<(usize, usize, Ordered<T>)>> =
+        Default::default();
+    let mut counter = 0;
+    for s in starts {
+        edge.push(Reverse((0, counter, Ordered(s))));
+        counter += 1;
+    }
+    let mut seen = HashSet::default();
+
+    std::iter::from_fn(move || {
+        while let Some(Reverse((len, _, Ordered(node)))) = edge.pop() {
+            if !seen.contains(&node) {
+                seen.insert(node.clone());
+                for (n, cost) in neighbors(&node) {
+                    if !seen.contains(&n) {
+                        edge.push(Reverse((len + cost, counter, Ordered(n))));
+                        counter += 1;
+                    }
+                }
+                return Some((node, len));
+            }
+        }
+        None
+    })
+}
+
+/// Wrapper that makes any value compare equal so it can ride along in a
+/// heap tuple.
+struct Ordered<T>(T);
+
+impl<T> PartialEq for Ordered<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for Ordered<T> {}
+
+impl<T> PartialOrd for Ordered<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Ordered<T> {
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
 /// Pathfinding result value that lets you trace the path that led to this
 /// node.
 #[derive(Clone, Eq, PartialEq, Deref)]
@@ -158,3 +225,79 @@ where
         None
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::{ivec2, IVec2};
+
+    use super::*;
+    use crate::s4;
+
+    #[test]
+    fn weighted_map_avoids_costly_cells() {
+        // Start at S, the straight route to G goes through the expensive ~
+        // cells, the detour around them is longer in steps but cheaper.
+        //
+        //     ......
+        //     S~~~~G
+        let start = ivec2(0, 1);
+        let goal = ivec2(5, 1);
+        let cost = |p: IVec2| {
+            if p.y == 1 && (1..5).contains(&p.x) {
+                10
+            } else {
+                1
+            }
+        };
+        let in_bounds =
+            |p: IVec2| (0..6).contains(&p.x) && (0..2).contains(&p.y);
+
+        let map: HashMap<IVec2, usize> = dijkstra_map_weighted(
+            |&p: &IVec2| {
+                s4::DIR
+                    .iter()
+                    .map(move |&d| p + d)
+                    .filter(|&q| in_bounds(q))
+                    .map(|q| (q, cost(q)))
+                    .collect::<Vec<_>>()
+            },
+            [start],
+        )
+        .collect();
+
+        // Up, five steps east, down.
+        assert_eq!(map[&goal], 7);
+
+        // Unweighted search goes straight through.
+        let steps: HashMap<IVec2, usize> = bfs(
+            |&p: &IVec2| {
+                s4::DIR
+                    .iter()
+                    .map(move |&d| p + d)
+                    .filter(|&q| in_bounds(q))
+                    .collect::<Vec<_>>()
+            },
+            [start],
+        )
+        .collect();
+        assert_eq!(steps[&goal], 5);
+    }
+
+    #[test]
+    fn weighted_map_yields_in_cost_order() {
+        let costs: Vec<usize> = dijkstra_map_weighted(
+            |&n: &i32| {
+                if n < 10 {
+                    vec![(n + 1, (n as usize % 3) + 1)]
+                } else {
+                    vec![]
+                }
+            },
+            [0],
+        )
+        .map(|(_, c)| c)
+        .collect();
+        assert_eq!(costs.len(), 11);
+        assert!(costs.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
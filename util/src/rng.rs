@@ -79,13 +79,72 @@ impl std::ops::Sub for Odds {
 
 pub trait RngExt {
     fn one_chance_in(&mut self, n: usize) -> bool;
+
+    /// Return true with the probability given by the odds.
+    fn chance(&mut self, odds: Odds) -> bool;
+
+    /// Pick a random item with the likelihood of each item being picked
+    /// proportional to its weight.
+    ///
+    /// Items with zero weight are never picked. Returns `None` if there are
+    /// no items with a positive weight.
+    fn weighted_choice<'a, T>(
+        &mut self,
+        items: &'a [T],
+        weight_fn: impl Fn(&T) -> f64,
+    ) -> Option<&'a T>;
 }
 
-impl<T: Rng + ?Sized> RngExt for T {
+impl<R: Rng + ?Sized> RngExt for R {
     fn one_chance_in(&mut self, n: usize) -> bool {
         if n == 0 {
             return false;
         }
         self.gen_range(0..n) == 0
     }
+
+    fn chance(&mut self, odds: Odds) -> bool {
+        odds.sample(self)
+    }
+
+    fn weighted_choice<'a, T>(
+        &mut self,
+        items: &'a [T],
+        weight_fn: impl Fn(&T) -> f64,
+    ) -> Option<&'a T> {
+        items.choose_weighted(self, weight_fn).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_choice() {
+        let mut rng = srng(&"weights");
+        let items = [("never", 0.0), ("rare", 1.0), ("common", 3.0)];
+
+        let mut counts = [0; 3];
+        for _ in 0..4000 {
+            let (name, _) = rng.weighted_choice(&items, |(_, w)| *w).unwrap();
+            counts[items.iter().position(|(n, _)| n == name).unwrap()] += 1;
+        }
+
+        assert_eq!(counts[0], 0);
+        // Should be around 1000 and 3000.
+        assert!((900..1100).contains(&counts[1]));
+        assert!((2900..3100).contains(&counts[2]));
+
+        let empty: [(&str, f64); 1] = [("never", 0.0)];
+        assert!(rng.weighted_choice(&empty, |(_, w)| *w).is_none());
+    }
+
+    #[test]
+    fn chance() {
+        let mut rng = srng(&"chance");
+        let hits = (0..1000).filter(|_| rng.chance(Odds(0))).count();
+        // Zero log-odds is a coin flip.
+        assert!((450..550).contains(&hits));
+    }
 }
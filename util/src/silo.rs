@@ -151,6 +151,67 @@ impl Silo {
             .collect()
     }
 
+    /// Render the silo as a sequence of pronounceable words.
+    ///
+    /// Each character of the silo becomes one syllable and every three
+    /// syllables make a word, so the seed can be read out like a passphrase.
+    pub fn to_words(&self) -> String {
+        self.0
+            .chars()
+            .map(|c| {
+                let i = idx(c).expect("invalid silo");
+                let (c, v) = (i / VOWELS.len(), i % VOWELS.len());
+                format!(
+                    "{}{}",
+                    CONSONANTS.as_bytes()[c] as char,
+                    VOWELS.as_bytes()[v] as char
+                )
+            })
+            .chunks(3)
+            .into_iter()
+            .map(|word| word.collect::<String>())
+            .join(" ")
+    }
+
+    /// Parse a silo from words generated by `to_words`.
+    ///
+    /// Case, whitespace and punctuation between the syllables are ignored.
+    pub fn from_words(s: &str) -> anyhow::Result<Silo> {
+        let letters: Vec<char> = s
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        let mut ret = String::new();
+        for syllable in letters.chunks(2) {
+            let (Some(c), Some(v)) = (
+                syllable.first().and_then(|&c| CONSONANTS.find(c)),
+                syllable.get(1).and_then(|&v| VOWELS.find(v)),
+            ) else {
+                bail!("not valid silo words: {s:?}");
+            };
+            ret.push(ALPHABET.as_bytes()[c * VOWELS.len() + v] as char);
+        }
+
+        Ok(Silo(ret))
+    }
+
+    /// Parse a seed given by the user, either a word seed from `to_words`
+    /// or arbitrary text turned into a silo with `new`.
+    ///
+    /// Only input made of nothing but letters, spaces and hyphens is read as
+    /// words, so that literal seeds never get silently mangled.
+    pub fn from_seed(s: &str) -> Silo {
+        let is_words = s
+            .chars()
+            .all(|c| c.is_ascii_alphabetic() || matches!(c, ' ' | '-'));
+        match Silo::from_words(s) {
+            Ok(silo) if is_words => silo,
+            _ => Silo::new(s),
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut ret = Vec::new();
 
@@ -268,7 +329,9 @@ impl FromStr for Silo {
             {
                 ret.push(c);
             } else {
-                bail!("not a valid silo: {s:?}");
+                // Not a literal silo, see if it was given as words.
+                return Silo::from_words(s)
+                    .map_err(|_| anyhow::anyhow!("not a valid silo: {s:?}"));
             }
         }
 
@@ -301,6 +364,11 @@ impl quickcheck::Arbitrary for Silo {
 
 pub const ALPHABET: &str = "0123456789ABCDEFGHJKMNPQRTUVWXYZ";
 
+// Syllables for word-encoded silos, each alphabet character maps to one
+// consonant-vowel pair.
+const CONSONANTS: &str = "bdfklmrs";
+const VOWELS: &str = "aiou";
+
 const fn idx(c: char) -> Option<usize> {
     match c as u8 {
         c @ (b'0'..=b'9') => Some(c as usize - 48),
@@ -371,6 +439,30 @@ mod test {
         }
     }
 
+    #[test]
+    fn words() {
+        assert_eq!(Silo::new("").to_words(), "");
+        assert_eq!(Silo::new("01Z").to_words(), "babisu");
+        assert_eq!(Silo::new("0123").to_words(), "babibo bu");
+        assert_eq!(Silo::from_words("Babi-Bo bu").unwrap(), Silo::new("0123"));
+        assert!(Silo::from_words("bab").is_err());
+        assert!(Silo::from_words("zebra").is_err());
+        assert_eq!("babibo bu".parse::<Silo>().unwrap(), Silo::new("0123"));
+        assert_eq!("01-23".parse::<Silo>().unwrap(), Silo::new("0123"));
+        assert!("zebra".parse::<Silo>().is_err());
+
+        assert_eq!(Silo::from_seed("babibo bu"), Silo::new("0123"));
+        assert_eq!(Silo::from_seed("K0U3MA"), Silo::new("K0U3MA"));
+        assert_eq!(Silo::from_seed("rand0m"), Silo::new("rand0m"));
+        assert_eq!(Silo::from_seed("zebra"), Silo::new("zebra"));
+
+        let mut rng = crate::rng::srng(&"words");
+        for len in 0..20 {
+            let seed = Silo::sample(&mut rng, len);
+            assert_eq!(Silo::from_words(&seed.to_words()).unwrap(), seed);
+        }
+    }
+
     #[test]
     fn seeding() {
         // Check that seeding works the same way on all platforms.
@@ -39,14 +39,54 @@ pub enum Block {
     SplatteredRock,
     Grass,
     Glass,
+    /// Open window or grating that can be seen and shot through but not
+    /// walked through.
+    Window,
     /// Rough, unworked mass, drawn as undifferentiated blob.
     Rubble,
 
     Altar,
     Door,
+    /// Door that can only be opened with a matching key.
+    LockedDoor(Lock),
+    /// Door that looks like a wall until it's found.
+    SecretDoor,
 
     Water,
     Magma,
+
+    /// Floor trap that looks like regular floor until it's found.
+    HiddenTrap(TrapKind),
+    /// Floor trap that has been found.
+    Trap(TrapKind),
+}
+
+/// Identifier for matching keys to locked doors.
+#[derive(
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Lock {
+    Copper,
+    Silver,
+    Gold,
+}
+
+/// What happens when a trap is triggered.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub enum TrapKind {
+    Damage,
+    Confusion,
+    Teleport,
 }
 
 use Block::*;
@@ -54,11 +94,62 @@ use Block::*;
 impl Block {
     /// Block is solid matter that can be stood on top of.
     pub fn is_support(self) -> bool {
-        matches!(self, Stone | SplatteredRock | Rubble | Grass | Glass)
+        matches!(
+            self,
+            Stone
+                | SplatteredRock
+                | Rubble
+                | Grass
+                | Glass
+                | HiddenTrap(_)
+                | Trap(_)
+        )
     }
 
     pub fn blocks_sight(self) -> bool {
-        matches!(self, Stone | SplatteredRock | Rubble | Grass | Magma | Door)
+        matches!(
+            self,
+            Stone
+                | SplatteredRock
+                | Rubble
+                | Grass
+                | Magma
+                | Door
+                | LockedDoor(_)
+                | SecretDoor
+        )
+    }
+
+    /// Shots can't fly through the block.
+    pub fn blocks_shot(self) -> bool {
+        !matches!(self, Window)
+    }
+
+    /// Sound can't travel through the block at all.
+    pub fn blocks_sound(self) -> bool {
+        matches!(self, Stone | SplatteredRock | Rubble | Grass | SecretDoor)
+    }
+
+    /// Relative cost of walking on top of the block.
+    ///
+    /// Cost-aware pathfinding will take a longer route around expensive
+    /// ground when the detour costs less in total.
+    pub fn move_cost(self) -> usize {
+        match self {
+            // Go around known traps if at all possible.
+            Trap(_) => 20,
+            Rubble => 3,
+            SplatteredRock => 2,
+            _ => 1,
+        }
+    }
+
+    /// Return the trap in the block, whether it's been found or not.
+    pub fn trap(self) -> Option<TrapKind> {
+        match self {
+            HiddenTrap(t) | Trap(t) => Some(t),
+            _ => None,
+        }
     }
 }
 
@@ -73,13 +164,25 @@ impl TryFrom<char> for Block {
             '§' => Ok(SplatteredRock),
             ';' => Ok(Grass),
             '|' => Ok(Glass),
+            '"' => Ok(Window),
             '%' => Ok(Rubble),
 
             '=' => Ok(Altar),
             '+' => Ok(Door),
+            '1' => Ok(LockedDoor(Lock::Copper)),
+            '2' => Ok(LockedDoor(Lock::Silver)),
+            '3' => Ok(LockedDoor(Lock::Gold)),
+            's' => Ok(SecretDoor),
 
             '~' => Ok(Water),
             '&' => Ok(Magma),
+
+            'd' => Ok(HiddenTrap(TrapKind::Damage)),
+            'c' => Ok(HiddenTrap(TrapKind::Confusion)),
+            't' => Ok(HiddenTrap(TrapKind::Teleport)),
+            'D' => Ok(Trap(TrapKind::Damage)),
+            'C' => Ok(Trap(TrapKind::Confusion)),
+            'T' => Ok(Trap(TrapKind::Teleport)),
             _ => bail!("Bad block {value:?}"),
         }
     }
@@ -93,13 +196,25 @@ impl From<Block> for char {
             SplatteredRock => '§',
             Grass => ';',
             Glass => '|',
+            Window => '"',
             Rubble => '%',
 
             Altar => '=',
             Door => '+',
+            LockedDoor(Lock::Copper) => '1',
+            LockedDoor(Lock::Silver) => '2',
+            LockedDoor(Lock::Gold) => '3',
+            SecretDoor => 's',
 
             Water => '~',
             Magma => '&',
+
+            HiddenTrap(TrapKind::Damage) => 'd',
+            HiddenTrap(TrapKind::Confusion) => 'c',
+            HiddenTrap(TrapKind::Teleport) => 't',
+            Trap(TrapKind::Damage) => 'D',
+            Trap(TrapKind::Confusion) => 'C',
+            Trap(TrapKind::Teleport) => 'T',
         }
     }
 }
@@ -1,5 +1,10 @@
 use std::{
-    collections::BTreeMap, fmt, path::Path, str::FromStr, sync::OnceLock,
+    collections::BTreeMap,
+    fmt,
+    path::Path,
+    ptr,
+    str::FromStr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
 };
 
 use anyhow::{anyhow, bail};
@@ -11,30 +16,103 @@ use serde_with::{DeserializeFromStr, SerializeDisplay};
 use strum::EnumIter;
 use util::{HashMap, IndexMap, LazyRes, StrExt, _String};
 
-use crate::SectorMap;
+use crate::{sector_map::Issue, Lock, SectorMap};
 
-static DATA: OnceLock<Data> = OnceLock::new();
+static DATA: Registry = Registry::new();
 
 /// Load content data from filesystem path.
 pub fn register_data_from(path: impl AsRef<Path>) -> anyhow::Result<()> {
     let data = util::dir_to_idm(path.as_ref())?;
-    register_data(idm::from_str(&data.to_string()).unwrap());
-    Ok(())
+    register_data(idm::from_str(&data.to_string()).unwrap())
 }
 
 /// Register content data directly from value.
-pub fn register_data(data: Data) {
-    match DATA.get() {
-        None => {
-            let _ = DATA.set(data);
-        }
-        Some(x) if x == &data => {
-            log::info!("registering the same gamedata twice, ignored");
+///
+/// Fails if the data refers to things that don't exist.
+pub fn register_data(data: Data) -> anyhow::Result<()> {
+    data.check()?;
+    DATA.register(data);
+    Ok(())
+}
+
+/// Replace the registered content data with new data.
+///
+/// This is a development tool for testing content changes without
+/// restarting the game. Only things that look up data after the reload will
+/// see the new data. Already spawned entities keep the stats they were
+/// spawned with and the world skeleton and generated terrain of a running
+/// game are not rebuilt, start a new game to see those change.
+pub fn reload_data(data: Data) -> anyhow::Result<()> {
+    log::info!("reloading gamedata");
+    data.check()?;
+    DATA.reload(data);
+    Ok(())
+}
+
+/// Number of times data has been registered or reloaded.
+///
+/// Use as part of the key for caches of values derived from data so that
+/// they get invalidated when data is reloaded.
+pub fn data_generation() -> usize {
+    DATA.generation()
+}
+
+/// Global data slot that can have its data swapped out.
+///
+/// Replaced data is leaked, so the `&'static` references handed out to it
+/// remain valid.
+struct Registry {
+    data: AtomicPtr<Data>,
+    generation: AtomicUsize,
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Registry {
+            data: AtomicPtr::new(ptr::null_mut()),
+            generation: AtomicUsize::new(0),
         }
-        _ => {
-            panic!("Tried to register different gamedata when data is already registered");
+    }
+
+    fn get(&self) -> Option<&'static Data> {
+        // SAFETY: Pointer is either null or points to leaked data that is
+        // never freed.
+        unsafe { self.data.load(Ordering::Acquire).as_ref() }
+    }
+
+    fn generation(&self) -> usize {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    fn register(&self, data: Data) {
+        let new = Box::into_raw(Box::new(data));
+        match self.data.compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => {
+                self.generation.fetch_add(1, Ordering::AcqRel);
+            }
+            Err(old) => {
+                // SAFETY: The new pointer was never shared, and the old one
+                // points to leaked data that is never freed.
+                let (new, old) = unsafe { (Box::from_raw(new), &*old) };
+                if *old == *new {
+                    log::info!("registering the same gamedata twice, ignored");
+                } else {
+                    panic!("Tried to register different gamedata when data is already registered");
+                }
+            }
         }
     }
+
+    fn reload(&self, data: Data) {
+        self.data
+            .store(Box::into_raw(Box::new(data)), Ordering::Release);
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
 }
 
 /// Static global game data.
@@ -46,6 +124,11 @@ pub struct Data {
     pub bestiary: IndexMap<_String, Monster>,
     pub armory: IndexMap<_String, Item>,
     pub campaign: BTreeMap<String, Scenario>,
+    /// Conversation trees for NPCs, keyed by NPC name.
+    pub dialogue: IndexMap<_String, Dialogue>,
+    /// Items monsters may leave behind when they die, keyed by monster
+    /// name. One is picked weighted by item rarity.
+    pub loot: IndexMap<_String, Vec<_String>>,
     /// Irregular plural words.
     pub plurals: HashMap<String, String>,
 }
@@ -59,6 +142,20 @@ pub struct Settings {
     pub id: String,
     /// Player-visible full title of the game.
     pub title: String,
+    /// How many turns between autosaves, zero disables autosaving.
+    pub autosave_interval: i64,
+    /// How many turns it takes for a level zero player to heal a wound,
+    /// zero disables passive regeneration.
+    pub regen_interval: i64,
+    /// How many turns the player must go without taking damage before
+    /// regeneration starts.
+    pub regen_delay: i64,
+    /// Sight radius for mobs that don't specify their own, zero uses the
+    /// engine default.
+    pub sight_radius: i32,
+    /// Whether the player puts on upgraded gear for empty slots
+    /// automatically when picking it up.
+    pub auto_equip: bool,
 }
 
 pub fn settings() -> &'static Settings {
@@ -78,6 +175,154 @@ impl Data {
     pub fn get() -> &'static Data {
         Default::default()
     }
+
+    /// Check that names the data refers to exist.
+    ///
+    /// Most references are parsed lazily during play, so a typo would
+    /// otherwise only show up when the game runs into it. Returns every bad
+    /// reference found.
+    pub fn validate(&self) -> Vec<String> {
+        let mut ret = Vec::new();
+
+        let check_pod = |ret: &mut Vec<String>, context: &str, pod: &str| {
+            for name in self.unknown_pod_names(pod) {
+                ret.push(format!("{context}: unknown object {name:?}"));
+            }
+        };
+
+        check_pod(&mut ret, "loadout", &self.loadout.to_string());
+
+        for (name, item) in &self.armory {
+            for power in item.on_hit.iter().chain(&item.power) {
+                if let Power::Summon(obj) = power {
+                    check_pod(
+                        &mut ret,
+                        &format!("armory {}", name.0),
+                        &obj.to_string(),
+                    );
+                }
+            }
+        }
+
+        for (name, items) in &self.loot {
+            if !self.bestiary.contains_key(name) {
+                ret.push(format!("loot: unknown monster {:?}", name.0));
+            }
+            for item in items {
+                check_pod(&mut ret, &format!("loot {}", name.0), item);
+            }
+        }
+
+        for (name, scenario) in &self.campaign {
+            let mut entrances = 0;
+            for ((c,), regions) in &scenario.legend {
+                let mut regions: Vec<&Region> = regions.iter().collect();
+                while let Some(region) = regions.pop() {
+                    match region {
+                        Region::Site(map) | Region::Hall(map) => {
+                            for (k, pod) in &map.legend {
+                                check_pod(
+                                    &mut ret,
+                                    &format!("campaign {name} {c} {k}"),
+                                    &pod.to_string(),
+                                );
+                            }
+
+                            for issue in
+                                map.validate().err().unwrap_or_default()
+                            {
+                                // Nothing gets stacked on top of a site, so
+                                // its upstairs are never connected.
+                                if region.is_site()
+                                    && matches!(
+                                        issue,
+                                        Issue::MisalignedUpstairs(_)
+                                    )
+                                {
+                                    continue;
+                                }
+                                ret.push(format!(
+                                    "campaign {name} {c}: {issue}"
+                                ));
+                            }
+
+                            entrances += map.entrances().count();
+                        }
+                        Region::Branch(rs) => regions.extend(rs),
+                        Region::Repeat(_, r) | Region::Named(_, r) => {
+                            regions.push(r)
+                        }
+                        Region::Generate(_) => {}
+                    }
+                }
+            }
+
+            if entrances != 1 {
+                ret.push(format!(
+                    "campaign {name}: {entrances} player start positions, \
+                     must have exactly one"
+                ));
+            }
+
+            for objective in &scenario.objectives {
+                if let Objective::Retrieve(item) = objective {
+                    if self.unknown_pod_names(item).next().is_some() {
+                        ret.push(format!(
+                            "campaign {name}: unknown item {item:?}"
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (npc, dialogue) in &self.dialogue {
+            if !self.bestiary.contains_key(npc) {
+                ret.push(format!("dialogue: unknown speaker {:?}", npc.0));
+            }
+
+            for (node_name, node) in dialogue {
+                let context = format!("dialogue {} {node_name}", npc.0);
+                for ((_,), choice) in &node.choices {
+                    if let Some(give) = &choice.give {
+                        check_pod(&mut ret, &context, &give.to_string());
+                    }
+                    if let Some(next) = &choice.next {
+                        if !dialogue.contains_key(next) {
+                            ret.push(format!(
+                                "{context}: unknown node {next:?}"
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        ret
+    }
+
+    /// Fail with a list of all bad references if `validate` finds any.
+    fn check(&self) -> anyhow::Result<()> {
+        let errors = self.validate();
+        if !errors.is_empty() {
+            bail!("Invalid gamedata:\n{}", errors.join("\n"));
+        }
+        Ok(())
+    }
+
+    /// Names of objects in a pod string that aren't found in the data.
+    fn unknown_pod_names<'a>(
+        &'a self,
+        pod: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        pod.lines()
+            .map(|line| util::parse::multipliable(line.trim()).1)
+            .filter(|name| !name.is_empty())
+            .filter(|name| {
+                let is_match = |k: &_String| k.eq_ignore_ascii_case(name);
+                !self.bestiary.keys().any(is_match)
+                    && !self.armory.keys().any(is_match)
+            })
+    }
 }
 
 /// A pod is an inert value that can hatch into one or several live runtime
@@ -237,6 +482,23 @@ impl FromStr for PodKind {
             return Ok(PodKind::Item(item));
         }
 
+        // No exact match, see if the name was just capitalized differently.
+        if let Some((_, monster)) = Data::get()
+            .bestiary
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+        {
+            return Ok(PodKind::Monster(monster));
+        }
+
+        if let Some((_, item)) = Data::get()
+            .armory
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+        {
+            return Ok(PodKind::Item(item));
+        }
+
         bail!("Unknown pod kind {s:?}")
     }
 }
@@ -285,6 +547,31 @@ pub struct Scenario {
     // legend serialize with a nice layout when using the tiled-export tool to
     // modify and reserialize scenario data.
     pub legend: Vec<((char,), Vec<Region>)>,
+    /// Objectives that need to be completed to win the scenario. A scenario
+    /// without objectives can't be won.
+    pub objectives: Vec<Objective>,
+}
+
+/// Something the player needs to accomplish.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Objective {
+    /// Kill every boss monster.
+    DefeatBosses,
+    /// Get the player this many levels below the surface.
+    ReachDepth(i32),
+    /// Get the player to carry an item with the given name.
+    Retrieve(String),
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Objective::DefeatBosses => write!(f, "Defeat all bosses"),
+            Objective::ReachDepth(depth) => write!(f, "Reach depth {depth}"),
+            Objective::Retrieve(name) => write!(f, "Retrieve the {name}"),
+        }
+    }
 }
 
 impl Scenario {
@@ -336,6 +623,8 @@ pub enum Region {
     Branch(Vec<Region>),
     /// A sequence of applying the same constructor multiple times.
     Repeat(u32, Box<Region>),
+    /// Give every level of the inner region a name shown to the player.
+    Named(_String, Box<Region>),
 }
 
 impl Region {
@@ -344,7 +633,7 @@ impl Region {
     pub fn is_site(&self) -> bool {
         match self {
             Region::Site(_) => true,
-            Region::Repeat(_, r) => r.is_site(),
+            Region::Repeat(_, r) | Region::Named(_, r) => r.is_site(),
             _ => false,
         }
     }
@@ -352,7 +641,7 @@ impl Region {
     pub fn is_prefab(&self) -> bool {
         match self {
             Region::Site(_) | Region::Hall(_) => true,
-            Region::Repeat(_, a) => a.is_prefab(),
+            Region::Repeat(_, a) | Region::Named(_, a) => a.is_prefab(),
             _ => false,
         }
     }
@@ -360,6 +649,7 @@ impl Region {
     pub fn height(&self) -> i32 {
         match self {
             Region::Repeat(n, a) => *n as i32 * a.height(),
+            Region::Named(_, a) => a.height(),
             Region::Branch(_) => 0,
             _ => 1,
         }
@@ -369,6 +659,7 @@ impl Region {
     pub fn count(&self) -> u32 {
         match self {
             Region::Repeat(n, inner) => n * inner.count(),
+            Region::Named(_, inner) => inner.count(),
             // Branches go off to the side so they don't add to count.
             Region::Branch(_) => 0,
             _ => 1,
@@ -378,6 +669,7 @@ impl Region {
     pub fn fixed_upstairs(&self) -> Option<IVec2> {
         match self {
             Region::Site(a) | Region::Hall(a) => a.find_upstairs(),
+            Region::Named(_, a) => a.fixed_upstairs(),
             _ => None,
         }
     }
@@ -391,6 +683,8 @@ pub enum GenericSector {
     Forest,
     Mountains,
     Dungeon,
+    /// Dungeon of rectangular rooms from binary space partitioning.
+    Bsp,
 }
 
 #[derive(
@@ -404,6 +698,13 @@ pub struct Monster {
     pub attack_damage: i32,
     pub rarity: u32,
     pub min_depth: u32,
+    /// How many times per turn the monster gets to move, zero means normal
+    /// speed.
+    pub speed: i8,
+    /// Width of the monster's square footprint in cells.
+    pub size: i32,
+    /// How far the monster can see, zero means the default sight radius.
+    pub sight: i32,
     pub flags: MonsterFlags,
 }
 
@@ -416,6 +717,27 @@ bitflags::bitflags! {
 
         /// Monster explodes when killed.
         const EXPLODES = 1 << 1;
+
+        /// Monster heals a wound every turn.
+        const REGENERATES = 1 << 2;
+
+        /// Monster doesn't care about the ground it's moving over.
+        const FLIES = 1 << 3;
+
+        /// Monster can move through solid walls.
+        const INCORPOREAL = 1 << 4;
+
+        /// Monster is a peaceful shopkeeper that trades its inventory.
+        const MERCHANT = 1 << 5;
+
+        /// Monster doesn't take part in fights.
+        const PEACEFUL = 1 << 6;
+
+        /// Monster leaves an edible corpse when killed.
+        const CORPSE = 1 << 7;
+
+        /// Monster leaves a corpse that poisons whoever eats it.
+        const POISONOUS = 1 << 8;
     }
 }
 
@@ -438,6 +760,14 @@ pub struct Item {
     pub kind: ItemKind,
     pub rarity: u32,
 
+    /// Doors this item opens if it's a key.
+    #[serde(with = "util::dash_option")]
+    pub lock: Option<Lock>,
+
+    /// Power a weapon invokes on the target of every melee hit.
+    #[serde(with = "util::dash_option")]
+    pub on_hit: Option<Power>,
+
     #[serde(with = "util::dash_option")]
     pub power: Option<Power>,
 }
@@ -452,6 +782,35 @@ impl SpawnDist for Item {
     }
 }
 
+/// Conversation tree with an NPC.
+///
+/// Nodes are keyed by name and the conversation starts from the first node.
+pub type Dialogue = IndexMap<String, DialogueNode>;
+
+#[derive(Clone, Default, Eq, PartialEq, Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DialogueNode {
+    /// What the NPC says.
+    pub text: String,
+    /// Responses the player can pick, conversation ends after the node if
+    /// there are none.
+    pub choices: Vec<((String,), Choice)>,
+}
+
+/// Player response in a conversation and its effects.
+#[derive(Clone, Default, Eq, PartialEq, Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Choice {
+    /// Node to continue to, conversation ends if there's none.
+    pub next: Option<String>,
+    /// Scenario flag to raise when this is picked.
+    pub set_flag: Option<String>,
+    /// Items the NPC gives to the player when this is picked.
+    ///
+    /// Lazy because pods can only be parsed once data has been registered.
+    pub give: Option<LazyRes<Pod>>,
+}
+
 #[derive(
     Copy,
     Clone,
@@ -471,11 +830,16 @@ pub enum ItemKind {
     None,
     MeleeWeapon,
     RangedWeapon,
+    /// Ammunition used up when firing ranged weapons.
+    Ammo,
     Armor,
     Ring,
     Scroll,
     Potion,
     Treasure,
+    Key,
+    /// Remains of a dead monster that can be eaten.
+    Corpse,
 }
 
 impl ItemKind {
@@ -497,17 +861,20 @@ impl ItemKind {
             None => 'X',
             MeleeWeapon => ')',
             RangedWeapon => ')',
+            Ammo => '(',
             Armor => '[',
             Ring => '°',
             Scroll => '?',
             Potion => '!',
             Treasure => '$',
+            Key => '-',
+            Corpse => '%',
         }
     }
 
     pub fn is_stacking(&self) -> bool {
         use ItemKind::*;
-        matches!(self, Scroll | Potion | Treasure)
+        matches!(self, Ammo | Scroll | Potion | Treasure)
     }
 }
 
@@ -545,13 +912,28 @@ pub enum Power {
     Fireball,
     MagicMapping,
     HealSelf,
+    /// Raise the enchantment of the wielded weapon.
+    EnchantWeapon,
+    /// Push the target away.
+    Knockback,
     Summon(LazyRes<PodObject>),
+    /// Speed up the user for a while.
+    Haste,
+    /// Slow down the target for a while.
+    Slow,
+    /// Move the user to a random spot in the current sector.
+    Teleport,
+    /// Move the target to a random spot in the current sector.
+    TeleportOther,
 }
 
 impl Power {
     pub fn needs_aim(self) -> bool {
         use Power::*;
-        matches!(self, Confusion | Fireball)
+        matches!(
+            self,
+            Confusion | Fireball | Knockback | Slow | TeleportOther
+        )
     }
 }
 
@@ -566,4 +948,61 @@ mod test {
         register_data_from("../data").unwrap();
         assert!(!Data::get().bestiary.is_empty());
     }
+
+    #[test]
+    fn dangling_references() {
+        let mut data = Data::default();
+        data.bestiary
+            .insert(_String("goblin".into()), Monster::default());
+        data.armory.insert(
+            _String("scroll of summon goblin".into()),
+            Item {
+                power: Some(Power::Summon(LazyRes::new("goblin".into()))),
+                ..Default::default()
+            },
+        );
+        data.armory.insert(
+            _String("scroll of summon gobbo".into()),
+            Item {
+                power: Some(Power::Summon(LazyRes::new("gobbo".into()))),
+                ..Default::default()
+            },
+        );
+
+        let errors = data.validate();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("scroll of summon gobbo"));
+        assert!(errors[0].contains("\"gobbo\""));
+
+        // Bad data never gets registered.
+        let err = register_data(data).unwrap_err().to_string();
+        assert!(err.contains("gobbo"));
+    }
+
+    #[test]
+    fn reload() {
+        // Use a local registry so the global data other tests use stays put.
+        let registry = Registry::new();
+        assert!(registry.get().is_none());
+
+        let mut data = Data::default();
+        data.bestiary.insert(
+            _String("goblin".into()),
+            Monster {
+                level: 1,
+                ..Default::default()
+            },
+        );
+        registry.register(data.clone());
+        let old = registry.get().unwrap();
+        let generation = registry.generation();
+
+        data.bestiary[0].level = 5;
+        registry.reload(data);
+        assert_eq!(registry.get().unwrap().bestiary["goblin"].level, 5);
+        assert!(registry.generation() > generation);
+
+        // Data from before the reload can still be read.
+        assert_eq!(old.bestiary["goblin"].level, 1);
+    }
 }
@@ -2,13 +2,14 @@ mod atlas;
 pub use atlas::{Atlas, AtlasKey, BitAtlas};
 
 mod block;
-pub use block::{Block, Terrain, Tile, Voxel};
+pub use block::{Block, Lock, Terrain, Tile, TrapKind, Voxel};
 
 mod data;
 pub use data::{
-    register_data, register_data_from, settings, Data, EquippedAt, Item,
-    ItemKind, Monster, MonsterFlags, Pod, PodKind, PodObject, Power, Region,
-    Scenario, Settings, SpawnDist,
+    data_generation, register_data, register_data_from, reload_data, settings,
+    Choice, Data, Dialogue, DialogueNode, EquippedAt, Item, ItemKind, Monster,
+    MonsterFlags, Objective, Pod, PodKind, PodObject, Power, Region, Scenario,
+    Settings, SpawnDist,
 };
 
 mod location;
@@ -19,7 +20,7 @@ pub mod mapgen;
 pub use mapgen::{Lot, MapGenerator, Patch};
 
 pub mod sector_map;
-pub use sector_map::SectorMap;
+pub use sector_map::{terrain_cell, SectorMap};
 
 mod waypoints;
 
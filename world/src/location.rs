@@ -7,7 +7,9 @@ use anyhow::bail;
 use glam::{ivec3, IVec2, IVec3};
 use util::{a3, s4, wallform_mask, Cloud, Neighbors2D};
 
-use crate::{Block, Cube, Tile, Voxel, Zone, SECTOR_HEIGHT, SECTOR_WIDTH};
+use crate::{
+    Block, Cube, Tile, TrapKind, Voxel, Zone, SECTOR_HEIGHT, SECTOR_WIDTH,
+};
 
 pub type Location = IVec3;
 
@@ -34,7 +36,18 @@ pub trait Coordinates:
     /// Look for the valid neighboring floor adjacent to current location.
     ///
     /// Can step up or down one Z level. Returns `None` if terrain is blocked.
+    ///
+    /// Diagonal steps can't squeeze between two walls, the same way such
+    /// wall corners are treated as closed when showing what's been seen.
     fn walk_step(&self, r: &impl Environs, dir: IVec2) -> Option<Self> {
+        if dir.x != 0
+            && dir.y != 0
+            && (*self + ivec3(dir.x, 0, 0)).tile(r).is_wall()
+            && (*self + ivec3(0, dir.y, 0)).tile(r).is_wall()
+        {
+            return None;
+        }
+
         let loc = *self + dir.extend(0);
         [loc.above(), loc, loc.below()]
             .into_iter()
@@ -78,7 +91,7 @@ pub trait Coordinates:
     /// Location is traversable space immediately above a support block.
     fn can_be_stood_in(&self, r: &impl Environs) -> bool {
         matches!(self.voxel(r), None | Some(Block::Door))
-            && self.below().voxel(r).map_or(false, |b| b.is_support())
+            && self.below().voxel(r).is_some_and(|b| b.is_support())
     }
 
     /// Return the pseudo-2D tile for terrain at given location.
@@ -107,8 +120,7 @@ pub trait Coordinates:
             .iter()
             .enumerate()
             .map(|(i, &d)| {
-                if self.walk_step(r, d).map_or(false, |loc| loc.z() > self.z())
-                {
+                if self.walk_step(r, d).is_some_and(|loc| loc.z() > self.z()) {
                     1 << i
                 } else {
                     0
@@ -123,8 +135,7 @@ pub trait Coordinates:
             .iter()
             .enumerate()
             .map(|(i, &d)| {
-                if self.walk_step(r, d).map_or(false, |loc| loc.z() < self.z())
-                {
+                if self.walk_step(r, d).is_some_and(|loc| loc.z() < self.z()) {
                     1 << i
                 } else {
                     0
@@ -133,6 +144,11 @@ pub trait Coordinates:
             .sum()
     }
 
+    /// Cost of walking into this location based on the ground it stands on.
+    fn move_cost(&self, r: &impl Environs) -> usize {
+        self.below().voxel(r).map_or(1, |b| b.move_cost())
+    }
+
     fn is_impassable(&self, r: &impl Environs) -> bool {
         match self.tile(r) {
             Tile::Void => true,
@@ -156,7 +172,7 @@ pub trait Coordinates:
         let is_transparent = |loc: &Self| {
             matches!(
                 loc.voxel(r),
-                None | Some(Block::Altar) | Some(Block::Glass)
+                None | Some(Block::Altar | Block::Glass | Block::Window)
             )
         };
 
@@ -412,11 +428,26 @@ impl Coordinates for Location {
                 r.set_voxel(*self, Some(Door));
                 r.set_voxel(self.below(), Some(Stone));
             }
+            'S' => {
+                r.set_voxel(self.above(), Some(Stone));
+                r.set_voxel(*self, Some(SecretDoor));
+                r.set_voxel(self.below(), Some(Stone));
+            }
+            '1' | '2' | '3' => {
+                r.set_voxel(self.above(), Some(Stone));
+                r.set_voxel(*self, Some(Block::try_from(c)?));
+                r.set_voxel(self.below(), Some(Stone));
+            }
             '|' => {
                 r.set_voxel(self.above(), Some(Stone));
                 r.set_voxel(*self, Some(Glass));
                 r.set_voxel(self.below(), Some(Stone));
             }
+            '"' => {
+                r.set_voxel(self.above(), Some(Stone));
+                r.set_voxel(*self, Some(Window));
+                r.set_voxel(self.below(), Some(Stone));
+            }
             '.' => {
                 r.set_voxel(*self, None);
                 r.set_voxel(self.below(), Some(Stone));
@@ -438,6 +469,21 @@ impl Coordinates for Location {
                 r.set_voxel(*self, Some(Stone));
                 r.set_voxel(self.below(), Some(Stone));
             }
+            '^' => {
+                r.set_voxel(*self, None);
+                r.set_voxel(self.below(), Some(HiddenTrap(TrapKind::Damage)));
+            }
+            ':' => {
+                r.set_voxel(*self, None);
+                r.set_voxel(
+                    self.below(),
+                    Some(HiddenTrap(TrapKind::Confusion)),
+                );
+            }
+            '*' => {
+                r.set_voxel(*self, None);
+                r.set_voxel(self.below(), Some(HiddenTrap(TrapKind::Teleport)));
+            }
             _ => bail!("Unknown terrain {c:?}"),
         };
 
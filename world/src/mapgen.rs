@@ -1,18 +1,18 @@
-use std::sync::Arc;
+use std::{ops::Range, sync::Arc};
 
 use derive_more::{Deref, DerefMut};
 use glam::{ivec3, IVec2, IVec3};
 use memoize::memoize;
 use rand::{distributions::Distribution, seq::SliceRandom, Rng, RngCore};
 use util::{
-    a3, v3, Cloud, HashMap, HashSet, IndexMap, IndexSet, Neighbors2D, Silo,
-    _String,
+    a3, v3, Cloud, HashMap, HashSet, IndexMap, IndexSet, Neighbors2D, RngExt,
+    Silo, StrExt, _String,
 };
 
 use crate::{
-    data::GenericSector, world, Block, Coordinates, Cube, Data, Environs, Item,
-    Level, Location, Monster, Pod, PodObject, SectorMap, SpawnDist, Voxel,
-    Zone, SECTOR_HEIGHT, SECTOR_WIDTH,
+    data::GenericSector, data_generation, world, Block, Coordinates, Cube,
+    Data, Environs, Item, Level, Location, Lock, Monster, Pod, PodObject,
+    SectorMap, SpawnDist, TrapKind, Voxel, Zone, SECTOR_HEIGHT, SECTOR_WIDTH,
 };
 
 pub trait MapGenerator {
@@ -28,6 +28,11 @@ pub trait MapGenerator {
     fn has_waypoint(&self) -> bool {
         false
     }
+
+    /// Number of boss monsters the generator will place.
+    fn num_bosses(&self) -> usize {
+        0
+    }
 }
 
 impl MapGenerator for Patch {
@@ -43,16 +48,16 @@ impl MapGenerator for Patch {
 
         // Defeated bosses will spawn an altar, so if this sector contains a
         // boss, mark it as having an altar.
-        if self
-            .spawns
+        self.num_bosses() > 0
+    }
+
+    fn num_bosses(&self) -> usize {
+        self.spawns
             .values()
             .flat_map(|pod| pod.objects())
-            .any(|obj| obj.is_boss())
-        {
-            return true;
-        }
-
-        false
+            .filter(|obj| obj.is_boss())
+            .map(|obj| obj.count as usize)
+            .sum()
     }
 }
 
@@ -73,6 +78,7 @@ impl MapGenerator for GenericSector {
             Forest => todo!(),
             Mountains => todo!(),
             Dungeon => rooms_and_corridors(rng, lot, 0.1, 0.1, 0.1, 0.0),
+            Bsp => bsp_rooms(rng, lot),
         }
     }
 }
@@ -97,13 +103,14 @@ pub struct Lot {
     pub down: Option<Location>,
 }
 
-// Currently only using EAST and SOUTH as visible edge to other sectors is on
-// the outer rectangle edge
+// The corridor lattice runs along the north and west edges of the lot, while
+// the east and south edges are solid walls that get opened towards
+// connected neighbors.
 
-//const NORTH: u8 = 0b1;
+const NORTH: u8 = 0b1;
 const EAST: u8 = 0b10;
 const SOUTH: u8 = 0b100;
-//const WEST: u8 = 0b1000;
+const WEST: u8 = 0b1000;
 
 impl Default for Lot {
     fn default() -> Self {
@@ -155,16 +162,16 @@ impl Lot {
 
     pub fn exit(&self, idx: usize) -> Option<Location> {
         let min = v3(self.volume.min());
-        let mid_x = min.x + SECTOR_WIDTH / 4 * 2;
-        let mid_y = min.y + SECTOR_HEIGHT / 4 * 2;
+        let mid_x = SECTOR_WIDTH / 4 * 2;
+        let mid_y = SECTOR_HEIGHT / 4 * 2;
 
         match idx {
-            0 => (self.sides & 0b1 != 0).then_some(min + ivec3(mid_x, 0, 0)),
-            1 => (self.sides & 0b10 != 0)
+            0 => (self.sides & NORTH != 0).then_some(min + ivec3(mid_x, 0, 0)),
+            1 => (self.sides & EAST != 0)
                 .then_some(min + ivec3(SECTOR_WIDTH - 1, mid_y, 0)),
-            2 => (self.sides & 0b100 != 0)
+            2 => (self.sides & SOUTH != 0)
                 .then_some(min + ivec3(mid_x, SECTOR_HEIGHT - 1, 0)),
-            3 => (self.sides & 0b1000 != 0).then_some(min + ivec3(0, mid_y, 0)),
+            3 => (self.sides & WEST != 0).then_some(min + ivec3(0, mid_y, 0)),
             _ => panic!("Bad exit dir {idx}"),
         }
     }
@@ -251,6 +258,15 @@ pub fn rooms_and_corridors(
     // How many times to run the smoothing cellular automaton for caves.
     const CAVE_CYCLES: usize = 2;
 
+    // Probability of generating a treasure vault.
+    const VAULT_CHANCE: f64 = 0.25;
+
+    // How many items to put in a vault.
+    const VAULT_LOOT: usize = 3;
+
+    // Probability of a vault door being locked.
+    const LOCKED_VAULT_CHANCE: f64 = 0.5;
+
     assert!((0.0..=1.0).contains(&roominess));
     assert!((0.0..=1.0).contains(&loopiness));
     assert!((0.0..=1.0).contains(&maziness));
@@ -401,6 +417,71 @@ pub fn rooms_and_corridors(
         .expect("Failed to place down stairwell");
     }
 
+    // Place a vault before the regular rooms fill up the map. The vault
+    // only connects to the rest of the map through its door, so make sure
+    // the door opens into a corridor cell.
+    let mut vault = None;
+    if rng.gen_bool(VAULT_CHANCE) {
+        let room = SectorMap::vault().oriented(rng.gen_range(0..8));
+        let (border, inside) = room.border_and_inside();
+        let door = room
+            .map
+            .char_grid()
+            .find_map(|(p, c)| (c == '+').then_some(p))
+            .expect("Vault has no door");
+        let entrance = door
+            .ns_4()
+            .find(|p| !border.contains_key(p) && !inside.contains_key(p))
+            .expect("Vault door is not on edge");
+
+        for _ in 0..32 {
+            let mut loc: IVec3 = floor.sample(rng);
+            loc.x = loc.x / 2 * 2 + 1;
+            loc.y = loc.y / 2 * 2 + 1;
+
+            let entrance = loc + entrance.extend(0);
+            if !floor.contains(entrance) || plan.contains_key(&entrance) {
+                continue;
+            }
+
+            // Don't share walls with anything.
+            if border
+                .keys()
+                .any(|p| plan.contains_key(&(loc + p.extend(0))))
+            {
+                continue;
+            }
+
+            if place_room(
+                &mut ret,
+                &mut plan,
+                &mut regions,
+                &mut region_idx,
+                loc,
+                &border,
+                &inside,
+            )
+            .is_some()
+            {
+                // Build the walls right away so nothing else can dig in.
+                for (p, &c) in border.iter().filter(|(_, &c)| c == '#') {
+                    (loc + p.extend(0))
+                        .apply_char_terrain(&mut ret.terrain, c)
+                        .expect("Bad SectorMap");
+                }
+
+                vault = Some((
+                    loc + door.extend(0),
+                    inside
+                        .keys()
+                        .map(|p| loc + p.extend(0))
+                        .collect::<Vec<_>>(),
+                ));
+                break;
+            }
+        }
+    }
+
     // Generate rooms.
     let mut room_fill = (floor.volume() as f32 * roominess) as i32;
     let mut room_failure_budget = 10;
@@ -549,12 +630,46 @@ pub fn rooms_and_corridors(
         }
     }
 
+    // North and west edges are corridor cells, open more links from them
+    // into the interior instead.
+    if lot.sides & NORTH != 0 {
+        let min = v3(floor.min());
+        for x in (0..SECTOR_WIDTH).step_by(2) {
+            let p1 = min + ivec3(x, 0, 0);
+            let p2 = min + ivec3(x, 1, 0);
+
+            if !plan.contains_key(&p1) && !plan.contains_key(&p2) {
+                extra_edges.push(p2);
+            }
+        }
+    }
+
+    if lot.sides & WEST != 0 {
+        let min = v3(floor.min());
+        for y in (0..SECTOR_HEIGHT).step_by(2) {
+            let p1 = min + ivec3(0, y, 0);
+            let p2 = min + ivec3(1, y, 0);
+
+            if !plan.contains_key(&p1) && !plan.contains_key(&p2) {
+                extra_edges.push(p2);
+            }
+        }
+    }
+
     extra_edges.shuffle(rng);
 
+    // Loops aren't needed for connectivity, so some of them can be made
+    // into secret passages.
+    const SECRET_DOOR_CHANCE: usize = 4;
+
     let n_loops = (extra_edges.len() as f32 * loopiness) as usize;
     for p in extra_edges.iter().take(n_loops) {
         if plan.get(p) == Some(&Doorway) {
-            ret.set_voxel(*p, Some(Block::Door));
+            if rng.one_chance_in(SECRET_DOOR_CHANCE) {
+                ret.set_voxel(*p, Some(Block::SecretDoor));
+            } else {
+                ret.set_voxel(*p, Some(Block::Door));
+            }
         } else {
             ret.set_voxel(*p, None);
         }
@@ -613,6 +728,12 @@ pub fn rooms_and_corridors(
             if lot.sides & SOUTH == 0 && p.y == floor.max()[1] - 1 {
                 return None;
             }
+            if lot.sides & WEST == 0 && p.x == floor.min()[0] {
+                return None;
+            }
+            if lot.sides & NORTH == 0 && p.y == floor.min()[1] {
+                return None;
+            }
 
             Some(p)
         })
@@ -706,15 +827,306 @@ pub fn rooms_and_corridors(
         }
     }
 
-    // Spawn creatures and items in open spots.
-    let mut spawn_posns = ret
+    // Make sure everything is reachable from the entrance. The digging
+    // stages should leave things connected, but cave erosion can open
+    // pockets that nothing else reaches.
+    let start = lot
+        .up
+        .map(|up| up + ivec3(0, 1, -1))
+        .or_else(|| (0..4).find_map(|i| lot.exit(i)))
+        .or_else(|| {
+            ret.terrain
+                .keys()
+                .map(|&p| v3(p))
+                .find(|&p| p.z == z && is_open(&ret, p))
+        });
+    if let Some(start) = start {
+        connect_regions(&mut ret, lot, &plan, start);
+        debug_assert!(
+            unreachable_cells(&ret, start).is_empty(),
+            "rooms_and_corridors: disconnected map"
+        );
+    }
+
+    let depth = 0.max(-lot.volume.min()[2]) as u32;
+
+    if let Some((door, mut inside)) = vault {
+        // Set up the door properly whether it was dug by the corridor
+        // connector or not.
+        door.apply_char_terrain(&mut ret.terrain, '+')
+            .expect("Bad door");
+
+        // Lock some vaults and leave the key lying somewhere outside.
+        if rng.gen_bool(LOCKED_VAULT_CHANCE) {
+            let lock = *[Lock::Copper, Lock::Silver, Lock::Gold]
+                .choose(rng)
+                .unwrap();
+            let key_pos = ret
+                .terrain
+                .iter()
+                .filter(|(p, v)| p[2] == z && v.is_none())
+                .map(|(&p, _)| v3(p))
+                .filter(|p| !inside.contains(p) && !ret.spawns.contains_key(p))
+                .collect::<Vec<_>>()
+                .choose(rng)
+                .copied();
+            if let (Some(pos), Some(key)) = (key_pos, vault_key(lock)) {
+                ret.set_voxel(door, Some(Block::LockedDoor(lock)));
+                ret.spawns.insert(pos, key);
+            }
+        }
+
+        // Guardian stands next to the door, the loot is behind it.
+        inside.sort_by_key(|p| (*p - door).abs().element_sum());
+        let mut inside = inside.into_iter();
+        if let (Some(pos), Some(mob)) =
+            (inside.next(), vault_guardian(rng, depth))
+        {
+            ret.spawns.insert(pos, mob);
+        }
+
+        let mut inside = inside.collect::<Vec<_>>();
+        inside.shuffle(rng);
+        for pos in inside.into_iter().take(VAULT_LOOT) {
+            let Some(item) = vault_loot(rng) else { break };
+            ret.spawns.insert(pos, item);
+        }
+    }
+
+    place_traps(rng, lot, &mut ret);
+    populate(rng, lot, &mut ret);
+
+    Ok(ret)
+}
+
+/// Map generator that recursively partitions the lot into rectangular
+/// leaves, places a room in each leaf and connects sibling partitions with
+/// corridors.
+pub fn bsp_rooms(rng: &mut dyn RngCore, lot: &Lot) -> anyhow::Result<Patch> {
+    let floor = lot.volume.floor();
+    let z = floor.min()[2];
+
+    let mut ret = Patch::default();
+
+    // Cells that rooms and corridors must leave alone.
+    let mut plan: HashMap<Location, Designation> = HashMap::default();
+
+    for dir in 0..4 {
+        if let Some(exit) = lot.exit(dir) {
+            ret.set_voxel(exit, None);
+            plan.insert(exit, Designation::Exit);
+        }
+    }
+
+    // Stairwells go in at the same spots as with `rooms_and_corridors`.
+    let stairwells = [
+        lot.up
+            .map(|up| (up + ivec3(-1, -1, -1), SectorMap::upstairs())),
+        lot.down
+            .map(|down| (down + ivec3(-1, -1, 1), SectorMap::downstairs())),
+    ];
+    for (loc, room) in stairwells.into_iter().flatten() {
+        let (border, inside) = room.border_and_inside();
+        for (p, &c) in border.iter() {
+            let loc = loc + p.extend(0);
+            if c == '.' {
+                ret.set_voxel(loc, None);
+            }
+            plan.insert(loc, Designation::Fixed);
+        }
+        for (p, &c) in inside.iter() {
+            let loc = loc + p.extend(0);
+            loc.apply_char_terrain(&mut ret.terrain, c)
+                .expect("Bad SectorMap");
+            plan.insert(loc, Designation::Fixed);
+        }
+    }
+
+    // Leave the outermost cells of the lot as walls.
+    let [x0, y0, _] = floor.min();
+    let [x1, y1, _] = floor.max();
+    let area = Cube::new([x0 + 1, y0 + 1, z], [x1 - 1, y1 - 1, z + 1]);
+
+    let mut leaves = Vec::new();
+    let mut siblings = Vec::new();
+    bsp_partition(rng, area, &mut leaves, &mut siblings);
+
+    // Place rooms, leave the last row and column of each leaf undug so
+    // there's always a wall between neighboring rooms.
+    let rooms: Vec<Option<Cube>> = leaves
+        .iter()
+        .map(|leaf| {
+            let [lw, lh, _] = leaf.dim();
+            let w = rng.gen_range(BSP_MIN_ROOM..lw);
+            let h = rng.gen_range(BSP_MIN_ROOM..lh);
+            let x = leaf.min()[0] + rng.gen_range(0..lw - w);
+            let y = leaf.min()[1] + rng.gen_range(0..lh - h);
+            let room = Cube::new([x, y, z], [x + w, y + h, z + 1]);
+
+            // Drop rooms that would run into a stairwell.
+            if room
+                .grow([1, 1, 0], [1, 1, 0])
+                .into_iter()
+                .any(|p| plan.get(&v3(p)) == Some(&Designation::Fixed))
+            {
+                return None;
+            }
+
+            for p in room {
+                ret.set_voxel(v3(p), None);
+            }
+            Some(room)
+        })
+        .collect();
+
+    // Connect a random room from each side of every split.
+    for [a, b] in siblings {
+        let a = rooms[a].iter().flatten().collect::<Vec<_>>();
+        let b = rooms[b].iter().flatten().collect::<Vec<_>>();
+        let (Some(a), Some(b)) = (a.choose(rng), b.choose(rng)) else {
+            continue;
+        };
+        let a: Location = a.sample(rng);
+        let b: Location = b.sample(rng);
+
+        // L-shaped corridor, randomly horizontal or vertical leg first.
+        let corner = if rng.gen::<bool>() {
+            ivec3(b.x, a.y, z)
+        } else {
+            ivec3(a.x, b.y, z)
+        };
+        for (p1, p2) in [(a, corner), (corner, b)] {
+            let d = (p2 - p1).signum();
+            let mut p = p1;
+            loop {
+                if !plan.contains_key(&p) {
+                    ret.set_voxel(p, None);
+                }
+                if p == p2 {
+                    break;
+                }
+                p += d;
+            }
+        }
+    }
+
+    // Hook up stairwells, exits and any rooms that ended up with no
+    // corridor.
+    let start = lot
+        .up
+        .map(|up| up + ivec3(0, 1, -1))
+        .or_else(|| (0..4).find_map(|i| lot.exit(i)))
+        .or_else(|| {
+            ret.terrain
+                .keys()
+                .map(|&p| v3(p))
+                .find(|&p| p.z == z && is_open(&ret, p))
+        });
+    if let Some(start) = start {
+        connect_regions(&mut ret, lot, &plan, start);
+        debug_assert!(
+            unreachable_cells(&ret, start).is_empty(),
+            "bsp_rooms: disconnected map"
+        );
+    }
+
+    place_traps(rng, lot, &mut ret);
+    populate(rng, lot, &mut ret);
+
+    Ok(ret)
+}
+
+/// Smallest room dimension for `bsp_rooms`.
+const BSP_MIN_ROOM: i32 = 3;
+
+/// Smallest partition dimension for `bsp_rooms`, room plus separating wall
+/// plus some slack.
+const BSP_MIN_LEAF: i32 = 6;
+
+/// Split `area` recursively until the pieces are too small to split further.
+///
+/// Leaves are pushed into `leaves`. For every split, the index ranges of the
+/// leaves on either side of the split are pushed into `siblings`, innermost
+/// splits first.
+fn bsp_partition(
+    rng: &mut dyn RngCore,
+    area: Cube,
+    leaves: &mut Vec<Cube>,
+    siblings: &mut Vec<[Range<usize>; 2]>,
+) {
+    let [w, h, _] = area.dim();
+
+    // Split along the longer axis if possible.
+    let axis = match (w >= 2 * BSP_MIN_LEAF, h >= 2 * BSP_MIN_LEAF) {
+        (true, true) if w >= h => 0,
+        (true, true) => 1,
+        (true, false) => 0,
+        (false, true) => 1,
+        (false, false) => {
+            leaves.push(area);
+            return;
+        }
+    };
+
+    let len = area.dim()[axis] as f32;
+    let margin = BSP_MIN_LEAF as f32 / len;
+    let mut plane = [0.0; 3];
+    plane[axis] = rng.gen_range(margin..=(1.0 - margin).max(margin));
+    let [a, b] = area.split_frac(plane);
+
+    // Rounding can leave a piece too small, just stop here then.
+    if a.dim()[axis] < BSP_MIN_LEAF || b.dim()[axis] < BSP_MIN_LEAF {
+        leaves.push(area);
+        return;
+    }
+
+    let start = leaves.len();
+    bsp_partition(rng, a, leaves, siblings);
+    let mid = leaves.len();
+    bsp_partition(rng, b, leaves, siblings);
+    siblings.push([start..mid, mid..leaves.len()]);
+}
+
+/// Hide a few traps in the floor.
+fn place_traps(rng: &mut dyn RngCore, lot: &Lot, patch: &mut Patch) {
+    // Maximum number of traps per map.
+    const MAX_TRAPS: usize = 3;
+
+    let z = lot.volume.floor().min()[2];
+
+    // Floor must be regular stone, don't turn stairwell shafts into traps.
+    let mut spots = patch
+        .terrain
+        .iter()
+        .filter(|(p, v)| p[2] == z && v.is_none())
+        .map(|(&p, _)| v3(p))
+        .filter(|p| {
+            matches!(
+                patch.terrain.get(&a3(p.below())),
+                None | Some(&Some(Block::Stone))
+            )
+        })
+        .collect::<Vec<_>>();
+    spots.shuffle(rng);
+
+    for p in spots.into_iter().take(rng.gen_range(0..=MAX_TRAPS)) {
+        let kind = *[TrapKind::Damage, TrapKind::Confusion, TrapKind::Teleport]
+            .choose(rng)
+            .unwrap();
+        patch.set_voxel(p.below(), Some(Block::HiddenTrap(kind)));
+    }
+}
+
+/// Spawn creatures and items in open spots.
+fn populate(rng: &mut dyn RngCore, lot: &Lot, patch: &mut Patch) {
+    let mut spawn_posns = patch
         .terrain
         .keys()
         .filter(|&[x, y, z]| {
-            ret.terrain.get(&[*x, *y, *z]) == Some(&None)
-                && ret.terrain.get(&[x + 1, *y, *z]) == Some(&None)
-                && ret.terrain.get(&[*x, y + 1, *z]) == Some(&None)
-                && ret.terrain.get(&[x + 1, y + 1, *z]) == Some(&None)
+            patch.terrain.get(&[*x, *y, *z]) == Some(&None)
+                && patch.terrain.get(&[x + 1, *y, *z]) == Some(&None)
+                && patch.terrain.get(&[*x, y + 1, *z]) == Some(&None)
+                && patch.terrain.get(&[x + 1, y + 1, *z]) == Some(&None)
         })
         .copied()
         .map(v3)
@@ -729,6 +1141,7 @@ pub fn rooms_and_corridors(
         // Deduplicate by collecting into IndexSet.
         .collect::<IndexSet<Location>>()
         .into_iter()
+        .filter(|p| !patch.spawns.contains_key(p))
         .collect::<Vec<Location>>();
     spawn_posns.shuffle(rng);
 
@@ -739,7 +1152,7 @@ pub fn rooms_and_corridors(
         let Some(mob) = random_monster(rng, depth) else {
             break;
         };
-        ret.spawns.insert(pos, mob);
+        patch.spawns.insert(pos, mob);
     }
 
     for _ in 0..10 {
@@ -747,15 +1160,118 @@ pub fn rooms_and_corridors(
         let Some(item) = random_item(rng, depth) else {
             break;
         };
-        ret.spawns.insert(pos, item);
+        patch.spawns.insert(pos, item);
     }
+}
 
-    Ok(ret)
+/// Floor cell that can be walked through.
+fn is_open(patch: &Patch, p: Location) -> bool {
+    matches!(
+        patch.terrain.get(&a3(p)),
+        Some(&None) | Some(&Some(Block::Door))
+    )
+}
+
+fn reachable_cells(patch: &Patch, start: Location) -> HashSet<Location> {
+    util::bfs(
+        |&p: &Location| {
+            p.ns_4().filter(|&q| is_open(patch, q)).collect::<Vec<_>>()
+        },
+        [start],
+    )
+    .map(|(p, _)| p)
+    .collect()
+}
+
+/// Open cells on the same floor as `start` that can't be walked to from
+/// `start`.
+fn unreachable_cells(patch: &Patch, start: Location) -> Vec<Location> {
+    let reached = reachable_cells(patch, start);
+    patch
+        .terrain
+        .keys()
+        .map(|&p| v3(p))
+        .filter(|&p| p.z == start.z && is_open(patch, p))
+        .filter(|p| !reached.contains(p))
+        .collect()
+}
+
+/// Dig tunnels from the region reachable from `start` to every other open
+/// region it can get to.
+///
+/// Tunnels can go through room doorways, but never through fixed walls, exit
+/// cells or the outer walls of the lot.
+fn connect_regions(
+    patch: &mut Patch,
+    lot: &Lot,
+    plan: &HashMap<Location, Designation>,
+    start: Location,
+) {
+    let floor = lot.volume.floor();
+    let [max_x, max_y, _] = floor.max();
+
+    let can_dig = |patch: &Patch, p: Location| {
+        floor.contains(p)
+            && p.x < max_x - 1
+            && p.y < max_y - 1
+            && !matches!(
+                plan.get(&p),
+                Some(&Designation::Exit) | Some(&Designation::Fixed)
+            )
+            && matches!(
+                patch.terrain.get(&a3(p)),
+                None | Some(&Some(Block::Stone))
+            )
+            // Don't break into stairwells.
+            && patch.terrain.get(&a3(p.above())) != Some(&None)
+    };
+
+    loop {
+        let reached = reachable_cells(patch, start);
+
+        // Sort for determinism, set iteration order is arbitrary.
+        let mut edge: Vec<Location> = reached.iter().copied().collect();
+        edge.sort_by_key(|p| a3(*p));
+        let mut edge: std::collections::VecDeque<Location> = edge.into();
+
+        // Breadth-first search out of the reached region until an unreached
+        // open cell is found.
+        let mut parent: HashMap<Location, Location> = HashMap::default();
+        let mut target = None;
+        'search: while let Some(p) = edge.pop_front() {
+            for q in p.ns_4() {
+                if reached.contains(&q) || parent.contains_key(&q) {
+                    continue;
+                }
+                if is_open(patch, q) {
+                    parent.insert(q, p);
+                    target = Some(q);
+                    break 'search;
+                }
+                if can_dig(patch, q) {
+                    parent.insert(q, p);
+                    edge.push_back(q);
+                }
+            }
+        }
+
+        // Nothing left that can be connected.
+        let Some(target) = target else { break };
+
+        let mut p = parent[&target];
+        while !reached.contains(&p) {
+            patch.set_voxel(p, None);
+            p = parent[&p];
+        }
+    }
 }
 
 fn random_monster(rng: &mut dyn RngCore, depth: u32) -> Option<Pod> {
+    // Generation is part of the key so the set is rebuilt if data is
+    // reloaded.
     #[memoize]
     fn monster_set(
+        _generation: usize,
         depth: u32,
     ) -> Arc<Vec<(&'static _String, &'static Monster)>> {
         Arc::new(
@@ -767,18 +1283,66 @@ fn random_monster(rng: &mut dyn RngCore, depth: u32) -> Option<Pod> {
         )
     }
 
-    let set = monster_set(depth);
-    let Ok((name, data)) = set.choose_weighted(rng, |(_, a)| a.spawn_weight())
+    let set = monster_set(data_generation(), depth);
+    let (name, data) = rng.weighted_choice(&set, |(_, a)| a.spawn_weight())?;
+
+    Some(PodObject::new(name.to_string(), (*data).into()).into())
+}
+
+/// Pick a monster that would normally show up a bit deeper than `depth`.
+fn vault_guardian(rng: &mut dyn RngCore, depth: u32) -> Option<Pod> {
+    // How many levels deeper to look for guardians.
+    const DEPTH_BONUS: u32 = 3;
+
+    let set = Data::get()
+        .bestiary
+        .iter()
+        .filter(|(_, m)| {
+            (depth + 1..=depth + DEPTH_BONUS).contains(&m.min_depth())
+        })
+        .collect::<Vec<_>>();
+    let Some((name, data)) =
+        rng.weighted_choice(&set, |(_, a)| a.spawn_weight())
     else {
-        return None;
+        // Nothing from just below, settle for anything that can show up
+        // there.
+        return random_monster(rng, depth + DEPTH_BONUS);
     };
 
     Some(PodObject::new(name.to_string(), (*data).into()).into())
 }
 
+/// Find the item that opens a lock.
+fn vault_key(lock: Lock) -> Option<Pod> {
+    let (name, data) = Data::get()
+        .armory
+        .iter()
+        .find(|(_, a)| a.lock == Some(lock))?;
+
+    Some(PodObject::new(name.to_string(), data.into()).into())
+}
+
+/// Pick one of the rare items.
+fn vault_loot(rng: &mut dyn RngCore) -> Option<Pod> {
+    // Items at most this likely to spawn count as rare.
+    const MAX_WEIGHT: f64 = 1.0 / 30.0;
+
+    let set = Data::get()
+        .armory
+        .iter()
+        .filter(|(_, a)| a.spawn_weight() <= MAX_WEIGHT)
+        .collect::<Vec<_>>();
+    let (name, data) = rng.weighted_choice(&set, |(_, a)| a.spawn_weight())?;
+
+    Some(PodObject::new(name.to_string(), (*data).into()).into())
+}
+
 fn random_item(rng: &mut dyn RngCore, depth: u32) -> Option<Pod> {
     #[memoize]
-    fn item_set(depth: u32) -> Arc<Vec<(&'static _String, &'static Item)>> {
+    fn item_set(
+        _generation: usize,
+        depth: u32,
+    ) -> Arc<Vec<(&'static _String, &'static Item)>> {
         Arc::new(
             Data::get()
                 .armory
@@ -788,11 +1352,304 @@ fn random_item(rng: &mut dyn RngCore, depth: u32) -> Option<Pod> {
         )
     }
 
-    let set = item_set(depth);
-    let Ok((name, data)) = set.choose_weighted(rng, |(_, a)| a.spawn_weight())
-    else {
-        return None;
-    };
+    let set = item_set(data_generation(), depth);
+    let (name, data) = rng.weighted_choice(&set, |(_, a)| a.spawn_weight())?;
 
     Some(PodObject::new(name.to_string(), (*data).into()).into())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Platform-independent hash of generated terrain.
+    fn terrain_hash(patch: &Patch) -> u64 {
+        use rand::RngCore;
+
+        patch.terrain.iter().fold(0, |h, (p, v)| {
+            let c = v.map_or('_', char::from);
+            util::srng(&(h, p[0], p[1], p[2], c)).next_u64()
+        })
+    }
+
+    #[test]
+    fn mapgen_is_deterministic() {
+        crate::register_data_from("../data").unwrap();
+
+        // If these fail after a deliberate change to map generation, update
+        // the hashes. Otherwise something has changed the order in which the
+        // generator consumes randomness and existing seeds will produce
+        // different maps.
+        //
+        // Spawns are not hashed since they depend on the gamedata.
+        let cases: [(u64, u8, f32, u64); 5] = [
+            (1, 0, 0.0, 7078251150446455322),
+            (2, EAST | SOUTH, 0.0, 3739514397918361742),
+            (3, NORTH | WEST, 0.0, 17164256514077585925),
+            (4, NORTH | EAST | SOUTH | WEST, 0.0, 7920941429475550376),
+            (5, EAST, 0.5, 10314998460511178097),
+        ];
+
+        for (seed, sides, caviness, hash) in cases {
+            let lot = Lot {
+                sides,
+                ..Default::default()
+            };
+            let mut rng = util::srng(&seed);
+            let patch =
+                rooms_and_corridors(&mut rng, &lot, 0.1, 0.1, 0.1, caviness)
+                    .unwrap();
+            assert_eq!(
+                terrain_hash(&patch),
+                hash,
+                "seed {seed} sides {sides:04b} changed"
+            );
+        }
+
+        let bsp_cases: [(u64, u8, u64); 4] = [
+            (1, 0, 13069184828483727270),
+            (2, EAST | SOUTH, 14979089249334849844),
+            (3, NORTH | WEST, 731967669732025429),
+            (4, NORTH | EAST | SOUTH | WEST, 4151389164204640535),
+        ];
+
+        for (seed, sides, hash) in bsp_cases {
+            let lot = Lot {
+                sides,
+                ..Default::default()
+            };
+            let mut rng = util::srng(&seed);
+            let patch = bsp_rooms(&mut rng, &lot).unwrap();
+            assert_eq!(
+                terrain_hash(&patch),
+                hash,
+                "bsp seed {seed} sides {sides:04b} changed"
+            );
+        }
+    }
+
+    #[test]
+    fn cave_pockets_get_connected() {
+        crate::register_data_from("../data").unwrap();
+
+        // This seed used to leave a few cells of cave cut off from the rest
+        // of the map.
+        let lot = Lot {
+            sides: EAST | SOUTH,
+            ..Default::default()
+        };
+        let mut rng = util::srng(&2);
+        let patch =
+            rooms_and_corridors(&mut rng, &lot, 0.3, 0.1, 0.1, 0.5).unwrap();
+
+        let start = lot.exit(1).unwrap();
+        assert!(unreachable_cells(&patch, start).is_empty());
+    }
+
+    #[test]
+    fn lot_exits_on_lot_edges() {
+        let volume = Level::level_at([2, -3, -1]);
+        let lot =
+            Lot::new(volume, NORTH | EAST | SOUTH | WEST, None, None).unwrap();
+        let min = v3(volume.min());
+        let max = v3(volume.max()) - ivec3(1, 1, 1);
+
+        let exits: Vec<Location> = (0..4).filter_map(|i| lot.exit(i)).collect();
+        assert_eq!(exits.len(), 4);
+        for e in &exits {
+            assert!(volume.contains(*e), "exit {e} outside lot");
+        }
+        assert_eq!(exits[0].y, min.y);
+        assert_eq!(exits[1].x, max.x);
+        assert_eq!(exits[2].y, max.y);
+        assert_eq!(exits[3].x, min.x);
+    }
+
+    #[test]
+    fn locked_vaults_have_keys() {
+        use crate::data::PodKind;
+
+        crate::register_data_from("../data").unwrap();
+
+        let lot = Lot::default();
+        let start = lot.up.unwrap() + ivec3(0, 1, -1);
+
+        let mut locked = 0;
+        for seed in 0..32 {
+            let mut rng = util::srng(&seed);
+            let patch = rooms_and_corridors(&mut rng, &lot, 0.1, 0.1, 0.1, 0.0)
+                .unwrap();
+
+            let Some(lock) = patch.terrain.values().find_map(|v| match v {
+                Some(Block::LockedDoor(lock)) => Some(*lock),
+                _ => None,
+            }) else {
+                continue;
+            };
+
+            let keys = patch
+                .spawns
+                .iter()
+                .filter(|(_, pod)| {
+                    pod.into_iter().any(|((obj,), _)| {
+                        matches!(obj.kind, PodKind::Item(item)
+                            if item.lock == Some(lock))
+                    })
+                })
+                .map(|(&p, _)| p)
+                .collect::<Vec<_>>();
+            assert_eq!(keys.len(), 1, "seed {seed}: no key for vault");
+            assert!(
+                reachable_cells(&patch, start).contains(&keys[0]),
+                "seed {seed}: key is out of reach"
+            );
+            locked += 1;
+        }
+
+        assert!(locked > 0);
+    }
+
+    #[test]
+    fn vaults_have_one_door() {
+        crate::register_data_from("../data").unwrap();
+
+        let lot = Lot::default();
+        let start = lot.up.unwrap() + ivec3(0, 1, -1);
+
+        let mut vaults = 0;
+        let mut facings = HashSet::default();
+        for seed in 0..32 {
+            let mut rng = util::srng(&seed);
+            let patch = rooms_and_corridors(&mut rng, &lot, 0.1, 0.1, 0.1, 0.0)
+                .unwrap();
+
+            // Vault doors are the ones set in solid walls.
+            let stone = |p: Location| {
+                patch.terrain.get(&a3(p)) == Some(&Some(Block::Stone))
+            };
+            for door in patch
+                .terrain
+                .iter()
+                .filter(|(_, v)| {
+                    matches!(v, Some(Block::Door | Block::LockedDoor(_)))
+                })
+                .map(|(&p, _)| v3(p))
+                .filter(|&p| {
+                    (stone(p + ivec3(1, 0, 0)) && stone(p - ivec3(1, 0, 0)))
+                        || (stone(p + ivec3(0, 1, 0))
+                            && stone(p - ivec3(0, 1, 0)))
+                })
+            {
+                let walk = |start: Location| {
+                    util::bfs(
+                        |&p: &Location| {
+                            p.ns_4()
+                                .filter(|&q| q != door && is_open(&patch, q))
+                                .collect::<Vec<_>>()
+                        },
+                        [start],
+                    )
+                    .map(|(p, _)| p)
+                    .collect::<HashSet<_>>()
+                };
+
+                let outside = walk(start);
+                let entry = door
+                    .ns_4()
+                    .filter(|&p| is_open(&patch, p) && !outside.contains(&p))
+                    .collect::<Vec<_>>();
+                assert_eq!(entry.len(), 1, "seed {seed}: vault is not sealed");
+
+                facings.insert(entry[0] - door);
+
+                let inside = walk(entry[0]);
+                assert!(inside.is_disjoint(&outside));
+                assert!(
+                    patch.spawns.keys().any(|p| inside.contains(p)),
+                    "seed {seed}: empty vault"
+                );
+                vaults += 1;
+            }
+        }
+
+        assert!(vaults > 0);
+        // Vaults get placed in random orientations.
+        assert!(facings.len() > 1, "Vaults always face the same way");
+    }
+
+    #[test]
+    fn west_only_connection() {
+        crate::register_data_from("../data").unwrap();
+
+        let volume = Level::level_at([0, 0, -1]);
+        let lot = Lot::new(volume, WEST, None, None).unwrap();
+        let exit = lot.exit(3).unwrap();
+        assert_eq!(exit.x, volume.min()[0]);
+        assert!((0..4).filter_map(|i| lot.exit(i)).count() == 1);
+
+        for seed in 0..8 {
+            let mut rng = util::srng(&seed);
+            let patch = rooms_and_corridors(&mut rng, &lot, 0.1, 0.1, 0.1, 0.0)
+                .unwrap();
+            assert_eq!(patch.terrain.get(&a3(exit)), Some(&None));
+
+            let open = patch
+                .terrain
+                .iter()
+                .filter(|(p, v)| p[2] == exit.z && v.is_none())
+                .count();
+            let reached = reachable_cells(&patch, exit);
+            // Most of the map should be reachable from the sole exit.
+            assert!(reached.len() * 2 > open, "seed {seed}: isolated exit");
+        }
+    }
+
+    #[test]
+    fn bsp_rooms_are_connected() {
+        crate::register_data_from("../data").unwrap();
+
+        for seed in 0..16u8 {
+            let lot = Lot {
+                sides: seed & 0b1111,
+                ..Default::default()
+            };
+            let mut rng = util::srng(&seed);
+            let patch = bsp_rooms(&mut rng, &lot).unwrap();
+
+            let start = lot.up.unwrap() + ivec3(0, 1, -1);
+            assert!(
+                unreachable_cells(&patch, start).is_empty(),
+                "seed {seed}: disconnected map"
+            );
+
+            let reached = reachable_cells(&patch, start);
+            for exit in (0..4).filter_map(|i| lot.exit(i)) {
+                assert!(reached.contains(&exit), "seed {seed}: exit {exit}");
+            }
+            let down = lot.down.unwrap() + ivec3(0, -1, 1);
+            assert!(reached.contains(&down), "seed {seed}: downstairs");
+        }
+    }
+
+    #[test]
+    fn bsp_room_count_scales_with_area() {
+        let count = |seed: u32, w: i32, h: i32| {
+            let mut leaves = Vec::new();
+            bsp_partition(
+                &mut util::srng(&seed),
+                Cube::new([0, 0, 0], [w, h, 1]),
+                &mut leaves,
+                &mut Vec::new(),
+            );
+            leaves.len()
+        };
+
+        for seed in 0..8 {
+            assert_eq!(count(seed, BSP_MIN_LEAF, BSP_MIN_LEAF), 1);
+            let small = count(seed, 16, 12);
+            let medium = count(seed, 32, 24);
+            let large = count(seed, 64, 48);
+            assert!(1 < small && small < medium && medium < large);
+        }
+    }
+}
@@ -1,13 +1,21 @@
-use std::ops::Deref;
+use std::{fmt, ops::Deref};
 
 use glam::{ivec2, ivec3, IVec2};
 use rand::distributions::{Distribution, Standard};
 use serde::{Deserialize, Serialize};
 use util::{Cloud, HashMap, IndexMap, LazyRes, Neighbors2D, StrExt};
 
-use crate::{Block, Coordinates, Cube, Environs, Location, Pod, Voxel};
+use crate::{
+    world::snap_stairwell_position, Block, Coordinates, Cube, Environs,
+    Location, Pod, TrapKind, Voxel, LEVEL_DEPTH,
+};
 
 /// Text map for 2D world part.
+///
+/// Legend tokens are usually single characters. If the legend has longer
+/// tokens, every map cell is as wide as the longest token. In a wide map,
+/// cells that aren't legend tokens use their first character as the terrain
+/// glyph, so terrain is written doubled like `##..##`.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct SectorMap {
@@ -15,7 +23,7 @@ pub struct SectorMap {
     pub map: String,
     // Use LazyRes for values since clutches can't be parsed until gamedata has
     // been loaded.
-    pub legend: IndexMap<char, LazyRes<Pod>>,
+    pub legend: IndexMap<String, LazyRes<Pod>>,
 }
 
 impl SectorMap {
@@ -66,8 +74,6 @@ impl SectorMap {
 
         for y in volume.min()[1]..volume.max()[1] {
             for x in volume.min()[0]..volume.max()[0] {
-                use Block::*;
-
                 let p = ivec3(x, y, z);
 
                 if let Some(c) = map_spawns.get(&p) {
@@ -75,22 +81,7 @@ impl SectorMap {
                     continue;
                 }
 
-                let c = match p.tile(r) {
-                    crate::Tile::Surface(loc, _) if loc == p.above() => '<',
-                    crate::Tile::Surface(loc, _) if loc == p.below() => '>',
-                    crate::Tile::Surface(_, Water) => '~',
-                    crate::Tile::Surface(_, Magma) => '&',
-                    crate::Tile::Surface(_, Grass) => ',',
-                    crate::Tile::Surface(_, SplatteredRock) => '§',
-                    crate::Tile::Surface(_, _) => '.',
-                    crate::Tile::Wall(Door) => '+',
-                    crate::Tile::Wall(Glass) => '|',
-                    crate::Tile::Wall(Altar) => '=',
-                    crate::Tile::Wall(Rubble) => '%',
-                    crate::Tile::Wall(_) => '#',
-                    crate::Tile::Void => '_',
-                };
-                map.push(c);
+                map.push(terrain_cell(r, p));
             }
             map.push('\n');
         }
@@ -101,7 +92,7 @@ impl SectorMap {
             map,
             legend: rev_legend
                 .into_iter()
-                .map(|(k, v)| (v, LazyRes::new(k)))
+                .map(|(k, v)| (v.to_string(), LazyRes::new(k)))
                 .collect(),
         }
     }
@@ -129,39 +120,163 @@ impl SectorMap {
         }
     }
 
-    pub fn entrances(&self) -> impl Iterator<Item = IVec2> + '_ {
+    /// Sealed treasure room with a single door on the south side.
+    pub fn vault() -> Self {
+        SectorMap {
+            map: "\
+#######
+#.....#
+#.....#
+#.....#
+###+###"
+                .to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Width of map cells in characters.
+    pub fn cell_width(&self) -> usize {
+        self.legend
+            .keys()
+            .map(|k| k.chars().count())
+            .max()
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    /// Iterate the tokens of non-blank map cells.
+    pub fn cells(&self) -> impl Iterator<Item = (IVec2, &str)> + '_ {
+        let w = self.cell_width();
+        let x_skip = self.map.indentation();
+
         self.map
-            .char_grid()
+            .lines()
+            .skip_while(|a| a.trim().is_empty())
+            .enumerate()
+            .flat_map(move |(y, line)| {
+                let chars: Vec<(usize, char)> =
+                    line.char_indices().skip(x_skip).collect();
+                chars
+                    .chunks(w)
+                    .enumerate()
+                    .filter_map(|(x, cell)| {
+                        let (a, _) = cell[0];
+                        let (b, c) = cell[cell.len() - 1];
+                        let token = line[a..b + c.len_utf8()].trim_end();
+                        (!token.is_empty())
+                            .then_some((ivec2(x as i32, y as i32), token))
+                    })
+                    .collect::<Vec<_>>()
+            })
+    }
+
+    /// Terrain glyph of a map cell token.
+    ///
+    /// Legend spawns stand on regular ground.
+    pub fn glyph(&self, token: &str) -> char {
+        if self.legend.contains_key(token) {
+            '.'
+        } else {
+            token.chars().next().unwrap_or(' ')
+        }
+    }
+
+    fn glyph_grid(&self) -> impl Iterator<Item = (IVec2, char)> + '_ {
+        self.cells().map(|(p, a)| (p, self.glyph(a)))
+    }
+
+    pub fn entrances(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.glyph_grid()
             .filter_map(|(p, c)| (c == '@').then_some(p))
     }
 
     pub fn find_downstairs(&self) -> Option<IVec2> {
-        self.map
-            .char_grid()
-            .find_map(|(p, c)| (c == '>').then_some(p))
+        self.glyph_grid().find_map(|(p, c)| (c == '>').then_some(p))
     }
 
     pub fn find_upstairs(&self) -> Option<IVec2> {
-        self.map
-            .char_grid()
-            .find_map(|(p, c)| (c == '<').then_some(p))
+        self.glyph_grid().find_map(|(p, c)| (c == '<').then_some(p))
     }
 
     pub fn dim(&self) -> IVec2 {
-        self.map
-            .char_grid()
+        self.cells()
             .map(|(p, _)| p)
             .fold(IVec2::ZERO, |a, x| a.max(x + ivec2(1, 1)))
     }
 
+    /// Return a copy of the map rotated clockwise by `quarters` quarter
+    /// turns.
+    ///
+    /// Stairs, entrances and legend spawns are all read from the map grid,
+    /// so they move along with the rotation.
+    pub fn rotated(&self, quarters: i32) -> Self {
+        let mut ret = self.clone();
+        for _ in 0..quarters.rem_euclid(4) {
+            let h = ret.dim().y;
+            ret = ret.transformed(|p| ivec2(h - 1 - p.y, p.x));
+        }
+        ret
+    }
+
+    /// Return a copy of the map flipped along the given axis, 0 for x and 1
+    /// for y.
+    pub fn mirrored(&self, axis: usize) -> Self {
+        let dim = self.dim();
+        match axis {
+            0 => self.transformed(|p| ivec2(dim.x - 1 - p.x, p.y)),
+            1 => self.transformed(|p| ivec2(p.x, dim.y - 1 - p.y)),
+            _ => panic!("SectorMap::mirrored: bad axis {axis}"),
+        }
+    }
+
+    /// Return one of the 8 rotated and mirrored variants of the map.
+    ///
+    /// Orientation 0 is the original map, orientations 4 to 7 are the
+    /// mirrored versions of 0 to 3.
+    pub fn oriented(&self, orientation: usize) -> Self {
+        let ret = self.rotated((orientation % 4) as i32);
+        if orientation % 8 >= 4 {
+            ret.mirrored(0)
+        } else {
+            ret
+        }
+    }
+
+    fn transformed(&self, f: impl Fn(IVec2) -> IVec2) -> Self {
+        let w = self.cell_width();
+        let cells: HashMap<IVec2, &str> =
+            self.cells().map(|(p, c)| (f(p), c)).collect();
+        let dim = cells
+            .keys()
+            .fold(IVec2::ZERO, |a, &p| a.max(p + ivec2(1, 1)));
+
+        let mut map = String::new();
+        for y in 0..dim.y {
+            let mut line = String::new();
+            for x in 0..dim.x {
+                let token = cells.get(&ivec2(x, y)).copied().unwrap_or("");
+                line.push_str(&format!("{token:w$}"));
+            }
+            map.push_str(line.trim_end());
+            if y < dim.y - 1 {
+                map.push('\n');
+            }
+        }
+
+        SectorMap {
+            map,
+            ..self.clone()
+        }
+    }
+
     pub fn spawns(
         &self,
         origin: Location,
     ) -> anyhow::Result<Vec<(Location, Pod)>> {
         let mut ret = Vec::default();
 
-        for (p, c) in self.map.char_grid() {
-            if let Some(name) = self.legend.get(&c) {
+        for (p, c) in self.cells() {
+            if let Some(name) = self.legend.get(c) {
                 ret.push((origin + p.extend(0), name.deref().clone()));
             }
         }
@@ -172,7 +287,7 @@ impl SectorMap {
     pub fn border_and_inside(
         &self,
     ) -> (IndexMap<IVec2, char>, IndexMap<IVec2, char>) {
-        let map: IndexMap<IVec2, char> = self.map.char_grid().collect();
+        let map: IndexMap<IVec2, char> = self.glyph_grid().collect();
 
         let mut border = IndexMap::default();
         let mut inside = IndexMap::default();
@@ -188,19 +303,61 @@ impl SectorMap {
         (border, inside)
     }
 
+    /// Check the map for problems that would otherwise only show up when
+    /// the map gets generated.
+    ///
+    /// Returns every issue found.
+    pub fn validate(&self) -> Result<(), Vec<Issue>> {
+        let mut ret = Vec::new();
+
+        let mut scratch = Cloud::default();
+        for (p, c) in self.cells() {
+            // Entrances and spawns become floor.
+            if c == "@" || self.legend.contains_key(c) {
+                continue;
+            }
+            if Location::ZERO
+                .apply_char_terrain(&mut scratch, self.glyph(c))
+                .is_err()
+            {
+                ret.push(Issue::UnknownCell(p, c.to_owned()));
+            }
+        }
+
+        // Stairwells alternate between two grids on consecutive levels and
+        // the map can go on either one.
+        let on_stairwell_grid = |p: IVec2| {
+            [0, -LEVEL_DEPTH].into_iter().any(|z| {
+                let loc = p.extend(z);
+                snap_stairwell_position(loc) == loc
+            })
+        };
+        if let Some(p) = self.find_upstairs() {
+            if !on_stairwell_grid(p) {
+                ret.push(Issue::MisalignedUpstairs(p));
+            }
+        }
+        if let Some(p) = self.find_downstairs() {
+            if !on_stairwell_grid(p) {
+                ret.push(Issue::MisalignedDownstairs(p));
+            }
+        }
+
+        if ret.is_empty() {
+            Ok(())
+        } else {
+            Err(ret)
+        }
+    }
+
     pub fn terrain(&self, origin: Location) -> anyhow::Result<Cloud<3, Voxel>> {
         let mut ret = Cloud::default();
 
-        for (p, c) in self.map.char_grid() {
+        for (p, c) in self.glyph_grid() {
             let p = origin + p.extend(0);
 
-            let c = match c {
-                // Rewrite entrace cells.
-                '@' => '.',
-                // Assume all spawns spawn on top of regular ground
-                c if self.legend.contains_key(&c) => '.',
-                c => c,
-            };
+            // Rewrite entrace cells.
+            let c = if c == '@' { '.' } else { c };
 
             p.apply_char_terrain(&mut ret, c)?;
         }
@@ -209,6 +366,74 @@ impl SectorMap {
     }
 }
 
+/// Text map character for the terrain at a location.
+pub fn terrain_cell(r: &impl Environs, p: Location) -> char {
+    use Block::*;
+
+    match p.tile(r) {
+        crate::Tile::Surface(loc, _) if loc == p.above() => '<',
+        crate::Tile::Surface(loc, _) if loc == p.below() => '>',
+        crate::Tile::Surface(_, Water) => '~',
+        crate::Tile::Surface(_, Magma) => '&',
+        crate::Tile::Surface(_, Grass) => ',',
+        crate::Tile::Surface(_, SplatteredRock) => '§',
+        // Hidden traps stay hidden, found ones use the map chars that place
+        // them.
+        crate::Tile::Surface(_, Trap(TrapKind::Damage)) => '^',
+        crate::Tile::Surface(_, Trap(TrapKind::Confusion)) => ':',
+        crate::Tile::Surface(_, Trap(TrapKind::Teleport)) => '*',
+        crate::Tile::Surface(_, _) => '.',
+        crate::Tile::Wall(Door) => '+',
+        crate::Tile::Wall(b @ LockedDoor(_)) => char::from(b),
+        crate::Tile::Wall(SecretDoor) => 'S',
+        crate::Tile::Wall(Glass) => '|',
+        crate::Tile::Wall(Window) => '"',
+        crate::Tile::Wall(Altar) => '=',
+        crate::Tile::Wall(Rubble) => '%',
+        crate::Tile::Wall(_) => '#',
+        crate::Tile::Void => '_',
+    }
+}
+
+/// Problem found by `SectorMap::validate`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Issue {
+    /// Cell token that isn't terrain or in the legend.
+    UnknownCell(IVec2, String),
+    /// Upstairs that don't line up with the stairwell grid.
+    MisalignedUpstairs(IVec2),
+    /// Downstairs that don't line up with the stairwell grid.
+    MisalignedDownstairs(IVec2),
+}
+
+impl Issue {
+    /// Map cell the issue is at.
+    pub fn pos(&self) -> IVec2 {
+        match self {
+            Issue::UnknownCell(p, _)
+            | Issue::MisalignedUpstairs(p)
+            | Issue::MisalignedDownstairs(p) => *p,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [x, y] = self.pos().to_array();
+        match self {
+            Issue::UnknownCell(_, c) => {
+                write!(f, "({x}, {y}): {c:?} is not terrain or in legend")
+            }
+            Issue::MisalignedUpstairs(_) => {
+                write!(f, "({x}, {y}): upstairs off the stairwell grid")
+            }
+            Issue::MisalignedDownstairs(_) => {
+                write!(f, "({x}, {y}): downstairs off the stairwell grid")
+            }
+        }
+    }
+}
+
 impl Distribution<SectorMap> for Standard {
     fn sample<R: rand::prelude::Rng + ?Sized>(&self, rng: &mut R) -> SectorMap {
         // Generate regular empty rectangular rooms.
@@ -245,3 +470,158 @@ impl Distribution<SectorMap> for Standard {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn orientation() {
+        let room = SectorMap {
+            map: "\
+#<..
+####"
+                .to_owned(),
+            ..Default::default()
+        };
+
+        let r1 = room.rotated(1);
+        assert_eq!(r1.map, "##\n#<\n#.\n#.");
+        assert_eq!(r1.dim(), ivec2(2, 4));
+        assert_eq!(r1.find_upstairs(), Some(ivec2(1, 1)));
+
+        assert_eq!(room.rotated(4), room);
+        assert_eq!(room.rotated(-1), room.rotated(3));
+        assert_eq!(room.rotated(2).find_upstairs(), Some(ivec2(2, 1)));
+
+        let m = room.mirrored(0);
+        assert_eq!(m.map, "..<#\n####");
+        assert_eq!(m.mirrored(0), room);
+        assert_eq!(room.mirrored(1).mirrored(1), room);
+
+        // All eight orientations are distinct for an asymmetric map.
+        let all: Vec<String> = (0..8).map(|i| room.oriented(i).map).collect();
+        for (i, a) in all.iter().enumerate() {
+            assert!(all[i + 1..].iter().all(|b| a != b));
+        }
+    }
+
+    #[test]
+    fn wide_legend() {
+        crate::register_data_from("../data").unwrap();
+
+        let room = SectorMap {
+            map: "\
+########
+##g1..##
+##..gK+
+########"
+                .to_owned(),
+            legend: [("g1", "goblin"), ("gK", "goblin king")]
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), LazyRes::new(v.to_owned())))
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(room.cell_width(), 2);
+        assert_eq!(room.dim(), ivec2(4, 4));
+
+        let goblin: Pod = idm::from_str("goblin").unwrap();
+        let king: Pod = idm::from_str("goblin king").unwrap();
+        assert_eq!(
+            room.spawns(Location::ZERO).unwrap(),
+            vec![(ivec3(1, 1, 0), goblin), (ivec3(2, 2, 0), king)]
+        );
+
+        let terrain = room.terrain(Location::ZERO).unwrap();
+        assert_eq!(terrain.voxel(ivec3(0, 1, 0)), Some(Block::Stone));
+        assert_eq!(terrain.voxel(ivec3(1, 1, 0)), None);
+        assert_eq!(terrain.voxel(ivec3(2, 1, 0)), None);
+        // Short final cell is still read as its glyph.
+        assert_eq!(terrain.voxel(ivec3(3, 2, 0)), Some(Block::Door));
+
+        // Rotation keeps the cells intact.
+        let r1 = room.rotated(1);
+        assert_eq!(r1.map, "########\n##..g1##\n##gK..##\n##+ ####");
+        assert_eq!(r1.rotated(3), room.rotated(4));
+    }
+
+    #[test]
+    fn validation() {
+        let room = SectorMap {
+            map: "\
+#####
+#.b.#
+#.x.#
+#####"
+                .to_owned(),
+            legend: [("b".to_owned(), LazyRes::new("bat".to_owned()))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let issues = room.validate().unwrap_err();
+        assert_eq!(issues, vec![Issue::UnknownCell(ivec2(2, 2), "x".into())]);
+        assert_eq!(
+            issues[0].to_string(),
+            "(2, 2): \"x\" is not terrain or in legend"
+        );
+
+        // Stairs that don't fit any stairwell grid.
+        let room = SectorMap {
+            map: "\
+####
+#.>#
+####"
+                .to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            room.validate(),
+            Err(vec![Issue::MisalignedDownstairs(ivec2(2, 1))])
+        );
+    }
+
+    #[test]
+    fn traps() {
+        let room = SectorMap {
+            map: "\
+#####
+#^:*#
+#####"
+                .to_owned(),
+            ..Default::default()
+        };
+        let mut terrain = room.terrain(Location::ZERO).unwrap();
+
+        for (x, kind, c) in [
+            (1, TrapKind::Damage, '^'),
+            (2, TrapKind::Confusion, ':'),
+            (3, TrapKind::Teleport, '*'),
+        ] {
+            let p = ivec3(x, 1, 0);
+            assert_eq!(terrain.voxel(p.below()), Some(Block::HiddenTrap(kind)));
+            // Hidden traps look like floor until found.
+            assert_eq!(terrain_cell(&terrain, p), '.');
+            terrain.set_voxel(p.below(), Some(Block::Trap(kind)));
+            assert_eq!(terrain_cell(&terrain, p), c);
+        }
+    }
+
+    #[test]
+    fn locked_doors() {
+        let room = SectorMap {
+            map: "\
+#####
+#123#
+#...#"
+                .to_owned(),
+            ..Default::default()
+        };
+        let terrain = room.terrain(Location::ZERO).unwrap();
+
+        for (x, c) in [(1, '1'), (2, '2'), (3, '3')] {
+            assert_eq!(terrain_cell(&terrain, ivec3(x, 1, 0)), c);
+        }
+    }
+}
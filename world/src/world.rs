@@ -11,9 +11,9 @@ use util::{
 
 use crate::{
     data::Region, waypoints::WaypointPair, Block, Coordinates, Cube, Environs,
-    Location, Lot, MapGenerator, Patch, Pod, Rect, Scenario, Terrain, Voxel,
-    Zone, DOWN, LEVEL_BASIS, LEVEL_DEPTH, NORTH, SECTOR_HEIGHT, SECTOR_WIDTH,
-    UP, WEST,
+    Location, Lot, MapGenerator, Objective, Patch, Pod, Rect, Scenario,
+    Terrain, Voxel, Zone, DOWN, LEVEL_BASIS, LEVEL_DEPTH, NORTH, SECTOR_HEIGHT,
+    SECTOR_WIDTH, UP, WEST,
 };
 
 /// Non-cached world data that goes in a save file.
@@ -122,6 +122,8 @@ pub struct Segment {
     pub connected_west: bool,
     pub connected_down: Option<Location>,
     pub generator: Box<dyn MapGenerator>,
+    /// Name given to the level in the scenario.
+    pub name: Option<String>,
 }
 
 impl<T: MapGenerator + 'static> From<T> for Segment {
@@ -131,6 +133,7 @@ impl<T: MapGenerator + 'static> From<T> for Segment {
             connected_west: false,
             connected_down: None,
             generator: Box::new(generator),
+            name: None,
         }
     }
 }
@@ -197,6 +200,17 @@ fn unfold(
 
                 pos
             }
+            Named(name, reg) => {
+                // Wrap every primitive the inner region unfolds into with
+                // the name.
+                let start = out.len();
+                pos = insert(pos, reg, out);
+                for (_, r) in out.iter_mut().skip(start) {
+                    *r = Named(name.clone(), Box::new(r.clone()));
+                }
+
+                pos
+            }
             primitive => {
                 out.insert(pos, primitive.clone());
                 pos + ivec3(0, 0, -1)
@@ -243,6 +257,9 @@ fn unfold(
                 // Build branch.
                 unfold(seed, pos + dir.extend(0), out, existing_shafts, slice)?;
             }
+            Named(_, a) if matches!(**a, Branch(_)) => {
+                bail!("Branches can't be named, name their contents instead");
+            }
             repeat_or_primitive => {
                 pos = insert(pos, repeat_or_primitive, out);
             }
@@ -275,7 +292,14 @@ fn build_skeleton(
         let mut branch = IndexMap::default();
         unfold(seed, p.extend(0), &mut branch, &mut existing_shafts, slice)?;
 
-        for (&p, r) in &branch {
+        for (&p, mut r) in &branch {
+            // Outermost name wins if names are nested.
+            let mut name = None;
+            while let Named(n, inner) = r {
+                name = name.or_else(|| Some(n.0.clone()));
+                r = inner;
+            }
+
             let s = Level::level_at(p);
             let origin = Location::from(s.min());
 
@@ -314,6 +338,7 @@ fn build_skeleton(
                     connected_west,
                     connected_down,
                     generator: Box::new(*gen),
+                    name,
                 },
                 Site(map) | Hall(map) => {
                     for p in map.entrances() {
@@ -332,10 +357,11 @@ fn build_skeleton(
                         generator: Box::new(Patch::from_sector_map(
                             origin, map,
                         )?),
+                        name,
                     }
                 }
 
-                Branch(_) | Repeat(_, _) => {
+                Branch(_) | Repeat(_, _) | Named(_, _) => {
                     panic!("unfold left structural regions in output")
                 }
             };
@@ -399,6 +425,25 @@ impl World {
         spawns
     }
 
+    /// Number of boss monsters placed in the whole world, including the
+    /// parts that haven't been generated yet.
+    pub fn num_bosses(&self) -> usize {
+        self.skeleton
+            .values()
+            .map(|s| s.generator.num_bosses())
+            .sum()
+    }
+
+    /// Objectives that need to be completed to win the scenario.
+    pub fn objectives(&self) -> &[Objective] {
+        &self.inner.scenario.objectives
+    }
+
+    /// Name the scenario gives to the level containing the location.
+    pub fn region_name(&self, loc: Location) -> Option<&str> {
+        self.skeleton.get(&Level::level_from(loc))?.name.as_deref()
+    }
+
     pub fn levels(&self) -> impl Iterator<Item = &Level> + '_ {
         self.skeleton.keys()
     }
@@ -428,7 +473,11 @@ impl World {
         };
 
         let lot = self.construct_lot(s);
-        self.inner.spawn_history.push(*s);
+        // Sectors get regenerated when a saved game is loaded, don't record
+        // them a second time.
+        if !spawns_done {
+            self.inner.spawn_history.push(*s);
+        }
 
         log::info!(
             "Generating {s:?}{}",
@@ -608,3 +657,47 @@ fn snap_to_chessboard3(parity: i32, bounds: &Rect, pos: IVec2) -> IVec2 {
     // Finally wrap it to the bounds of the chessboard and we're done.
     bounds.mod_proj(adjusted_pos)
 }
+
+#[cfg(test)]
+mod test {
+    use util::_String;
+
+    use super::*;
+    use crate::{data::GenericSector, SectorMap};
+
+    #[test]
+    fn named_regions() {
+        crate::register_data_from("../data").unwrap();
+
+        let scenario = Scenario {
+            map: "a".into(),
+            legend: vec![(
+                ('a',),
+                vec![
+                    // Needs an altar for a waypoint.
+                    Region::Site(SectorMap {
+                        map: "@=".into(),
+                        ..Default::default()
+                    }),
+                    Region::Named(
+                        _String("The Sewers".into()),
+                        Box::new(Region::Repeat(
+                            2,
+                            Box::new(Region::Generate(GenericSector::Dungeon)),
+                        )),
+                    ),
+                ],
+            )],
+            ..Default::default()
+        };
+
+        let world = World::new(Silo::new("rand0m"), scenario).unwrap();
+        let surface = Location::from(Level::level_at([0, 0, 0]).min());
+
+        assert_eq!(world.region_name(surface), None);
+        for z in [-1, -2] {
+            let loc = Location::from(Level::level_at([0, 0, z]).center());
+            assert_eq!(world.region_name(loc), Some("The Sewers"));
+        }
+    }
+}